@@ -2,8 +2,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use futures_util::ready;
 use pin_project::pin_project;
-use super::{Combine, Filter, FilterBase, Internal, Tuple};
+use super::{Combine, Describe, Filter, FilterBase, Internal, Tuple};
 use crate::generic::CombinedTuples;
 use crate::reject::CombineRejection;
 #[derive(Clone, Copy, Debug)]
@@ -24,7 +25,12 @@ where
     type Error = <U::Error as CombineRejection<T::Error>>::One;
     type Future = AndFuture<T, U>;
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        AndFuture {
+            state: State::First(self.first.filter(Internal), self.second.clone()),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::and(self.first.describe(Internal), self.second.describe(Internal))
     }
 }
 #[allow(missing_debug_implementations)]
@@ -51,7 +57,7 @@ where
         <U::Error as CombineRejection<T::Error>>::One,
     >;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        self.project().state.poll(cx)
     }
 }
 impl<T, TE, U, E> Future for State<T, TE, U>
@@ -67,6 +73,21 @@ where
         <U::Error as CombineRejection<E>>::One,
     >;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        loop {
+            match self.as_mut().project() {
+                StateProj::First(first, second) => {
+                    let ex1 = ready!(first.poll(cx))?;
+                    let fut2 = second.filter(Internal);
+                    self.set(State::Second(Some(ex1), fut2));
+                }
+                StateProj::Second(ex1, second) => {
+                    let ex2 = ready!(second.poll(cx))?;
+                    let ex3 = ex1.take().unwrap().combine(ex2);
+                    self.set(State::Done);
+                    return Poll::Ready(Ok(ex3));
+                }
+                StateProj::Done => panic!("polled after complete"),
+            }
+        }
     }
 }
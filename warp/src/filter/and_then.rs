@@ -1,9 +1,9 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use futures_util::{TryFuture};
+use futures_util::{ready, TryFuture};
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Func, Internal};
+use super::{Describe, Filter, FilterBase, Func, Internal};
 use crate::reject::CombineRejection;
 #[derive(Clone, Copy, Debug)]
 pub struct AndThen<T, F> {
@@ -22,7 +22,12 @@ where
     type Future = AndThenFuture<T, F>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        AndThenFuture {
+            state: State::First(self.filter.filter(Internal), self.callback.clone()),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
 #[allow(missing_debug_implementations)]
@@ -61,7 +66,7 @@ where
         <<F::Output as TryFuture>::Error as CombineRejection<T::Error>>::One,
     >;
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        self.project().state.poll(cx)
     }
 }
 impl<T, F> Future for State<T, F>
@@ -76,6 +81,23 @@ where
         <<F::Output as TryFuture>::Error as CombineRejection<T::Error>>::One,
     >;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        loop {
+            match self.as_mut().project() {
+                StateProj::First(first, second) => {
+                    let ex1 = ready!(first.try_poll(cx))?;
+                    let fut2 = second.call(ex1);
+                    self.set(State::Second(fut2));
+                }
+                StateProj::Second(second) => {
+                    let ex2 = match ready!(second.try_poll(cx)) {
+                        Ok(item) => Ok((item,)),
+                        Err(err) => Err(From::from(err)),
+                    };
+                    self.set(State::Done);
+                    return Poll::Ready(ex2);
+                }
+                StateProj::Done => panic!("polled after complete"),
+            }
+        }
     }
 }
@@ -3,7 +3,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
-use super::{Filter, FilterBase, Internal, Tuple};
+use futures_util::TryFutureExt;
+
+use super::{Describe, Filter, FilterBase, Internal, Tuple};
 use crate::reject::Rejection;
 
 
@@ -39,17 +41,22 @@ impl<T: Tuple + Send> BoxedFilter<T> {
         F: Filter<Extract = T> + Send + Sync + 'static,
         F::Error: Into<Rejection>,
     {
-        loop {}
+        let filter = Arc::new(BoxingFilter {
+            filter: filter.map_err(super::Internal, Into::into),
+        });
+        BoxedFilter { filter }
     }
 }
 impl<T: Tuple> Clone for BoxedFilter<T> {
     fn clone(&self) -> BoxedFilter<T> {
-        loop {}
+        BoxedFilter {
+            filter: self.filter.clone(),
+        }
     }
 }
 impl<T: Tuple> fmt::Debug for BoxedFilter<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        loop {}
+        f.debug_struct("BoxedFilter").finish()
     }
 }
 fn _assert_send() {
@@ -60,7 +67,10 @@ impl<T: Tuple + Send> FilterBase for BoxedFilter<T> {
     type Error = Rejection;
     type Future = Pin<Box<dyn Future<Output = Result<T, Rejection>> + Send>>;
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        self.filter.filter(Internal)
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
 struct BoxingFilter<F> {
@@ -77,6 +87,9 @@ where
         Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>,
     >;
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        Box::pin(self.filter.filter(Internal).into_future())
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
@@ -0,0 +1,125 @@
+use super::{Filter, FilterBase, Internal};
+use crate::filters::openapi::Meta;
+
+/// The metadata a filter tree exposes about the requests it matches.
+///
+/// `And` combinators produce a sequence of requirements, while `Or`
+/// produces alternatives, so flattening the tree yields one list of
+/// requirements per route.
+#[derive(Clone, Debug)]
+pub enum Describe {
+    Empty,
+    Leaf(Meta),
+    And(Box<Describe>, Box<Describe>),
+    Or(Box<Describe>, Box<Describe>),
+}
+
+impl Describe {
+    pub(crate) fn and(first: Describe, second: Describe) -> Describe {
+        match (first, second) {
+            (Describe::Empty, other) | (other, Describe::Empty) => other,
+            (first, second) => Describe::And(Box::new(first), Box::new(second)),
+        }
+    }
+
+    pub(crate) fn or(first: Describe, second: Describe) -> Describe {
+        Describe::Or(Box::new(first), Box::new(second))
+    }
+
+    /// Flatten the tree into every sequence of metadata a request could
+    /// match, in the order `or` would try them.
+    pub(crate) fn flatten(&self) -> Vec<Vec<Meta>> {
+        match self {
+            Describe::Empty => vec![Vec::new()],
+            Describe::Leaf(meta) => vec![vec![meta.clone()]],
+            Describe::And(first, second) => {
+                let seconds = second.flatten();
+                first
+                    .flatten()
+                    .into_iter()
+                    .flat_map(|head| {
+                        seconds.iter().map(move |tail| {
+                            let mut seq = head.clone();
+                            seq.extend(tail.iter().cloned());
+                            seq
+                        })
+                    })
+                    .collect()
+            }
+            Describe::Or(first, second) => {
+                let mut alts = first.flatten();
+                alts.extend(second.flatten());
+                alts
+            }
+        }
+    }
+}
+
+/// Attach metadata to a filter built from closures, which otherwise has
+/// nothing to describe itself with.
+pub(crate) fn described<F, D>(filter: F, describe: D) -> Described<F, D>
+where
+    F: Filter,
+    D: Fn() -> Meta,
+{
+    Described { filter, describe }
+}
+
+#[derive(Clone, Copy)]
+#[allow(missing_debug_implementations)]
+pub(crate) struct Described<F, D> {
+    filter: F,
+    describe: D,
+}
+
+impl<F, D> FilterBase for Described<F, D>
+where
+    F: Filter,
+    D: Fn() -> Meta,
+{
+    type Extract = F::Extract;
+    type Error = F::Error;
+    type Future = F::Future;
+
+    #[inline]
+    fn filter(&self, _: Internal) -> Self::Future {
+        self.filter.filter(Internal)
+    }
+
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::Leaf((self.describe)())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Describe;
+    use crate::filters::openapi::Meta;
+
+    fn path(s: &str) -> Describe {
+        Describe::Leaf(Meta::Path(s.to_owned()))
+    }
+
+    #[test]
+    fn flatten_and_or() {
+        // path("a").and(path("b").or(path("c"))).and(path("d"))
+        let tree = Describe::and(
+            Describe::and(path("a"), Describe::or(path("b"), path("c"))),
+            path("d"),
+        );
+        let flat = tree
+            .flatten()
+            .into_iter()
+            .map(|seq| {
+                seq.into_iter()
+                    .map(|meta| match meta {
+                        Meta::Path(s) => s,
+                        other => panic!("unexpected meta: {:?}", other),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flat, vec!["a/b/d", "a/c/d"]);
+    }
+}
@@ -2,8 +2,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use futures_util::{ready, TryFuture};
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Func, Internal};
+use super::{Describe, Filter, FilterBase, Func, Internal};
 #[derive(Clone, Copy, Debug)]
 pub struct Map<T, F> {
     pub(super) filter: T,
@@ -19,7 +20,13 @@ where
     type Future = MapFuture<T, F>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        MapFuture {
+            extract: self.filter.filter(Internal),
+            callback: self.callback.clone(),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
 #[allow(missing_debug_implementations)]
@@ -37,6 +44,13 @@ where
     type Output = Result<(F::Output,), T::Error>;
     #[inline]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        let pin = self.project();
+        match ready!(pin.extract.try_poll(cx)) {
+            Ok(ex) => {
+                let ex = (pin.callback.call(ex),);
+                Poll::Ready(Ok(ex))
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
     }
 }
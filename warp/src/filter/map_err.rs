@@ -2,8 +2,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use futures_util::TryFuture;
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Internal};
+use super::{Describe, Filter, FilterBase, Internal};
 use crate::reject::IsReject;
 #[derive(Clone, Copy, Debug)]
 pub struct MapErr<T, F> {
@@ -21,7 +22,13 @@ where
     type Future = MapErrFuture<T, F>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        MapErrFuture {
+            extract: self.filter.filter(Internal),
+            callback: self.callback.clone(),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
 #[allow(missing_debug_implementations)]
@@ -39,6 +46,10 @@ where
     type Output = Result<T::Extract, E>;
     #[inline]
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        self.as_mut()
+            .project()
+            .extract
+            .try_poll(cx)
+            .map_err(|err| (self.callback)(err))
     }
 }
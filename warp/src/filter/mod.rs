@@ -1,6 +1,7 @@
 mod and;
 mod and_then;
 mod boxed;
+mod describe;
 mod map;
 mod map_err;
 mod or;
@@ -8,39 +9,50 @@ mod or_else;
 mod recover;
 pub(crate) mod service;
 mod then;
+mod timeout;
 mod unify;
 mod untuple_one;
 mod wrap;
 pub(crate) use self::and::And;
 use self::and_then::AndThen;
 pub use self::boxed::BoxedFilter;
+pub(crate) use self::describe::{described, Describe};
 pub(crate) use self::map::Map;
 pub(crate) use self::map_err::MapErr;
 pub(crate) use self::or::Or;
 use self::or_else::OrElse;
 use self::recover::Recover;
 use self::then::Then;
+pub use self::timeout::Timeout;
 use self::unify::Unify;
 use self::untuple_one::UntupleOne;
 pub use self::wrap::wrap_fn;
 pub(crate) use self::wrap::{Wrap, WrapSealed};
 pub(crate) use crate::generic::{one, Combine, Either, Func, One, Tuple};
+use crate::filters::tower::{FilterService, Forward};
 use crate::reject::{CombineRejection, IsReject, Rejection};
-use crate::route::Route;
+use crate::route::{self, Route};
 use futures_util::{future, TryFuture, TryFutureExt};
 use std::future::Future;
+use std::time::Duration;
 pub trait FilterBase {
     type Extract: Tuple;
     type Error: IsReject;
     type Future: Future<Output = Result<Self::Extract, Self::Error>> + Send;
     fn filter(&self, internal: Internal) -> Self::Future;
+    fn describe(&self, _internal: Internal) -> Describe {
+        Describe::Empty
+    }
     fn map_err<F, E>(self, _internal: Internal, fun: F) -> MapErr<Self, F>
     where
         Self: Sized,
         F: Fn(Self::Error) -> E + Clone,
         E: ::std::fmt::Debug + Send,
     {
-        loop {}
+        MapErr {
+            filter: self,
+            callback: fun,
+        }
     }
 }
 #[allow(missing_debug_implementations)]
@@ -54,7 +66,10 @@ pub trait Filter: FilterBase {
         F: Filter + Clone,
         F::Error: CombineRejection<Self::Error>,
     {
-        loop {}
+        And {
+            first: self,
+            second: other,
+        }
     }
 
     fn or<F>(self, other: F) -> Or<Self, F>
@@ -63,7 +78,10 @@ pub trait Filter: FilterBase {
         F: Filter,
         F::Error: CombineRejection<Self::Error>,
     {
-        loop {}
+        Or {
+            first: self,
+            second: other,
+        }
     }
 
     fn map<F>(self, fun: F) -> Map<Self, F>
@@ -71,7 +89,10 @@ pub trait Filter: FilterBase {
         Self: Sized,
         F: Func<Self::Extract> + Clone,
     {
-        loop {}
+        Map {
+            filter: self,
+            callback: fun,
+        }
     }
 
     fn then<F>(self, fun: F) -> Then<Self, F>
@@ -90,7 +111,10 @@ pub trait Filter: FilterBase {
         F::Output: TryFuture + Send,
         <F::Output as TryFuture>::Error: CombineRejection<Self::Error>,
     {
-        loop {}
+        AndThen {
+            filter: self,
+            callback: fun,
+        }
     }
 
     fn or_else<F>(self, fun: F) -> OrElse<Self, F>
@@ -113,12 +137,78 @@ pub trait Filter: FilterBase {
         loop {}
     }
 
+    /// Reject with a timeout if this filter doesn't finish within `duration`.
+    ///
+    /// The rejection renders as `503 Service Unavailable`, or as
+    /// `504 Gateway Timeout` when a shorter deadline sent by the client
+    /// (see [`deadline_header`](Timeout::deadline_header)) elapsed first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use warp::Filter;
+    ///
+    /// let route = warp::path("report")
+    ///     .and_then(|| async { Ok::<_, warp::Rejection>("a very slow report") })
+    ///     .timeout(Duration::from_secs(5));
+    /// ```
+    fn timeout(self, duration: Duration) -> Timeout<Self>
+    where
+        Self: Sized,
+        Self::Error: CombineRejection<Rejection>,
+    {
+        Timeout {
+            filter: self,
+            duration,
+            deadline_header: None,
+        }
+    }
+
+    /// Wrap this filter in a tower `Layer`.
+    ///
+    /// Requests reaching this filter are forwarded through the layer's
+    /// middleware as an `http::Request<hyper::Body>`, and the filter
+    /// continues from where the chain left off. See
+    /// [`FilterService`](crate::tower::FilterService) for how rejections
+    /// are handled.
+    ///
+    /// Only the filter `layer` is called on is wrapped: a filter can't see
+    /// the filters that are `and`ed after it. To wrap the remainder of a
+    /// chain, build that remainder as its own filter, call `layer` on it,
+    /// and `and` it onto the start of the chain, as below.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::Filter;
+    ///
+    /// # #[derive(Clone)]
+    /// # struct SomeTowerLayer;
+    /// # impl<S> tower_layer::Layer<S> for SomeTowerLayer {
+    /// #     type Service = S;
+    /// #     fn layer(&self, inner: S) -> S { inner }
+    /// # }
+    /// let api = warp::path!("users" / u32)
+    ///     .map(|id| format!("user #{}", id))
+    ///     .layer(SomeTowerLayer);
+    ///
+    /// let routes = warp::path("api").and(api);
+    /// ```
+    fn layer<L>(self, layer: L) -> Forward<L::Service>
+    where
+        Self: Sized,
+        L: tower_layer::Layer<FilterService<Self>>,
+    {
+        Forward::layered(self, layer)
+    }
+
     fn unify<T>(self) -> Unify<Self>
     where
         Self: Filter<Extract = (Either<T, T>,)> + Sized,
         T: Tuple,
     {
-        loop {}
+        Unify { filter: self }
     }
 
     fn untuple_one<T>(self) -> UntupleOne<Self>
@@ -134,7 +224,7 @@ pub trait Filter: FilterBase {
         Self: Sized,
         W: Wrap<Self>,
     {
-        loop {}
+        wrapper.wrap(self)
     }
 
     fn boxed(self) -> BoxedFilter<Self::Extract>
@@ -143,7 +233,7 @@ pub trait Filter: FilterBase {
         Self::Extract: Send,
         Self::Error: Into<Rejection>,
     {
-        loop {}
+        BoxedFilter::new(self)
     }
 }
 impl<T: FilterBase> Filter for T {}
@@ -159,7 +249,7 @@ where
     U::Ok: Tuple,
     U::Error: IsReject,
 {
-    loop {}
+    FilterFn { func }
 }
 pub(crate) fn filter_fn_one<F, U>(
     func: F,
@@ -189,6 +279,6 @@ where
     type Future = future::IntoFuture<U>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        route::with(|route| (self.func)(route)).into_future()
     }
 }
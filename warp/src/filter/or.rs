@@ -2,10 +2,12 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+use futures_util::{ready, TryFuture};
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Internal};
+use super::{Describe, Filter, FilterBase, Internal};
 use crate::generic::Either;
 use crate::reject::CombineRejection;
+use crate::route;
 
 type Combined<E1, E2> = <E1 as CombineRejection<E2>>::Combined;
 #[derive(Clone, Copy, Debug)]
@@ -23,7 +25,14 @@ where
     type Error = Combined<U::Error, T::Error>;
     type Future = EitherFuture<T, U>;
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        let idx = route::with(|route| route.matched_path_index());
+        EitherFuture {
+            state: State::First(self.first.filter(Internal), self.second.clone()),
+            original_path_index: PathIndex(idx),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::or(self.first.describe(Internal), self.second.describe(Internal))
     }
 }
 #[allow(missing_debug_implementations)]
@@ -43,7 +52,7 @@ enum State<T: Filter, U: Filter> {
 struct PathIndex(usize);
 impl PathIndex {
     fn reset_path(&self) {
-        loop {}
+        route::with(|route| route.reset_matched_path_index(self.0));
     }
 }
 impl<T, U> Future for EitherFuture<T, U>
@@ -57,6 +66,40 @@ where
         Combined<U::Error, T::Error>,
     >;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        loop {
+            let pin = self.as_mut().project();
+            let (err1, fut2) = match pin.state.project() {
+                StateProj::First(first, second) => match ready!(first.try_poll(cx)) {
+                    Ok(ex1) => {
+                        return Poll::Ready(Ok((Either::A(ex1),)));
+                    }
+                    Err(e) => {
+                        pin.original_path_index.reset_path();
+                        (e, second.filter(Internal))
+                    }
+                },
+                StateProj::Second(err1, second) => {
+                    let ex2 = match ready!(second.try_poll(cx)) {
+                        Ok(ex2) => Ok((Either::B(ex2),)),
+                        Err(e) => {
+                            pin.original_path_index.reset_path();
+                            let err1 = err1.take().expect("polled after complete");
+                            Err(e.combine(err1))
+                        }
+                    };
+                    self.set(EitherFuture {
+                        state: State::Done,
+                        ..*self
+                    });
+                    return Poll::Ready(ex2);
+                }
+                StateProj::Done => panic!("polled after complete"),
+            };
+
+            self.set(EitherFuture {
+                state: State::Second(Some(err1), fut2),
+                ..*self
+            });
+        }
     }
 }
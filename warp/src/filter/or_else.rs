@@ -3,7 +3,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use futures_util::{TryFuture};
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Func, Internal};
+use super::{Describe, Filter, FilterBase, Func, Internal};
 use crate::reject::IsReject;
 
 #[derive(Clone, Copy, Debug)]
@@ -25,6 +25,9 @@ where
     fn filter(&self, _: Internal) -> Self::Future {
         loop {}
     }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
+    }
 }
 #[allow(missing_debug_implementations)]
 #[pin_project]
@@ -0,0 +1,230 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::TryFuture;
+use http::header::HeaderName;
+use pin_project::pin_project;
+use tokio::time::{sleep, Sleep};
+
+use super::{Describe, Filter, FilterBase, Internal};
+use crate::reject::{self, CombineRejection, Rejection};
+use crate::route;
+
+/// A filter that rejects if its inner filter doesn't finish in time.
+///
+/// Created with [`Filter::timeout`].
+#[derive(Clone, Debug)]
+pub struct Timeout<T> {
+    pub(super) filter: T,
+    pub(super) duration: Duration,
+    pub(super) deadline_header: Option<HeaderName>,
+}
+
+impl<T> Timeout<T> {
+    /// Also honor a deadline sent by the client in the header `name`,
+    /// using whichever of the two limits is shorter.
+    ///
+    /// The header value uses the `grpc-timeout` format: up to 8 digits
+    /// followed by a unit, one of `H`, `M`, `S`, `m` (millis), `u` (micros)
+    /// or `n` (nanos), such as `250m`. Values that don't parse are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use warp::Filter;
+    ///
+    /// let route = warp::path("slow")
+    ///     .and_then(|| async { Ok::<_, warp::Rejection>("done") })
+    ///     .timeout(Duration::from_secs(30))
+    ///     .deadline_header(warp::http::header::HeaderName::from_static("grpc-timeout"));
+    /// ```
+    pub fn deadline_header(mut self, name: HeaderName) -> Self {
+        self.deadline_header = Some(name);
+        self
+    }
+}
+
+impl<T> FilterBase for Timeout<T>
+where
+    T: Filter,
+    T::Error: CombineRejection<Rejection>,
+{
+    type Extract = T::Extract;
+    type Error = <T::Error as CombineRejection<Rejection>>::One;
+    type Future = TimeoutFuture<T::Future>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let deadline = self.deadline_header.as_ref().and_then(|name| {
+            route::with(|route| {
+                route
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(parse_deadline)
+            })
+        });
+        let (duration, from_deadline) = match deadline {
+            Some(deadline) if deadline < self.duration => (deadline, true),
+            _ => (self.duration, false),
+        };
+        TimeoutFuture {
+            extract: self.filter.filter(Internal),
+            sleep: sleep(duration),
+            duration,
+            from_deadline,
+        }
+    }
+
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
+    }
+}
+
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct TimeoutFuture<F> {
+    #[pin]
+    extract: F,
+    #[pin]
+    sleep: Sleep,
+    duration: Duration,
+    from_deadline: bool,
+}
+
+impl<F> Future for TimeoutFuture<F>
+where
+    F: TryFuture,
+    F::Error: CombineRejection<Rejection>,
+{
+    type Output = Result<F::Ok, <F::Error as CombineRejection<Rejection>>::One>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let pin = self.project();
+        if let Poll::Ready(result) = pin.extract.try_poll(cx) {
+            return Poll::Ready(result.map_err(From::from));
+        }
+        match pin.sleep.poll(cx) {
+            Poll::Ready(()) => {
+                tracing::debug!("filter timed out after {:?}", pin.duration);
+                let err = reject::timed_out(*pin.duration, *pin.from_deadline);
+                Poll::Ready(Err(From::from(err)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Parse a `grpc-timeout` style value, such as `100m` or `5S`.
+fn parse_deadline(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if !value.is_ascii() || value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    let duration = match unit {
+        "H" => Duration::from_secs(amount * 60 * 60),
+        "M" => Duration::from_secs(amount * 60),
+        "S" => Duration::from_secs(amount),
+        "m" => Duration::from_millis(amount),
+        "u" => Duration::from_micros(amount),
+        "n" => Duration::from_nanos(amount),
+        _ => return None,
+    };
+    Some(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_deadline;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::time::Duration;
+
+    use futures_util::future::{self, poll_fn};
+    use http::header::HeaderName;
+
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::reject::{self, IsReject, Rejection, TimedOut};
+    use crate::route::{self, Route};
+    use crate::Request;
+
+    /// Never finishes, as a stuck handler would.
+    #[derive(Clone)]
+    struct Stuck;
+
+    impl FilterBase for Stuck {
+        type Extract = ();
+        type Error = Rejection;
+        type Future = future::Pending<Result<(), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            future::pending()
+        }
+    }
+
+    async fn run<F>(filter: &F, req: Request) -> Result<F::Extract, F::Error>
+    where
+        F: FilterBase,
+    {
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx))).await
+    }
+
+    #[tokio::test]
+    async fn slow_filters_rejected() {
+        let filter = Stuck.timeout(Duration::from_millis(10));
+        let err = run(&filter, Request::default()).await.unwrap_err();
+        let timed_out = err.find::<TimedOut>().expect("timed out");
+        assert_eq!(timed_out.duration(), Duration::from_millis(10));
+        assert!(!timed_out.is_deadline());
+        assert_eq!(err.status(), 503);
+
+        let filter = Stuck
+            .timeout(Duration::from_secs(30))
+            .deadline_header(HeaderName::from_static("grpc-timeout"));
+        let mut req = Request::default();
+        req.headers_mut().insert("grpc-timeout", "10m".parse().unwrap());
+        let err = run(&filter, req).await.unwrap_err();
+        assert!(err.find::<TimedOut>().expect("timed out").is_deadline());
+        assert_eq!(err.status(), 504);
+    }
+
+    #[tokio::test]
+    async fn fast_filters_pass() {
+        let filter = crate::any().timeout(Duration::from_millis(10));
+        assert!(run(&filter, Request::default()).await.is_ok());
+    }
+
+    #[test]
+    fn timeouts_rendered() {
+        let res = reject::timed_out(Duration::from_secs(1), false).into_response();
+        assert_eq!(res.status(), 503);
+        let res = reject::timed_out(Duration::from_secs(1), true).into_response();
+        assert_eq!(res.status(), 504);
+    }
+
+    #[test]
+    fn parse_grpc_timeout() {
+        assert_eq!(parse_deadline("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_deadline("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_deadline("5S"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_deadline("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_deadline("7u"), Some(Duration::from_micros(7)));
+        assert_eq!(parse_deadline("99999999n"), Some(Duration::from_nanos(99_999_999)));
+
+        assert_eq!(parse_deadline("100"), None);
+        assert_eq!(parse_deadline("m"), None);
+        assert_eq!(parse_deadline("-1S"), None);
+        assert_eq!(parse_deadline("1.5S"), None);
+        assert_eq!(parse_deadline("123456789S"), None);
+        assert_eq!(parse_deadline("10x"), None);
+    }
+}
@@ -1,9 +1,9 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use futures_util::{TryFuture};
+use futures_util::{ready, TryFuture};
 use pin_project::pin_project;
-use super::{Either, Filter, FilterBase, Internal, Tuple};
+use super::{Describe, Either, Filter, FilterBase, Internal, Tuple};
 #[derive(Clone, Copy, Debug)]
 pub struct Unify<F> {
     pub(super) filter: F,
@@ -18,7 +18,12 @@ where
     type Future = UnifyFuture<F::Future>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        UnifyFuture {
+            inner: self.filter.filter(Internal),
+        }
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
     }
 }
 #[allow(missing_debug_implementations)]
@@ -34,6 +39,8 @@ where
     type Output = Result<T, F::Error>;
     #[inline]
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        Poll::Ready(match ready!(self.project().inner.try_poll(cx))? {
+            (Either::A(x),) | (Either::B(x),) => Ok(x),
+        })
     }
 }
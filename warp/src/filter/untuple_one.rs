@@ -3,7 +3,7 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use pin_project::pin_project;
-use super::{Filter, FilterBase, Internal, Tuple};
+use super::{Describe, Filter, FilterBase, Internal, Tuple};
 #[derive(Clone, Copy, Debug)]
 pub struct UntupleOne<F> {
     pub(super) filter: F,
@@ -20,6 +20,9 @@ where
     fn filter(&self, _: Internal) -> Self::Future {
         loop {}
     }
+    fn describe(&self, _: Internal) -> Describe {
+        self.filter.describe(Internal)
+    }
 }
 #[allow(missing_debug_implementations)]
 #[pin_project]
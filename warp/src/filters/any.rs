@@ -54,7 +54,7 @@ impl FilterBase for Any {
     type Future = AnyFut;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        AnyFut
     }
 }
 #[allow(missing_debug_implementations)]
@@ -63,6 +63,6 @@ impl Future for AnyFut {
     type Output = Result<(), Infallible>;
     #[inline]
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        loop {}
+        Poll::Ready(Ok(()))
     }
 }
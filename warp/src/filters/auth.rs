@@ -0,0 +1,276 @@
+//! Authentication Filters
+//!
+//! Filters extracting credentials from the `Authorization` header, with the
+//! [`Basic`](basic) and [`Bearer`](bearer) schemes.
+//!
+//! Requests without credentials, or whose credentials fail verification,
+//! are rejected with an [`Unauthorized`], whose default response carries
+//! the `WWW-Authenticate` challenge telling clients how to authenticate.
+//!
+//! With the `jwt` feature, [`jwt`](jwt::jwt) also validates bearer tokens
+//! as JSON Web Tokens.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//!
+//! #[derive(Debug)]
+//! struct User(String);
+//!
+//! let admin = warp::path("admin")
+//!     .and(warp::auth::basic_with("admin", |creds: warp::auth::Credentials| async move {
+//!         if creds.username() == "admin" && creds.password() == "hunter2" {
+//!             Some(User(creds.username().to_owned()))
+//!         } else {
+//!             None
+//!         }
+//!     }))
+//!     .map(|user: User| format!("hello {:?}", user));
+//! ```
+
+#[cfg(feature = "jwt")]
+pub mod jwt;
+
+use std::fmt;
+use std::future::Future;
+use std::iter;
+
+use futures_util::future;
+use headers::authorization::{Authorization, Basic};
+use headers::Header;
+use http::header::HeaderValue;
+
+use super::header;
+use crate::filter::Filter;
+use crate::reject::{self, Rejection};
+
+pub use crate::reject::Unauthorized;
+#[cfg(feature = "jwt")]
+pub use self::jwt::jwt;
+
+const BASIC: &str = "Basic";
+const BEARER: &str = "Bearer";
+
+/// Extract [`Credentials`] sent with the `Basic` scheme.
+///
+/// Rejects with an [`Unauthorized`] challenging for `realm` if they are
+/// missing or malformed.
+pub fn basic(
+    realm: &'static str,
+) -> impl Filter<Extract = (Credentials,), Error = Rejection> + Copy {
+    header::optional::<String>("authorization").and_then(move |value: Option<String>| {
+        let credentials = value.as_deref().and_then(Credentials::decode).ok_or_else(|| {
+            tracing::debug!("basic credentials missing or malformed");
+            reject::unauthorized(BASIC, realm, None)
+        });
+        future::ready(credentials)
+    })
+}
+
+/// Extract `Basic` credentials, verified with `verify`.
+///
+/// `verify` returns what the credentials identify, such as a user, or
+/// `None` to reject with an [`Unauthorized`] challenging for `realm`.
+pub fn basic_with<F, Fut, T>(
+    realm: &'static str,
+    verify: F,
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    F: Fn(Credentials) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+    T: Send,
+{
+    basic(realm).and_then(move |credentials| {
+        let verified = verify(credentials);
+        async move {
+            verified.await.ok_or_else(|| {
+                tracing::debug!("basic credentials rejected by verifier");
+                reject::unauthorized(BASIC, realm, None)
+            })
+        }
+    })
+}
+
+/// Extract a [`Token`] sent with the `Bearer` scheme.
+///
+/// Rejects with an [`Unauthorized`] challenging for `realm` if it is
+/// missing, or with `error="invalid_request"` if it is malformed.
+pub fn bearer(realm: &'static str) -> impl Filter<Extract = (Token,), Error = Rejection> + Copy {
+    header::optional::<String>("authorization").and_then(move |value: Option<String>| {
+        let token = match value.as_deref().and_then(|value| strip_scheme(value, BEARER)) {
+            Some(token) => Token::decode(token).ok_or_else(|| {
+                tracing::debug!("bearer token malformed");
+                reject::unauthorized(
+                    BEARER,
+                    realm,
+                    Some(("invalid_request", "The access token is malformed")),
+                )
+            }),
+            None => {
+                tracing::debug!("bearer token missing");
+                Err(reject::unauthorized(BEARER, realm, None))
+            }
+        };
+        future::ready(token)
+    })
+}
+
+/// Extract a `Bearer` token, verified with `verify`.
+///
+/// `verify` returns what the token grants access as, or `None` to reject
+/// with an [`Unauthorized`] with `error="invalid_token"`.
+pub fn bearer_with<F, Fut, T>(
+    realm: &'static str,
+    verify: F,
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    F: Fn(Token) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Option<T>> + Send,
+    T: Send,
+{
+    bearer(realm).and_then(move |token| {
+        let verified = verify(token);
+        async move {
+            verified.await.ok_or_else(|| {
+                tracing::debug!("bearer token rejected by verifier");
+                reject::unauthorized(
+                    BEARER,
+                    realm,
+                    Some(("invalid_token", "The access token is invalid")),
+                )
+            })
+        }
+    })
+}
+
+/// Strip an authentication `scheme`, matched case-insensitively.
+fn strip_scheme<'a>(value: &'a str, scheme: &str) -> Option<&'a str> {
+    let (name, rest) = value.split_once(' ')?;
+    if name.eq_ignore_ascii_case(scheme) {
+        Some(rest.trim_start_matches(' '))
+    } else {
+        None
+    }
+}
+
+/// A username and password sent with the `Basic` scheme.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    fn decode(value: &str) -> Option<Credentials> {
+        let encoded = strip_scheme(value, BASIC)?;
+        let value = HeaderValue::from_str(&format!("{} {}", BASIC, encoded)).ok()?;
+        let basic = Authorization::<Basic>::decode(&mut iter::once(&value)).ok()?;
+        Some(Credentials {
+            username: basic.username().to_owned(),
+            password: basic.password().to_owned(),
+        })
+    }
+
+    /// The username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// The password.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish()
+    }
+}
+
+/// A token sent with the `Bearer` scheme.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token(String);
+
+impl Token {
+    /// Check the `b64token` syntax of RFC 6750.
+    fn decode(token: &str) -> Option<Token> {
+        let chars = token.trim_end_matches('=');
+        let valid = !chars.is_empty()
+            && chars
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~+/".contains(&b));
+        if valid {
+            Some(Token(token.to_owned()))
+        } else {
+            None
+        }
+    }
+
+    /// The token as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Token").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Credentials, Token};
+    use crate::reject::IsReject;
+
+    #[test]
+    fn basic_credentials_decoded() {
+        // "Aladdin:open sesame"
+        let creds = Credentials::decode("basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==").unwrap();
+        assert_eq!(creds.username(), "Aladdin");
+        assert_eq!(creds.password(), "open sesame");
+
+        assert_eq!(Credentials::decode("Bearer QWxhZGRpbjpvcGVuIHNlc2FtZQ=="), None);
+        assert_eq!(Credentials::decode("Basic !!!"), None);
+    }
+
+    #[test]
+    fn bearer_tokens_checked() {
+        assert_eq!(Token::decode("mF_9.B5f-4.1JqM").unwrap().as_str(), "mF_9.B5f-4.1JqM");
+        assert!(Token::decode("YWJj==").is_some());
+        assert!(Token::decode("a b").is_none());
+        assert!(Token::decode("==").is_none());
+    }
+
+    #[test]
+    fn challenges() {
+        let res = crate::reject::unauthorized("Basic", "admin", None).into_response();
+        assert_eq!(res.status(), 401);
+        assert_eq!(
+            res.headers()["www-authenticate"],
+            r#"Basic realm="admin", charset="UTF-8""#
+        );
+
+        let rejection = crate::reject::unauthorized(
+            "Bearer",
+            "api \"v2\"",
+            Some(("invalid_token", "The access token is invalid")),
+        );
+        assert_eq!(rejection.status(), 401);
+        assert_eq!(
+            rejection.into_response().headers()["www-authenticate"],
+            r#"Bearer realm="api \"v2\"", error="invalid_token", error_description="The access token is invalid""#
+        );
+
+        let rejection = crate::reject::unauthorized(
+            "Bearer",
+            "api",
+            Some(("invalid_request", "The access token is malformed")),
+        );
+        assert_eq!(rejection.status(), 400);
+    }
+}
@@ -0,0 +1,489 @@
+//! JSON Web Tokens
+//!
+//! The [`jwt`](jwt()) filter validates `Bearer` tokens signed with
+//! `HS256`, `RS256` or `ES256`, against a [`KeySet`] loaded from a JWKS
+//! document or built from individual keys, and extracts their claims.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use serde::Deserialize;
+//! use warp::auth::jwt::{KeySet, Validation};
+//! use warp::Filter;
+//!
+//! #[derive(Deserialize)]
+//! struct Claims {
+//!     sub: String,
+//! }
+//!
+//! let keys = KeySet::from_jwks_file("jwks.json").expect("jwks.json");
+//! let validation = Validation::new(keys)
+//!     .issuer("https://auth.example.com")
+//!     .audience("api");
+//!
+//! let route = warp::path("me")
+//!     .and(warp::auth::jwt::<Claims>(validation))
+//!     .map(|claims: Claims| format!("hello {}", claims.sub));
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey};
+use serde::de::DeserializeOwned;
+
+use crate::filter::Filter;
+use crate::reject::{self, Rejection, Unauthorized};
+
+/// Extract the claims of a `Bearer` JWT, checked with `validation`.
+///
+/// Requests without a token are rejected with an [`Unauthorized`], and
+/// those with a token failing validation with an [`InvalidJwt`] telling
+/// why, both rendered as a `401 Unauthorized`.
+pub fn jwt<C>(validation: Validation) -> impl Filter<Extract = (C,), Error = Rejection> + Clone
+where
+    C: DeserializeOwned + Send + 'static,
+{
+    let validation = Arc::new(validation);
+    super::bearer(validation.realm).and_then(move |token: super::Token| {
+        let claims = validation.decode(token.as_str()).map_err(|reason| {
+            tracing::debug!("jwt rejected: {}", reason.description());
+            reject::known(InvalidJwt {
+                reason,
+                realm: validation.realm,
+            })
+        });
+        future::ready(claims)
+    })
+}
+
+/// The keys JWTs may be signed with.
+#[derive(Clone, Default)]
+pub struct KeySet {
+    keys: Vec<Key>,
+}
+
+#[derive(Clone)]
+struct Key {
+    id: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+impl KeySet {
+    /// Create an empty key set.
+    pub fn new() -> KeySet {
+        KeySet::default()
+    }
+
+    /// Load the keys of a JWKS document.
+    ///
+    /// Keys for algorithms other than `HS256`, `RS256` and `ES256` are
+    /// skipped.
+    pub fn from_jwks(jwks: &str) -> Result<KeySet, KeyError> {
+        let set: JwkSet = serde_json::from_str(jwks).map_err(KeyError::json)?;
+        let mut keys = KeySet::new();
+        for jwk in &set.keys {
+            let algorithm = match (jwk.common.algorithm, &jwk.algorithm) {
+                (Some(algorithm), _) => algorithm,
+                (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+                (None, AlgorithmParameters::EllipticCurve(ref ec))
+                    if ec.curve == EllipticCurve::P256 =>
+                {
+                    Algorithm::ES256
+                }
+                (None, AlgorithmParameters::OctetKey(_)) => Algorithm::HS256,
+                (None, _) => {
+                    tracing::warn!("jwks key {:?} skipped: unsupported", jwk.common.key_id);
+                    continue;
+                }
+            };
+            if !supported(algorithm) {
+                tracing::warn!(
+                    "jwks key {:?} skipped: unsupported algorithm {:?}",
+                    jwk.common.key_id,
+                    algorithm
+                );
+                continue;
+            }
+            keys.keys.push(Key {
+                id: jwk.common.key_id.clone(),
+                algorithm,
+                key: DecodingKey::from_jwk(jwk).map_err(KeyError::key)?,
+            });
+        }
+        Ok(keys)
+    }
+
+    /// Load the keys of a JWKS file.
+    pub fn from_jwks_file(path: impl AsRef<Path>) -> Result<KeySet, KeyError> {
+        let jwks = std::fs::read_to_string(path).map_err(KeyError::io)?;
+        KeySet::from_jwks(&jwks)
+    }
+
+    /// Add an `HS256` secret, with an optional key ID.
+    pub fn hs256(mut self, id: Option<&str>, secret: &[u8]) -> KeySet {
+        self.push(id, Algorithm::HS256, DecodingKey::from_secret(secret));
+        self
+    }
+
+    /// Add an `RS256` public key in PEM format, with an optional key ID.
+    pub fn rs256_pem(mut self, id: Option<&str>, pem: &[u8]) -> Result<KeySet, KeyError> {
+        let key = DecodingKey::from_rsa_pem(pem).map_err(KeyError::key)?;
+        self.push(id, Algorithm::RS256, key);
+        Ok(self)
+    }
+
+    /// Add an `ES256` public key in PEM format, with an optional key ID.
+    pub fn es256_pem(mut self, id: Option<&str>, pem: &[u8]) -> Result<KeySet, KeyError> {
+        let key = DecodingKey::from_ec_pem(pem).map_err(KeyError::key)?;
+        self.push(id, Algorithm::ES256, key);
+        Ok(self)
+    }
+
+    fn push(&mut self, id: Option<&str>, algorithm: Algorithm, key: DecodingKey) {
+        self.keys.push(Key {
+            id: id.map(str::to_owned),
+            algorithm,
+            key,
+        });
+    }
+
+    /// The number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the set has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.keys.iter().map(|key| (&key.id, key.algorithm)))
+            .finish()
+    }
+}
+
+fn supported(algorithm: Algorithm) -> bool {
+    matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::RS256 | Algorithm::ES256
+    )
+}
+
+/// How the [`jwt`](jwt()) filter validates tokens.
+///
+/// The signature, `exp` and `nbf` are always checked, and `exp` is
+/// required. `iss` and `aud` are only checked if configured.
+#[derive(Clone, Debug)]
+pub struct Validation {
+    keys: KeySet,
+    issuers: Vec<String>,
+    audiences: Vec<String>,
+    leeway: Duration,
+    realm: &'static str,
+}
+
+impl Validation {
+    /// Validate tokens signed with one of `keys`.
+    pub fn new(keys: KeySet) -> Validation {
+        Validation {
+            keys,
+            issuers: Vec::new(),
+            audiences: Vec::new(),
+            leeway: Duration::from_secs(60),
+            realm: "jwt",
+        }
+    }
+
+    /// Require the `iss` claim to be `issuer`. Can be called multiple times
+    /// to accept several issuers.
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuers.push(issuer.into());
+        self
+    }
+
+    /// Require the `aud` claim to include `audience`. Can be called
+    /// multiple times to accept several audiences.
+    pub fn audience(mut self, audience: impl Into<String>) -> Self {
+        self.audiences.push(audience.into());
+        self
+    }
+
+    /// Allow `exp` and `nbf` to be off by `leeway`, for clock skew.
+    /// Defaults to 60 seconds.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Set the realm of the `WWW-Authenticate` challenge. Defaults to
+    /// `jwt`.
+    pub fn realm(mut self, realm: &'static str) -> Self {
+        self.realm = realm;
+        self
+    }
+
+    fn decode<C: DeserializeOwned>(&self, token: &str) -> Result<C, Reason> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| Reason::Malformed)?;
+        if !supported(header.alg) {
+            return Err(Reason::UnsupportedAlgorithm);
+        }
+
+        let mut validation = jsonwebtoken::Validation::new(header.alg);
+        validation.leeway = self.leeway.as_secs();
+        validation.validate_nbf = true;
+        if !self.issuers.is_empty() {
+            validation.set_issuer(&self.issuers);
+        }
+        if !self.audiences.is_empty() {
+            validation.set_audience(&self.audiences);
+        }
+
+        let mut candidates = self
+            .keys
+            .keys
+            .iter()
+            .filter(|key| key.algorithm == header.alg)
+            .filter(|key| header.kid.is_none() || key.id == header.kid)
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(Reason::UnknownKey);
+        }
+        let mut reason = Reason::BadSignature;
+        for key in candidates {
+            match jsonwebtoken::decode::<C>(token, &key.key, &validation) {
+                Ok(data) => return Ok(data.claims),
+                Err(err) => {
+                    reason = Reason::from(err.kind());
+                    if reason != Reason::BadSignature {
+                        break;
+                    }
+                }
+            }
+        }
+        Err(reason)
+    }
+}
+
+/// A JWT failed [validation](Validation).
+#[derive(Debug)]
+pub struct InvalidJwt {
+    reason: Reason,
+    realm: &'static str,
+}
+
+/// Why a JWT failed validation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Reason {
+    /// The token isn't a well-formed JWT.
+    Malformed,
+    /// The token is signed with an algorithm other than `HS256`, `RS256`
+    /// or `ES256`.
+    UnsupportedAlgorithm,
+    /// No key in the set matches the token's `kid` and algorithm.
+    UnknownKey,
+    /// The signature doesn't match any candidate key.
+    BadSignature,
+    /// The `exp` claim is in the past.
+    Expired,
+    /// The `nbf` claim is in the future.
+    NotYetValid,
+    /// The `iss` claim isn't an accepted issuer.
+    InvalidIssuer,
+    /// The `aud` claim doesn't include an accepted audience.
+    InvalidAudience,
+    /// A required claim is missing, or the claims don't deserialize.
+    InvalidClaims,
+}
+
+impl Reason {
+    fn description(self) -> &'static str {
+        match self {
+            Reason::Malformed => "The token is malformed",
+            Reason::UnsupportedAlgorithm => "The token algorithm is not supported",
+            Reason::UnknownKey => "The token signing key is unknown",
+            Reason::BadSignature => "The token signature is invalid",
+            Reason::Expired => "The token has expired",
+            Reason::NotYetValid => "The token is not valid yet",
+            Reason::InvalidIssuer => "The token issuer is not accepted",
+            Reason::InvalidAudience => "The token audience is not accepted",
+            Reason::InvalidClaims => "The token claims are invalid",
+        }
+    }
+}
+
+impl From<&ErrorKind> for Reason {
+    fn from(kind: &ErrorKind) -> Reason {
+        match *kind {
+            ErrorKind::InvalidSignature => Reason::BadSignature,
+            ErrorKind::ExpiredSignature => Reason::Expired,
+            ErrorKind::ImmatureSignature => Reason::NotYetValid,
+            ErrorKind::InvalidIssuer => Reason::InvalidIssuer,
+            ErrorKind::InvalidAudience => Reason::InvalidAudience,
+            ErrorKind::MissingRequiredClaim(_) | ErrorKind::Json(_) => Reason::InvalidClaims,
+            _ => Reason::Malformed,
+        }
+    }
+}
+
+impl InvalidJwt {
+    /// Why the token failed validation.
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    pub(crate) fn challenge(&self) -> String {
+        let error = ("invalid_token", self.reason.description());
+        Unauthorized::new("Bearer", self.realm, Some(error)).challenge()
+    }
+}
+
+impl fmt::Display for InvalidJwt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason.description())
+    }
+}
+
+impl StdError for InvalidJwt {}
+
+/// An error loading a [`KeySet`].
+#[derive(Debug)]
+pub struct KeyError {
+    source: Box<dyn StdError + Send + Sync>,
+}
+
+impl KeyError {
+    fn io(err: io::Error) -> KeyError {
+        KeyError {
+            source: Box::new(err),
+        }
+    }
+
+    fn json(err: serde_json::Error) -> KeyError {
+        KeyError {
+            source: Box::new(err),
+        }
+    }
+
+    fn key(err: jsonwebtoken::errors::Error) -> KeyError {
+        KeyError {
+            source: Box::new(err),
+        }
+    }
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid key set: {}", self.source)
+    }
+}
+
+impl StdError for KeyError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::{json, Value};
+
+    use super::{KeySet, Reason, Validation};
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn sign(kid: Option<&str>, secret: &[u8], claims: Value) -> String {
+        let header = Header {
+            kid: kid.map(str::to_owned),
+            ..Header::default()
+        };
+        encode(&header, &claims, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    #[test]
+    fn claims_validated() {
+        let keys = KeySet::new()
+            .hs256(Some("old"), b"old secret")
+            .hs256(Some("new"), b"new secret");
+        let validation = Validation::new(keys)
+            .issuer("https://auth.example.com")
+            .audience("api");
+        let exp = now() + 600;
+        let claims = json!({
+            "sub": "alice",
+            "iss": "https://auth.example.com",
+            "aud": "api",
+            "exp": exp,
+        });
+        let decode = |token: &str| validation.decode::<Value>(token);
+
+        let token = sign(Some("new"), b"new secret", claims.clone());
+        assert_eq!(decode(&token).unwrap()["sub"], "alice");
+        let token = sign(None, b"old secret", claims.clone());
+        assert_eq!(decode(&token).unwrap()["sub"], "alice");
+
+        let token = sign(Some("new"), b"old secret", claims.clone());
+        assert_eq!(decode(&token), Err(Reason::BadSignature));
+        let token = sign(Some("gone"), b"new secret", claims.clone());
+        assert_eq!(decode(&token), Err(Reason::UnknownKey));
+        assert_eq!(decode("not.a.jwt"), Err(Reason::Malformed));
+
+        let mut expired = claims.clone();
+        expired["exp"] = json!(now() - 3600);
+        let token = sign(None, b"new secret", expired);
+        assert_eq!(decode(&token), Err(Reason::Expired));
+
+        let mut early = claims.clone();
+        early["nbf"] = json!(now() + 3600);
+        let token = sign(None, b"new secret", early);
+        assert_eq!(decode(&token), Err(Reason::NotYetValid));
+
+        let mut issuer = claims.clone();
+        issuer["iss"] = json!("https://evil.example.com");
+        let token = sign(None, b"new secret", issuer);
+        assert_eq!(decode(&token), Err(Reason::InvalidIssuer));
+
+        let mut audience = claims;
+        audience["aud"] = json!("other");
+        let token = sign(None, b"new secret", audience);
+        assert_eq!(decode(&token), Err(Reason::InvalidAudience));
+    }
+
+    #[test]
+    fn jwks_loaded() {
+        let jwks = r#"{"keys": [
+            {"kty": "oct", "kid": "a", "k": "c2VjcmV0"},
+            {"kty": "RSA", "kid": "b", "alg": "RS256", "n": "sXchDaQebHnPiGvyDOAT4saGEUetSyo9MKLOoWFsueri23bOdgWp4Dy1WlUzewbgBHod5pcM9H95GQRV3JDXboIRROSBigeC5yjU1hGzHHyXss8UDprecbAYxknTcQkhslANGRUZmdTOQ5qTRsLAt6BTYuyvVRdhS8exSZEy_c4gs_7svlJJQ4H9_NxsiIoLwAEk7-Q3UXERGYw_75IDrGA84-lA_-Ct4eTlXHBIY2EaV7t7LjJaynVJCpkv4LKjTTAumiGUIuQhrNhZLuF_RJLqHpM2kgWFLU7-VTdL1VbC2tejvcI2BlMkEpk1BzBZI0KQB0GaDWFLN-aEAw3vRw", "e": "AQAB"},
+            {"kty": "OKP", "kid": "c", "crv": "Ed25519", "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}
+        ]}"#;
+        let keys = KeySet::from_jwks(jwks).unwrap();
+        assert_eq!(keys.len(), 2);
+
+        let validation = Validation::new(keys);
+        let token = sign(Some("a"), b"secret", json!({"sub": "bob", "exp": now() + 60}));
+        assert_eq!(validation.decode::<Value>(&token).unwrap()["sub"], "bob");
+
+        assert!(KeySet::from_jwks("{}").is_err());
+    }
+}
@@ -3,18 +3,23 @@
 //! Filters that extract a body for a route.
 use std::error::Error as StdError;
 use std::fmt;
+use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
-use bytes::{Buf, Bytes};
-use futures_util::{future, Stream, TryFutureExt};
-use headers::ContentLength;
-use http::header::CONTENT_TYPE;
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::{future, ready, Stream, TryFutureExt};
+use headers::{ContentLength, HeaderMapExt};
+use http::header::{HeaderValue, CONTENT_TYPE};
 use hyper::Body;
 use mime;
+use pin_project::pin_project;
 use serde::de::DeserializeOwned;
 
 
-use crate::filter::{filter_fn, filter_fn_one, Filter, FilterBase};
+use crate::filter::{described, filter_fn, filter_fn_one, Filter, FilterBase};
+use crate::filters::openapi::Meta;
 use crate::reject::{self, Rejection};
 type BoxError = Box<dyn StdError + Send + Sync>;
 pub(crate) fn body() -> impl Filter<Extract = (Body,), Error = Rejection> + Copy {
@@ -48,23 +53,54 @@ pub(crate) fn body() -> impl Filter<Extract = (Body,), Error = Rejection> + Copy
 pub fn content_length_limit(
     limit: u64,
 ) -> impl Filter<Extract = (), Error = Rejection> + Copy {
-    crate::filters::header::header2()
-        .map_err(
-            crate::filter::Internal,
-            |_| {
+    filter_fn(move |route| {
+        if let Some(decoded) = route.extensions().get::<DecodedBody>() {
+            tracing::trace!("body is decoded, limiting decoded length to {}", limit);
+            decoded.limit_to(limit);
+            return future::ok(());
+        }
+        let length = match route.headers().typed_get::<ContentLength>() {
+            Some(ContentLength(length)) => length,
+            None => {
                 tracing::debug!("content-length missing");
-                reject::length_required()
-            },
-        )
-        .and_then(move |ContentLength(length)| {
-            if length <= limit {
-                future::ok(())
-            } else {
-                tracing::debug!("content-length: {} is over limit {}", length, limit);
-                future::err(reject::payload_too_large())
+                return future::err(reject::length_required());
             }
-        })
-        .untuple_one()
+        };
+        if length <= limit {
+            future::ok(())
+        } else {
+            tracing::debug!("content-length: {} is over limit {}", length, limit);
+            future::err(reject::payload_too_large())
+        }
+    })
+}
+
+/// Marks a body that a wrapper decodes as it is read, such as
+/// `compression::decompress_request`.
+///
+/// The decoded length isn't known up front, so instead of requiring a
+/// `Content-Length`, [`content_length_limit`] lowers the most bytes the
+/// wrapper lets the body decode to.
+#[derive(Clone, Debug)]
+pub(crate) struct DecodedBody {
+    limit: Arc<AtomicU64>,
+}
+
+impl DecodedBody {
+    pub(crate) fn new(limit: u64) -> DecodedBody {
+        DecodedBody {
+            limit: Arc::new(AtomicU64::new(limit)),
+        }
+    }
+
+    /// The most bytes the body may decode to.
+    pub(crate) fn limit(&self) -> u64 {
+        self.limit.load(Ordering::Acquire)
+    }
+
+    fn limit_to(&self, limit: u64) {
+        self.limit.fetch_min(limit, Ordering::AcqRel);
+    }
 }
 
 
@@ -177,7 +213,7 @@ pub fn json<T: DeserializeOwned + Send>() -> impl Filter<
     Extract = (T,),
     Error = Rejection,
 > + Copy {
-    is_content_type::<Json>()
+    let filter = is_content_type::<Json>()
         .and(bytes())
         .and_then(|buf| async move {
             Json::decode(buf)
@@ -185,7 +221,11 @@ pub fn json<T: DeserializeOwned + Send>() -> impl Filter<
                     tracing::debug!("request json body error: {}", err);
                     reject::known(BodyDeserializeError { cause: err })
                 })
-        })
+        });
+    described(filter, || Meta::Body {
+        content_type: "application/json",
+        ty: std::any::type_name::<T>(),
+    })
 }
 
 
@@ -215,7 +255,7 @@ pub fn form<T: DeserializeOwned + Send>() -> impl Filter<
     Extract = (T,),
     Error = Rejection,
 > + Copy {
-    is_content_type::<Form>()
+    let filter = is_content_type::<Form>()
         .and(aggregate())
         .and_then(|buf| async move {
             Form::decode(buf)
@@ -223,8 +263,383 @@ pub fn form<T: DeserializeOwned + Send>() -> impl Filter<
                     tracing::debug!("request form body error: {}", err);
                     reject::known(BodyDeserializeError { cause: err })
                 })
+        });
+    described(filter, || Meta::Body {
+        content_type: "application/x-www-form-urlencoded",
+        ty: std::any::type_name::<T>(),
+    })
+}
+/// Returns a `Filter` that streams the body as JSON Lines (NDJSON),
+/// deserializing each line as a `T`.
+///
+/// Lines are parsed as they arrive, so the body is never fully buffered.
+/// Empty lines are skipped, and a trailing `\r` is ignored. The stream
+/// yields an error, then ends, if a line is longer than `max_line` bytes,
+/// doesn't deserialize, or the body fails to be read.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::TryStreamExt;
+/// use warp::Filter;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Event {
+///     kind: String,
+/// }
+///
+/// let route = warp::post()
+///     .and(warp::body::json_lines(64 * 1024))
+///     .and_then(|events: warp::body::JsonLines<_, Event>| async move {
+///         let count = events
+///             .try_fold(0, |count, _event| async move { Ok(count + 1) })
+///             .await
+///             .map_err(|_| warp::reject())?;
+///         Ok::<_, warp::Rejection>(format!("ingested {} events", count))
+///     });
+/// ```
+pub fn json_lines<T: DeserializeOwned + Send + 'static>(max_line: usize) -> impl Filter<
+    Extract = (JsonLines<impl Stream<Item = Result<impl Buf, crate::Error>>, T>,),
+    Error = Rejection,
+> + Copy {
+    stream().map(move |body| JsonLines {
+        body,
+        lines: LineBuffer::new(max_line),
+        done: false,
+        _marker: PhantomData,
+    })
+}
+
+/// A stream of values deserialized from a JSON Lines body.
+///
+/// Created with [`json_lines`].
+#[pin_project]
+pub struct JsonLines<S, T> {
+    #[pin]
+    body: S,
+    lines: LineBuffer,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<S, T> fmt::Debug for JsonLines<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLines")
+            .field("buffered", &self.lines.buf.len())
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<S, B, T> Stream for JsonLines<S, T>
+where
+    S: Stream<Item = Result<B, crate::Error>>,
+    B: Buf,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, crate::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut pin = self.project();
+        loop {
+            if *pin.done && pin.lines.buf.is_empty() {
+                return Poll::Ready(None);
+            }
+            let line = match pin.lines.next_line(*pin.done) {
+                Ok(line) => line,
+                Err(err) => {
+                    *pin.done = true;
+                    pin.lines.buf.clear();
+                    return Poll::Ready(Some(Err(crate::Error::new(err))));
+                }
+            };
+            if let Some(line) = line {
+                let item = serde_json::from_slice(&line).map_err(|err| {
+                    tracing::debug!("json line deserialize error: {}", err);
+                    *pin.done = true;
+                    pin.lines.buf.clear();
+                    crate::Error::new(err)
+                });
+                return Poll::Ready(Some(item));
+            }
+            if *pin.done {
+                // Only whitespace was left after the body ended.
+                return Poll::Ready(None);
+            }
+            match ready!(pin.body.as_mut().poll_next(cx)) {
+                Some(Ok(mut chunk)) => {
+                    while chunk.has_remaining() {
+                        let bytes = chunk.chunk();
+                        let len = bytes.len();
+                        pin.lines.buf.extend_from_slice(bytes);
+                        chunk.advance(len);
+                    }
+                }
+                Some(Err(err)) => {
+                    *pin.done = true;
+                    pin.lines.buf.clear();
+                    return Poll::Ready(Some(Err(err)));
+                }
+                None => *pin.done = true,
+            }
+        }
+    }
+}
+
+/// Splits buffered bytes into lines, up to a maximum length.
+struct LineBuffer {
+    buf: BytesMut,
+    /// How far `buf` has been searched for a newline.
+    scanned: usize,
+    max_line: usize,
+}
+
+impl LineBuffer {
+    fn new(max_line: usize) -> LineBuffer {
+        LineBuffer {
+            buf: BytesMut::new(),
+            scanned: 0,
+            max_line,
+        }
+    }
+
+    /// Take the next non-empty line, without its line ending.
+    ///
+    /// At the end of the body, whatever is left is the last line.
+    fn next_line(&mut self, end: bool) -> Result<Option<Bytes>, LineTooLong> {
+        loop {
+            let newline = self.buf[self.scanned..].iter().position(|&b| b == b'\n');
+            let mut line = match newline {
+                Some(pos) => {
+                    let line = self.buf.split_to(self.scanned + pos + 1);
+                    self.scanned = 0;
+                    line
+                }
+                None if end => {
+                    self.scanned = 0;
+                    self.buf.split()
+                }
+                None => {
+                    self.scanned = self.buf.len();
+                    if self.buf.len() > self.max_line {
+                        return Err(LineTooLong(self.max_line));
+                    }
+                    return Ok(None);
+                }
+            };
+            while let Some(b'\n') | Some(b'\r') = line.last() {
+                line.truncate(line.len() - 1);
+            }
+            if line.len() > self.max_line {
+                return Err(LineTooLong(self.max_line));
+            }
+            if !line.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some(line.freeze()));
+            }
+            if self.buf.is_empty() {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LineTooLong(usize);
+
+impl fmt::Display for LineTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "JSON line longer than {} bytes", self.0)
+    }
+}
+
+impl StdError for LineTooLong {}
+
+/// Returns a `Filter` that deserializes the body as JSON or as a form,
+/// picked by its `Content-Type`.
+///
+/// This reads the body once, so unlike `json().or(form())`, it works on
+/// bodies that can only be consumed once. Requests without a
+/// `Content-Type` are assumed to be JSON. Other content-types reject with
+/// an [`UnsupportedMediaType`](crate::reject::UnsupportedMediaType)
+/// listing the ones accepted.
+///
+/// # Warning
+///
+/// This does not have a default size limit, it would be wise to use one to
+/// prevent a overly large request from using too much memory.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use warp::Filter;
+///
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::negotiated())
+///     .map(|simple_map: HashMap<String, String>| {
+///         "Got a JSON or form body!"
+///     });
+/// ```
+pub fn negotiated<T: DeserializeOwned + Send + 'static>() -> impl Filter<
+    Extract = (T,),
+    Error = Rejection,
+> + Clone {
+    negotiated_with(Formats::default())
+}
+
+/// Returns a `Filter` that deserializes the body with one of `formats`,
+/// picked by its `Content-Type`.
+///
+/// Requests without a `Content-Type` are assumed to be in the first
+/// registered format. Requests with any other type, including ones that
+/// can't be parsed, are rejected with a `415 Unsupported Media Type`
+/// listing the accepted types.
+///
+/// # Example
+///
+/// ```
+/// use warp::body::Formats;
+/// use warp::Filter;
+///
+/// #[derive(serde::Deserialize)]
+/// struct Order {
+///     item: String,
+/// }
+///
+/// let formats = Formats::default().register("text/plain", |body| {
+///     Ok(Order {
+///         item: std::str::from_utf8(&body)?.trim().to_owned(),
+///     })
+/// });
+/// let route = warp::body::content_length_limit(1024 * 32)
+///     .and(warp::body::negotiated_with(formats))
+///     .map(|order: Order| format!("ordered {}", order.item));
+/// ```
+pub fn negotiated_with<T: Send + 'static>(formats: Formats<T>) -> impl Filter<
+    Extract = (T,),
+    Error = Rejection,
+> + Clone {
+    let content_type = formats.formats.first().map_or("*/*", |format| format.content_type);
+    let formats = Arc::new(formats);
+    let filter = filter_fn(move |route| {
+        let format = formats.pick(route.headers().get(CONTENT_TYPE));
+        future::ready(format.map(|format| (format.decode.clone(),)))
+    })
+    .and(bytes())
+        .and_then(|decode: Decoder<T>, buf: Bytes| {
+            future::ready(decode(buf).map_err(|err| {
+                tracing::debug!("request negotiated body error: {}", err);
+                reject::known(BodyDeserializeError { cause: err })
+            }))
+        });
+    described(filter, move || Meta::Body {
+        content_type,
+        ty: std::any::type_name::<T>(),
+    })
+}
+
+type Decoder<T> = Arc<dyn Fn(Bytes) -> Result<T, BoxError> + Send + Sync>;
+
+/// The formats [`negotiated_with`] can deserialize a body from.
+///
+/// The default formats are JSON and forms.
+pub struct Formats<T> {
+    formats: Vec<Format<T>>,
+}
+
+struct Format<T> {
+    content_type: &'static str,
+    mime: mime::Mime,
+    decode: Decoder<T>,
+}
+
+impl<T> Formats<T> {
+    /// Create a registry without any formats.
+    pub fn new() -> Formats<T> {
+        Formats {
+            formats: Vec::new(),
+        }
+    }
+
+    /// Deserialize bodies with `content_type` with `decode`.
+    ///
+    /// Parameters of the request's `Content-Type`, such as `charset`, are
+    /// ignored when matching. If several formats have the same
+    /// content-type, the first one registered is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `content_type` isn't a valid media type.
+    pub fn register<F>(mut self, content_type: &'static str, decode: F) -> Self
+    where
+        F: Fn(Bytes) -> Result<T, BoxError> + Send + Sync + 'static,
+    {
+        let mime = content_type.parse().expect("invalid content-type");
+        self.formats.push(Format {
+            content_type,
+            mime,
+            decode: Arc::new(decode),
+        });
+        self
+    }
+
+    fn pick(&self, content_type: Option<&HeaderValue>) -> Result<&Format<T>, Rejection> {
+        let format = match content_type {
+            Some(value) => value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<mime::Mime>().ok())
+                .and_then(|ct| {
+                    self.formats.iter().find(|format| {
+                        format.mime.type_() == ct.type_() && format.mime.subtype() == ct.subtype()
+                    })
+                }),
+            None => self.formats.first(),
+        };
+        format.ok_or_else(|| {
+            tracing::debug!("content-type {:?} not in negotiated formats", content_type);
+            reject::unsupported_media_type(self.accepted())
+        })
+    }
+
+    fn accepted(&self) -> Vec<String> {
+        self.formats
+            .iter()
+            .map(|format| format.mime.essence_str().to_owned())
+            .collect()
+    }
+}
+
+impl<T: DeserializeOwned> Formats<T> {
+    /// Accept JSON bodies.
+    pub fn json(self) -> Self {
+        self.register("application/json", |buf| {
+            serde_json::from_slice(&buf).map_err(Into::into)
+        })
+    }
+
+    /// Accept `application/x-www-form-urlencoded` bodies.
+    pub fn form(self) -> Self {
+        self.register("application/x-www-form-urlencoded", |buf| {
+            serde_urlencoded::from_bytes(&buf).map_err(Into::into)
         })
+    }
+}
+
+impl<T: DeserializeOwned> Default for Formats<T> {
+    fn default() -> Self {
+        Formats::new().json().form()
+    }
 }
+
+impl<T> fmt::Debug for Formats<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.formats.iter().map(|format| format.content_type))
+            .finish()
+    }
+}
+
 trait Decode {
     const MIME: (mime::Name<'static>, mime::Name<'static>);
     const WITH_NO_CONTENT_TYPE: bool;
@@ -255,6 +670,7 @@ impl Decode for Form {
 fn is_content_type<D: Decode>() -> impl Filter<Extract = (), Error = Rejection> + Copy {
     filter_fn(move |route| {
         let (type_, subtype) = D::MIME;
+        let unsupported = || reject::unsupported_media_type(vec![format!("{}/{}", type_, subtype)]);
         if let Some(value) = route.headers().get(CONTENT_TYPE) {
             tracing::trace!("is_content_type {}/{}? {:?}", type_, subtype, value);
             let ct = value.to_str().ok().and_then(|s| s.parse::<mime::Mime>().ok());
@@ -265,18 +681,18 @@ fn is_content_type<D: Decode>() -> impl Filter<Extract = (), Error = Rejection>
                     tracing::debug!(
                         "content-type {:?} doesn't match {}/{}", value, type_, subtype
                     );
-                    future::err(reject::unsupported_media_type())
+                    future::err(unsupported())
                 }
             } else {
                 tracing::debug!("content-type {:?} couldn't be parsed", value);
-                future::err(reject::unsupported_media_type())
+                future::err(unsupported())
             }
         } else if D::WITH_NO_CONTENT_TYPE {
             tracing::trace!("no content-type header, assuming {}/{}", type_, subtype);
             future::ok(())
         } else {
             tracing::debug!("no content-type found");
-            future::err(reject::unsupported_media_type())
+            future::err(unsupported())
         }
     })
 }
@@ -308,7 +724,7 @@ impl StdError for BodyDeserializeError {
     }
 }
 #[derive(Debug)]
-pub(crate) struct BodyReadError(::hyper::Error);
+pub(crate) struct BodyReadError(pub(crate) ::hyper::Error);
 impl fmt::Display for BodyReadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         loop {}
@@ -318,3 +734,88 @@ impl StdError for BodyReadError {}
 unit_error! {
     pub (crate) BodyConsumedMultipleTimes : "Request body consumed multiple times"
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::marker::PhantomData;
+    use std::task::Poll;
+
+    use bytes::Bytes;
+    use futures_util::{stream, StreamExt};
+    use http::HeaderValue;
+
+    use super::{Formats, JsonLines, LineBuffer};
+    use crate::reject::IsReject;
+
+    #[test]
+    fn lines_split_and_limited() {
+        let mut lines = LineBuffer::new(8);
+        lines.buf.extend_from_slice(b"{\"a\":1}\r\n\n  \n[2]\n[3");
+        assert_eq!(lines.next_line(false).unwrap().unwrap(), r#"{"a":1}"#);
+        assert_eq!(lines.next_line(false).unwrap().unwrap(), "[2]");
+        assert_eq!(lines.next_line(false).unwrap(), None);
+        lines.buf.extend_from_slice(b"]");
+        assert_eq!(lines.next_line(false).unwrap(), None);
+        assert_eq!(lines.next_line(true).unwrap().unwrap(), "[3]");
+        assert_eq!(lines.next_line(true).unwrap(), None);
+
+        lines.buf.extend_from_slice(b"[1,2,3,4,");
+        assert!(lines.next_line(false).is_err());
+        let mut lines = LineBuffer::new(8);
+        lines.buf.extend_from_slice(b"[1,2,3,4]\n");
+        assert!(lines.next_line(false).is_err());
+    }
+
+    #[tokio::test]
+    async fn json_lines_end_after_trailing_whitespace() {
+        let mut chunks = vec![Bytes::from("[1]\n  ")].into_iter();
+        let mut ended = false;
+        let body = stream::poll_fn(move |_| match chunks.next() {
+            Some(chunk) => Poll::Ready(Some(Ok::<_, crate::Error>(chunk))),
+            None => {
+                assert!(!ended, "body polled after it ended");
+                ended = true;
+                Poll::Ready(None)
+            }
+        });
+        let lines = JsonLines::<_, Vec<u32>> {
+            body,
+            lines: LineBuffer::new(8),
+            done: false,
+            _marker: PhantomData,
+        };
+        let items = lines.map(Result::unwrap).collect::<Vec<_>>().await;
+        assert_eq!(items, [vec![1]]);
+    }
+
+    #[test]
+    fn formats_picked_by_content_type() {
+        let formats = Formats::<HashMap<String, String>>::default()
+            .register("text/plain", |_| Ok(HashMap::new()));
+        let decode = |ct: Option<&'static str>, body: &'static str| {
+            let ct = ct.map(HeaderValue::from_static);
+            formats
+                .pick(ct.as_ref())
+                .map(|format| (format.decode)(Bytes::from(body)).unwrap())
+        };
+
+        let json = decode(Some("application/json; charset=utf-8"), r#"{"a":"1"}"#).unwrap();
+        assert_eq!(json["a"], "1");
+        let form = decode(Some("application/x-www-form-urlencoded"), "a=2").unwrap();
+        assert_eq!(form["a"], "2");
+        assert!(decode(Some("text/plain"), "a").unwrap().is_empty());
+        assert_eq!(decode(None, r#"{"a":"3"}"#).unwrap()["a"], "3");
+
+        let err = decode(Some("application/cbor"), "").unwrap_err();
+        assert_eq!(err.status(), 415);
+        let err = decode(Some("application/json; charset"), "").unwrap_err();
+        assert_eq!(err.status(), 415);
+        let err = decode(Some("not a media type"), "").unwrap_err();
+        assert_eq!(err.status(), 415);
+        assert_eq!(
+            formats.accepted(),
+            ["application/json", "application/x-www-form-urlencoded", "text/plain"]
+        );
+    }
+}
@@ -1,20 +1,43 @@
 //! Compression Filters
 //!
-//! Filters that compress the body of a response.
+//! Filters that compress the body of a response, and that decompress the
+//! body of a request.
+//!
+//! [`auto`] negotiates the codec with each client from its
+//! `Accept-Encoding`, while [`gzip`], [`deflate`], [`brotli`] and [`zstd`]
+//! always compress with one codec.
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 #[cfg(feature = "compression-brotli")]
-use async_compression::tokio::bufread::BrotliEncoder;
+use async_compression::tokio::bufread::{BrotliDecoder, BrotliEncoder};
 #[cfg(feature = "compression-gzip")]
-use async_compression::tokio::bufread::{DeflateEncoder, GzipEncoder};
-use http::header::HeaderValue;
+use async_compression::tokio::bufread::{GzipDecoder, GzipEncoder, ZlibDecoder, ZlibEncoder};
+#[cfg(feature = "compression-zstd")]
+use async_compression::tokio::bufread::{ZstdDecoder, ZstdEncoder};
+use futures_util::{StreamExt, TryStreamExt};
+use http::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_RANGE, CONTENT_TYPE, VARY,
+};
+use http::StatusCode;
+use hyper::body::HttpBody;
 use hyper::{
     header::{CONTENT_ENCODING, CONTENT_LENGTH},
     Body,
 };
+use tokio::io::{AsyncBufRead, AsyncRead};
 use tokio_util::io::{ReaderStream, StreamReader};
 use crate::filter::{Filter, WrapSealed};
-use crate::reject::IsReject;
+use crate::filters::body::DecodedBody;
+use crate::filters::header::AcceptEncoding;
+use crate::reject::{CombineRejection, IsReject, Rejection};
 use crate::reply::{Reply, Response};
-use self::internal::{CompressionProps, WithCompression};
+use self::internal::{
+    CompressionProps, WithAutoCompression, WithCompression, WithDecompression,
+};
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum CompressionAlgo {
     #[cfg(feature = "compression-brotli")]
     BR,
@@ -22,11 +45,71 @@ enum CompressionAlgo {
     DEFLATE,
     #[cfg(feature = "compression-gzip")]
     GZIP,
+    #[cfg(feature = "compression-zstd")]
+    ZSTD,
 }
 impl From<CompressionAlgo> for HeaderValue {
     #[inline]
     fn from(algo: CompressionAlgo) -> Self {
-        loop {}
+        HeaderValue::from_static(algo.name())
+    }
+}
+impl CompressionAlgo {
+    /// The enabled codecs, in the order preferred when a client accepts
+    /// several equally.
+    const PREFERRED: &'static [CompressionAlgo] = &[
+        #[cfg(feature = "compression-brotli")]
+        CompressionAlgo::BR,
+        #[cfg(feature = "compression-zstd")]
+        CompressionAlgo::ZSTD,
+        #[cfg(feature = "compression-gzip")]
+        CompressionAlgo::GZIP,
+        #[cfg(feature = "compression-gzip")]
+        CompressionAlgo::DEFLATE,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "compression-brotli")]
+            CompressionAlgo::BR => "br",
+            #[cfg(feature = "compression-gzip")]
+            CompressionAlgo::DEFLATE => "deflate",
+            #[cfg(feature = "compression-gzip")]
+            CompressionAlgo::GZIP => "gzip",
+            #[cfg(feature = "compression-zstd")]
+            CompressionAlgo::ZSTD => "zstd",
+        }
+    }
+
+    /// Pick the codec with the highest q-value in an `Accept-Encoding`.
+    fn negotiate(accept: &HeaderValue) -> Option<CompressionAlgo> {
+        let accept = AcceptEncoding::parse(accept.to_str().ok()?);
+        accept
+            .ranked(CompressionAlgo::PREFERRED, CompressionAlgo::name)
+            .first()
+            .copied()
+    }
+
+    fn encode(self, body: Body) -> Body {
+        self.encode_reader(body_reader(body))
+    }
+
+    fn encode_reader<R>(self, body: R) -> Body
+    where
+        R: AsyncBufRead + Send + Unpin + 'static,
+    {
+        let reader: Box<dyn AsyncRead + Send + Unpin> = match self {
+            #[cfg(feature = "compression-brotli")]
+            CompressionAlgo::BR => Box::new(BrotliEncoder::new(body)),
+            // HTTP's `deflate` is the zlib format.
+            #[cfg(feature = "compression-gzip")]
+            CompressionAlgo::DEFLATE => Box::new(ZlibEncoder::new(body)),
+            #[cfg(feature = "compression-gzip")]
+            CompressionAlgo::GZIP => Box::new(GzipEncoder::new(body)),
+            #[cfg(feature = "compression-zstd")]
+            CompressionAlgo::ZSTD => Box::new(ZstdEncoder::new(body)),
+        };
+        Body::wrap_stream(ReaderStream::new(reader))
     }
 }
 
@@ -49,7 +132,7 @@ pub struct Compression<F> {
 
 #[cfg(feature = "compression-gzip")]
 pub fn gzip() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
-    loop {}
+    compress_with(CompressionAlgo::GZIP)
 }
 
 
@@ -66,7 +149,7 @@ pub fn gzip() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
 
 #[cfg(feature = "compression-gzip")]
 pub fn deflate() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
-    loop {}
+    compress_with(CompressionAlgo::DEFLATE)
 }
 
 
@@ -83,8 +166,40 @@ pub fn deflate() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
 
 #[cfg(feature = "compression-brotli")]
 pub fn brotli() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
-    loop {}
+    compress_with(CompressionAlgo::BR)
+}
+/// Compresses the response body with the zstd algorithm and sets the
+/// `content-encoding: zstd` header.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::get()
+///     .and(warp::path::end())
+///     .and(warp::fs::file("./README.md"))
+///     .with(warp::compression::zstd());
+/// ```
+#[cfg(feature = "compression-zstd")]
+pub fn zstd() -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
+    compress_with(CompressionAlgo::ZSTD)
 }
+
+/// Compress response bodies with `algo`, replacing their `Content-Length`
+/// with a `Content-Encoding`.
+fn compress_with(
+    algo: CompressionAlgo,
+) -> Compression<impl Fn(CompressionProps) -> Response + Copy> {
+    let func = move |mut props: CompressionProps| {
+        let body = algo.encode_reader(StreamReader::new(props.body));
+        props.head.headers.append(CONTENT_ENCODING, algo.into());
+        props.head.headers.remove(CONTENT_LENGTH);
+        Response::from_parts(props.head, body)
+    };
+    Compression { func }
+}
+
 impl<FN, F> WrapSealed<F> for Compression<FN>
 where
     FN: Fn(CompressionProps) -> Response + Clone + Send,
@@ -94,9 +209,360 @@ where
 {
     type Wrapped = WithCompression<FN, F>;
     fn wrap(&self, filter: F) -> Self::Wrapped {
-        loop {}
+        WithCompression {
+            filter,
+            compress: self.clone(),
+        }
+    }
+}
+/// Compress replies with the best codec each client accepts.
+///
+/// The codec is negotiated from the request's `Accept-Encoding` q-values,
+/// preferring `br`, then `zstd`, `gzip` and `deflate` (depending on the
+/// enabled features) when several are accepted equally.
+///
+/// Replies are left as they are if they:
+///
+/// - are smaller than the [`min_size`](Auto::min_size),
+/// - already have a `Content-Encoding`, or are a `Content-Range`,
+/// - have a `Content-Type` that doesn't compress well, such as images,
+///   or that is streamed, like `text/event-stream`,
+/// - have `Cache-Control: no-transform`.
+///
+/// Otherwise `Vary: Accept-Encoding` is added, since the reply depends on
+/// it, and compressed replies have their `Content-Length` removed.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::get()
+///     .and(warp::path::end())
+///     .and(warp::fs::file("./README.md"))
+///     .with(warp::compression::auto().min_size(512));
+/// ```
+pub fn auto() -> Auto {
+    Auto {
+        min_size: DEFAULT_MIN_SIZE,
+    }
+}
+
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+/// Wrapper that negotiates how to compress replies.
+///
+/// Created with [`auto`].
+#[derive(Clone, Copy, Debug)]
+pub struct Auto {
+    min_size: u64,
+}
+
+impl Auto {
+    /// Set the smallest reply, in bytes, worth compressing.
+    ///
+    /// Replies of unknown size, such as streams, are always compressed.
+    /// Defaults to 1 KiB.
+    pub fn min_size(self, bytes: u64) -> Self {
+        Auto { min_size: bytes }
+    }
+
+    fn compress(&self, accept: Option<&HeaderValue>, mut res: Response) -> Response {
+        if !self.is_compressible(&res) {
+            return res;
+        }
+        add_vary(res.headers_mut());
+        let algo = match accept.and_then(CompressionAlgo::negotiate) {
+            Some(algo) => algo,
+            None => return res,
+        };
+        let (mut head, body) = res.into_parts();
+        head.headers.insert(CONTENT_ENCODING, algo.into());
+        head.headers.remove(CONTENT_LENGTH);
+        Response::from_parts(head, algo.encode(body))
+    }
+
+    fn is_compressible(&self, res: &Response) -> bool {
+        let status = res.status();
+        if status.is_informational()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::PARTIAL_CONTENT
+            || status == StatusCode::NOT_MODIFIED
+        {
+            return false;
+        }
+        let headers = res.headers();
+        if headers.contains_key(CONTENT_ENCODING) || headers.contains_key(CONTENT_RANGE) {
+            return false;
+        }
+        let no_transform = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|directive| directive.trim().eq_ignore_ascii_case("no-transform"));
+        if no_transform {
+            return false;
+        }
+        let size = HttpBody::size_hint(res.body()).exact().or_else(|| {
+            headers
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+        });
+        if size.map_or(false, |size| size < self.min_size) {
+            return false;
+        }
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .map_or(false, |mime| is_compressible_type(&mime))
+    }
+}
+
+impl<F> WrapSealed<F> for Auto
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Future: Send,
+    F::Error: IsReject,
+{
+    type Wrapped = WithAutoCompression<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithAutoCompression { auto: *self, filter }
+    }
+}
+
+fn is_compressible_type(mime: &mime::Mime) -> bool {
+    let textual = matches!(mime.suffix(), Some(suffix) if suffix == "json" || suffix == "xml");
+    match mime.type_() {
+        mime::TEXT => mime.subtype() != mime::EVENT_STREAM,
+        mime::APPLICATION => {
+            textual
+                || matches!(
+                    mime.subtype().as_str(),
+                    "json"
+                        | "javascript"
+                        | "ecmascript"
+                        | "xml"
+                        | "x-ndjson"
+                        | "graphql"
+                        | "wasm"
+                        | "x-www-form-urlencoded"
+                )
+        }
+        mime::IMAGE => textual || mime.subtype() == "x-icon" || mime.subtype() == "bmp",
+        mime::FONT => mime.subtype() == "ttf" || mime.subtype() == "otf",
+        _ => false,
+    }
+}
+
+/// Add `Accept-Encoding` to `Vary`, unless it's already listed.
+fn add_vary(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|name| name == "*" || name.eq_ignore_ascii_case("accept-encoding"));
+    if !listed {
+        headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+    }
+}
+
+/// Read a body as an `AsyncBufRead`, for the codecs.
+fn body_reader(body: Body) -> impl AsyncBufRead + Send + Unpin {
+    StreamReader::new(TryStreamExt::map_err(body, |err| {
+        io::Error::new(io::ErrorKind::Other, err)
+    }))
+}
+
+/// Decompress request bodies sent with a supported `Content-Encoding`.
+///
+/// Bodies encoded with `gzip`, `deflate`, `br` or `zstd` (depending on the enabled
+/// features) are decoded as they are read, so the wrapped body filters see
+/// the original content. The `Content-Encoding` and `Content-Length`
+/// headers are removed, since they no longer describe the body; a wrapped
+/// [`content_length_limit`](crate::body::content_length_limit) limits the
+/// decompressed length instead.
+///
+/// Requests with any other encoding are rejected with an
+/// [`UnsupportedEncoding`] (`415 Unsupported Media Type`), and bodies that
+/// decompress to more than the [`limit`](DecompressRequest::limit) are
+/// rejected with `413 Payload Too Large`.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::post()
+///     .and(warp::body::content_length_limit(64 * 1024))
+///     .and(warp::body::json())
+///     .map(|body: serde_json::Value| warp::reply::json(&body))
+///     .with(warp::compression::decompress_request().limit(1024 * 1024));
+/// ```
+pub fn decompress_request() -> DecompressRequest {
+    DecompressRequest {
+        limit: DEFAULT_DECOMPRESSED_LIMIT,
+    }
+}
+
+const DEFAULT_DECOMPRESSED_LIMIT: u64 = 10 * 1024 * 1024;
+
+/// Wrapper that decompresses request bodies.
+///
+/// Created with [`decompress_request`].
+#[derive(Clone, Copy, Debug)]
+pub struct DecompressRequest {
+    limit: u64,
+}
+
+impl DecompressRequest {
+    /// Set the most bytes a body may decompress to.
+    ///
+    /// Defaults to 10 MiB.
+    pub fn limit(self, bytes: u64) -> Self {
+        DecompressRequest { limit: bytes }
+    }
+}
+
+impl<F> WrapSealed<F> for DecompressRequest
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Send,
+    F::Future: Send,
+    F::Error: CombineRejection<Rejection>,
+{
+    type Wrapped = WithDecompression<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithDecompression {
+            filter,
+            limit: self.limit,
+        }
+    }
+}
+
+/// The request body was sent with an unsupported `Content-Encoding`.
+#[derive(Debug)]
+pub struct UnsupportedEncoding {
+    encoding: String,
+}
+
+impl UnsupportedEncoding {
+    /// The content-codings that would have been accepted.
+    pub fn accepted(&self) -> &'static [&'static str] {
+        ACCEPTED_ENCODINGS
+    }
+}
+
+impl fmt::Display for UnsupportedEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The request's content-encoding {:?} is not supported, expected one of: {}",
+            self.encoding,
+            ACCEPTED_ENCODINGS.join(", ")
+        )
+    }
+}
+
+impl StdError for UnsupportedEncoding {}
+
+const ACCEPTED_ENCODINGS: &[&str] = &[
+    #[cfg(feature = "compression-gzip")]
+    "gzip",
+    #[cfg(feature = "compression-gzip")]
+    "deflate",
+    #[cfg(feature = "compression-brotli")]
+    "br",
+    #[cfg(feature = "compression-zstd")]
+    "zstd",
+    "identity",
+];
+
+/// Decoders for the request body, picked from its `Content-Encoding`.
+enum RequestDecoder {
+    Identity,
+    #[cfg(feature = "compression-brotli")]
+    Brotli,
+    #[cfg(feature = "compression-gzip")]
+    Deflate,
+    #[cfg(feature = "compression-gzip")]
+    Gzip,
+    #[cfg(feature = "compression-zstd")]
+    Zstd,
+}
+
+impl RequestDecoder {
+    fn from_header(value: Option<&HeaderValue>) -> Result<RequestDecoder, UnsupportedEncoding> {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(RequestDecoder::Identity),
+        };
+        let unsupported = || UnsupportedEncoding {
+            encoding: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        };
+        let mut codings = value
+            .to_str()
+            .map_err(|_| unsupported())?
+            .split(',')
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"));
+        let coding = match (codings.next(), codings.next()) {
+            (None, _) => return Ok(RequestDecoder::Identity),
+            (Some(coding), None) => coding.to_ascii_lowercase(),
+            // Stacked codings are too rare to be worth decoding.
+            (Some(_), Some(_)) => return Err(unsupported()),
+        };
+        match coding.as_str() {
+            #[cfg(feature = "compression-gzip")]
+            "gzip" | "x-gzip" => Ok(RequestDecoder::Gzip),
+            #[cfg(feature = "compression-gzip")]
+            "deflate" => Ok(RequestDecoder::Deflate),
+            #[cfg(feature = "compression-brotli")]
+            "br" => Ok(RequestDecoder::Brotli),
+            #[cfg(feature = "compression-zstd")]
+            "zstd" => Ok(RequestDecoder::Zstd),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Decode `body`, failing once it decompresses to more than the `limit`
+    /// at that point, which is recorded in `exceeded`.
+    fn decode(self, body: Body, limit: DecodedBody, exceeded: Arc<AtomicBool>) -> Body {
+        let body = body_reader(body);
+        let reader: Box<dyn AsyncRead + Send + Unpin> = match self {
+            RequestDecoder::Identity => return Body::wrap_stream(ReaderStream::new(body)),
+            #[cfg(feature = "compression-brotli")]
+            RequestDecoder::Brotli => Box::new(BrotliDecoder::new(body)),
+            #[cfg(feature = "compression-gzip")]
+            RequestDecoder::Deflate => Box::new(ZlibDecoder::new(body)),
+            #[cfg(feature = "compression-gzip")]
+            RequestDecoder::Gzip => Box::new(GzipDecoder::new(body)),
+            #[cfg(feature = "compression-zstd")]
+            RequestDecoder::Zstd => Box::new(ZstdDecoder::new(body)),
+        };
+        let mut decoded = 0;
+        Body::wrap_stream(ReaderStream::new(reader).map(move |chunk| {
+            let chunk = chunk?;
+            decoded += chunk.len() as u64;
+            if decoded > limit.limit() {
+                exceeded.store(true, Ordering::Release);
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "decompressed body is too large",
+                ));
+            }
+            Ok(chunk)
+        }))
     }
 }
+
 mod internal {
     use std::future::Future;
     use std::pin::Pin;
@@ -105,10 +571,16 @@ mod internal {
     use futures_util::{ready, Stream, TryFuture};
     use hyper::Body;
     use pin_project::pin_project;
-    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
     use crate::reject::IsReject;
     use crate::reply::{Reply, Response};
-    use super::Compression;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+    use crate::reject::{self, CombineRejection, Rejection};
+    use crate::route;
+    use crate::filters::body::DecodedBody;
+    use super::{Auto, Compression, RequestDecoder};
     
     
     #[pin_project]
@@ -131,12 +603,14 @@ mod internal {
             self: Pin<&mut Self>,
             cx: &mut Context<'_>,
         ) -> Poll<Option<Self::Item>> {
-            loop {}
+            use std::io::{Error, ErrorKind};
+            let pin = self.project();
+            S::poll_next(pin.body, cx).map_err(|_| Error::from(ErrorKind::InvalidData))
         }
     }
     impl From<Body> for CompressableBody<Body, hyper::Error> {
         fn from(body: Body) -> Self {
-            loop {}
+            CompressableBody { body }
         }
     }
     
@@ -147,7 +621,11 @@ mod internal {
     }
     impl From<http::Response<Body>> for CompressionProps {
         fn from(resp: http::Response<Body>) -> Self {
-            loop {}
+            let (head, body) = resp.into_parts();
+            CompressionProps {
+                body: body.into(),
+                head,
+            }
         }
     }
     #[allow(missing_debug_implementations)]
@@ -155,7 +633,7 @@ mod internal {
     impl Reply for Compressed {
         #[inline]
         fn into_response(self) -> Response {
-            loop {}
+            self.0
         }
     }
     #[allow(missing_debug_implementations)]
@@ -175,7 +653,13 @@ mod internal {
         type Error = F::Error;
         type Future = WithCompressionFuture<FN, F::Future>;
         fn filter(&self, _: Internal) -> Self::Future {
-            loop {}
+            WithCompressionFuture {
+                compress: self.compress.clone(),
+                future: self.filter.filter(Internal),
+            }
+        }
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
         }
     }
     #[allow(missing_debug_implementations)]
@@ -194,7 +678,372 @@ mod internal {
     {
         type Output = Result<(Compressed,), F::Error>;
         fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            loop {}
+            let result = ready!(self.as_mut().project().future.try_poll(cx));
+            match result {
+                Ok(reply) => {
+                    let resp = (self.compress.func)(reply.into_response().into());
+                    Poll::Ready(Ok((Compressed(resp),)))
+                }
+                Err(reject) => Poll::Ready(Err(reject)),
+            }
         }
     }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone, Copy)]
+    pub struct WithAutoCompression<F> {
+        pub(super) auto: Auto,
+        pub(super) filter: F,
+    }
+
+    impl<F> FilterBase for WithAutoCompression<F>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+        F::Future: Send,
+        F::Error: IsReject,
+    {
+        type Extract = (Compressed,);
+        type Error = F::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let auto = self.auto;
+            let accept = route::with(|route| route.headers().get(ACCEPT_ENCODING).cloned());
+            let reply = self.filter.filter(Internal);
+            Box::pin(async move {
+                let res = reply.await?.into_response();
+                Ok((Compressed(auto.compress(accept.as_ref(), res)),))
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone, Copy)]
+    pub struct WithDecompression<F> {
+        pub(super) filter: F,
+        pub(super) limit: u64,
+    }
+
+    impl<F> FilterBase for WithDecompression<F>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Send,
+        F::Future: Send,
+        F::Error: CombineRejection<Rejection>,
+    {
+        type Extract = F::Extract;
+        type Error = <F::Error as CombineRejection<Rejection>>::One;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let filter = self.filter.clone();
+            let limit = self.limit;
+            Box::pin(async move {
+                let exceeded = Arc::new(AtomicBool::new(false));
+                let decoded = DecodedBody::new(limit);
+                route::with(|route| {
+                    let decoder = RequestDecoder::from_header(route.headers().get(CONTENT_ENCODING))
+                        .map_err(|err| {
+                            tracing::debug!("request decompression: {}", err);
+                            reject::known(err)
+                        })?;
+                    if let RequestDecoder::Identity = decoder {
+                        return Ok::<_, Rejection>(());
+                    }
+                    if let Some(body) = route.take_body() {
+                        route.set_body(decoder.decode(body, decoded.clone(), exceeded.clone()));
+                        route.headers_mut().remove(CONTENT_ENCODING);
+                        route.headers_mut().remove(CONTENT_LENGTH);
+                        // Lets `content_length_limit` check the decoded length
+                        // instead of the `Content-Length` removed above.
+                        route.extensions_mut().insert(decoded.clone());
+                    }
+                    Ok(())
+                })
+                .map_err(Self::Error::from)?;
+
+                let result = filter.filter(Internal).await;
+                route::with(|route| route.extensions_mut().remove::<DecodedBody>());
+                result.map_err(|err| {
+                    if exceeded.load(Ordering::Acquire) {
+                        tracing::debug!(
+                            "request body decompressed past limit of {} bytes",
+                            decoded.limit()
+                        );
+                        Self::Error::from(reject::payload_too_large())
+                    } else {
+                        Self::Error::from(err)
+                    }
+                })
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use futures_util::future::{self, poll_fn};
+    use http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
+    use http::StatusCode;
+    use hyper::Body;
+
+    use super::{CompressionAlgo, RequestDecoder};
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::filters::body::{BodyReadError, DecodedBody};
+    use crate::reject::{IsReject, Rejection};
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
+    use crate::Request;
+
+    /// Run `filter` on `req`, as the server would.
+    async fn run<F>(filter: &F, req: Request) -> Result<F::Extract, F::Error>
+    where
+        F: FilterBase,
+        F::Future: Unpin,
+    {
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await
+    }
+
+    /// Encode `data` with `algo`, as a client would send it.
+    async fn encoded(algo: CompressionAlgo, data: &[u8]) -> Body {
+        let body = algo.encode(Body::from(data.to_vec()));
+        hyper::body::to_bytes(body).await.unwrap().into()
+    }
+
+    fn decoder(value: &'static str) -> Result<RequestDecoder, super::UnsupportedEncoding> {
+        RequestDecoder::from_header(Some(&HeaderValue::from_static(value)))
+    }
+
+    #[test]
+    fn request_decoders_picked() {
+        assert!(matches!(RequestDecoder::from_header(None), Ok(RequestDecoder::Identity)));
+        assert!(matches!(decoder("identity"), Ok(RequestDecoder::Identity)));
+        #[cfg(feature = "compression-gzip")]
+        {
+            assert!(matches!(decoder("GZIP"), Ok(RequestDecoder::Gzip)));
+            assert!(matches!(decoder("deflate, identity"), Ok(RequestDecoder::Deflate)));
+        }
+        #[cfg(feature = "compression-zstd")]
+        assert!(matches!(decoder("zstd"), Ok(RequestDecoder::Zstd)));
+
+        let err = decoder("compress").err().unwrap();
+        assert!(err.to_string().contains("\"compress\""));
+        assert!(err.accepted().contains(&"identity"));
+        assert!(decoder("gzip, deflate").is_err());
+    }
+
+    #[tokio::test]
+    async fn problem_keeps_accept_encoding() {
+        let err = crate::reject::known(decoder("compress").err().unwrap());
+        let res = crate::problem::recover(err).await.unwrap().into_response();
+        assert_eq!(res.status(), http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let accepted = super::ACCEPTED_ENCODINGS.join(", ");
+        assert_eq!(res.headers()[http::header::ACCEPT_ENCODING], accepted);
+    }
+
+    #[tokio::test]
+    async fn request_decoded_within_limit() {
+        let data = b"hello decompressed world".repeat(100);
+
+        for &algo in CompressionAlgo::PREFERRED {
+            let new_decoder = || decoder(algo.name()).unwrap();
+            let exceeded = Arc::new(AtomicBool::new(false));
+            let body = new_decoder().decode(
+                encoded(algo, &data).await,
+                DecodedBody::new(10_000),
+                exceeded.clone(),
+            );
+            assert_eq!(hyper::body::to_bytes(body).await.unwrap(), data, "{}", algo.name());
+            assert!(!exceeded.load(Ordering::Acquire));
+
+            let body = new_decoder().decode(
+                encoded(algo, &data).await,
+                DecodedBody::new(1_000),
+                exceeded.clone(),
+            );
+            assert!(hyper::body::to_bytes(body).await.is_err());
+            assert!(exceeded.load(Ordering::Acquire), "{}", algo.name());
+        }
+    }
+
+    /// `content_length_limit(limit)` followed by reading the body as JSON,
+    /// run one after the other.
+    #[derive(Clone)]
+    struct LimitedJson(u64);
+
+    impl FilterBase for LimitedJson {
+        type Extract = (serde_json::Value,);
+        type Error = Rejection;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Rejection>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let limit = crate::body::content_length_limit(self.0);
+            Box::pin(async move {
+                limit.filter(Internal).await?;
+                let (body,) = crate::body::body().filter(Internal).await?;
+                let buf = hyper::body::to_bytes(body)
+                    .await
+                    .map_err(|err| crate::reject::known(BodyReadError(err)))?;
+                Ok((serde_json::from_slice(&buf).unwrap(),))
+            })
+        }
+    }
+
+    #[cfg(feature = "compression-gzip")]
+    #[tokio::test]
+    async fn decoded_body_checked_by_content_length_limit() {
+        let text = "hello decompressed world ".repeat(100);
+        let data = serde_json::to_vec(&serde_json::json!({ "text": text })).unwrap();
+        let request = || async {
+            let body = hyper::body::to_bytes(encoded(CompressionAlgo::GZIP, &data).await)
+                .await
+                .unwrap();
+            let mut req = Request::new(Body::from(body.clone()));
+            let headers = req.headers_mut();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            headers.insert(CONTENT_LENGTH, body.len().into());
+            req
+        };
+        let route = |limit| LimitedJson(limit).with(super::decompress_request());
+        let (json,) = run(&route(10_000), request().await).await.unwrap();
+        assert_eq!(json["text"], text);
+
+        // The compressed body fits, but the decompressed one doesn't.
+        let err = run(&route(data.len() as u64 - 1), request().await)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[cfg(all(
+        feature = "compression-brotli",
+        feature = "compression-gzip",
+        feature = "compression-zstd"
+    ))]
+    #[test]
+    fn accept_encoding_negotiated() {
+        let negotiate = |value| CompressionAlgo::negotiate(&HeaderValue::from_static(value));
+        assert_eq!(negotiate("gzip, br;q=0.9"), Some(CompressionAlgo::GZIP));
+        assert_eq!(negotiate("gzip, zstd, br"), Some(CompressionAlgo::BR));
+        assert_eq!(negotiate("deflate;q=0.5, x-gzip"), Some(CompressionAlgo::GZIP));
+        assert_eq!(negotiate("*;q=0.5, br;q=0, zstd;q=0.1"), Some(CompressionAlgo::GZIP));
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate("gzip;q=0, compress"), None);
+    }
+
+    /// Replies with its text, as a handler would.
+    #[derive(Clone)]
+    struct Text(String);
+
+    impl FilterBase for Text {
+        type Extract = (Response,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(Response,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let res = http::Response::builder()
+                .header(CONTENT_LENGTH, self.0.len())
+                .body(self.0.clone().into())
+                .unwrap();
+            future::ok((res,))
+        }
+    }
+
+    /// Check `filter` replies with `text` compressed with `algo`.
+    async fn check_compressed<F, R>(filter: F, algo: CompressionAlgo, text: &str)
+    where
+        F: FilterBase<Extract = (R,)>,
+        F::Future: Unpin,
+        F::Error: std::fmt::Debug,
+        R: Reply,
+    {
+        let (reply,) = run(&filter, Request::default()).await.unwrap();
+        let res = reply.into_response();
+        assert_eq!(res.headers()[CONTENT_ENCODING], algo.name());
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body = decoder(algo.name()).unwrap().decode(
+            body.into(),
+            DecodedBody::new(u64::MAX),
+            Default::default(),
+        );
+        assert_eq!(hyper::body::to_bytes(body).await.unwrap(), text, "{}", algo.name());
+    }
+
+    #[tokio::test]
+    async fn single_codec_wrappers_compress() {
+        let text = "hello compressed world\n".repeat(100);
+        let reply = || Text(text.clone());
+
+        #[cfg(feature = "compression-gzip")]
+        {
+            check_compressed(reply().with(super::gzip()), CompressionAlgo::GZIP, &text).await;
+            check_compressed(reply().with(super::deflate()), CompressionAlgo::DEFLATE, &text)
+                .await;
+        }
+        #[cfg(feature = "compression-brotli")]
+        check_compressed(reply().with(super::brotli()), CompressionAlgo::BR, &text).await;
+        #[cfg(feature = "compression-zstd")]
+        check_compressed(reply().with(super::zstd()), CompressionAlgo::ZSTD, &text).await;
+    }
+
+    #[tokio::test]
+    async fn auto_compresses_eligible_replies() {
+        let text = "hello compressed world\n".repeat(100);
+        let reply = |content_type, body: &str| -> Response {
+            http::Response::builder()
+                .header("content-type", content_type)
+                .header("content-length", body.len())
+                .body(body.to_owned().into())
+                .unwrap()
+        };
+        let algo = CompressionAlgo::PREFERRED[0];
+        let accept = HeaderValue::from_static(algo.name());
+        let auto = super::auto();
+
+        let res = auto.compress(Some(&accept), reply("text/plain", &text));
+        assert_eq!(res.headers()["content-encoding"], algo.name());
+        assert_eq!(res.headers()["vary"], "Accept-Encoding");
+        assert!(!res.headers().contains_key("content-length"));
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body = decoder(algo.name()).unwrap().decode(
+            body.into(),
+            DecodedBody::new(u64::MAX),
+            Default::default(),
+        );
+        assert_eq!(hyper::body::to_bytes(body).await.unwrap(), text);
+
+        let res = auto.compress(None, reply("application/json", &text));
+        assert!(!res.headers().contains_key("content-encoding"));
+        assert_eq!(res.headers()["vary"], "Accept-Encoding");
+
+        let res = auto.compress(Some(&accept), reply("text/plain", "tiny"));
+        assert!(!res.headers().contains_key("content-encoding"));
+        let res = auto.compress(Some(&accept), reply("image/png", &text));
+        assert!(!res.headers().contains_key("vary"));
+        let res = auto.compress(Some(&accept), reply("text/event-stream", &text));
+        assert!(!res.headers().contains_key("content-encoding"));
+
+        let mut encoded = reply("text/plain", &text);
+        encoded.headers_mut().insert("content-encoding", HeaderValue::from_static("br"));
+        let res = auto.compress(Some(&accept), encoded);
+        assert_eq!(res.headers()["content-encoding"], "br");
+    }
 }
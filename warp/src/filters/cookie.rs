@@ -1,13 +1,16 @@
 //! Cookie Filters
 
 use futures_util::future;
-use headers::Cookie;
+use headers::Cookie as CookieHeader;
+use http::header::{HeaderValue, COOKIE};
 
 use super::header;
-use crate::filter::{Filter, One};
+use crate::filter::{filter_fn_one, Filter, One};
 use crate::reject::Rejection;
 use std::convert::Infallible;
+use std::fmt;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 
 
@@ -16,7 +19,7 @@ pub fn cookie<T>(name: &'static str) -> impl Filter<Extract = One<T>, Error = Re
 where
     T: FromStr + Send + 'static,
 {
-    header::header2().and_then(move |cookie: Cookie| {
+    header::header2().and_then(move |cookie: CookieHeader| {
         let cookie = cookie
             .get(name)
             .ok_or_else(|| crate::reject::missing_cookie(name))
@@ -35,7 +38,7 @@ pub fn optional<T>(
 where
     T: FromStr + Send + 'static,
 {
-    header::optional2().map(move |opt: Option<Cookie>| {
+    header::optional2().map(move |opt: Option<CookieHeader>| {
         let cookie = opt.and_then(|cookie| cookie.get(name).map(|x| T::from_str(x)));
         match cookie {
             Some(Ok(t)) => Some(t),
@@ -44,3 +47,345 @@ where
         }
     })
 }
+
+/// Extract every cookie sent with the request, as a [`Jar`].
+///
+/// This never rejects: requests without cookies get an empty jar.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+/// use warp::cookie::Jar;
+///
+/// let route = warp::cookie::all()
+///     .map(|jar: Jar| format!("{} cookies", jar.len()));
+/// ```
+pub fn all() -> impl Filter<Extract = One<Jar>, Error = Infallible> + Copy {
+    filter_fn_one(|route| {
+        let jar = route
+            .headers()
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Jar>();
+        future::ok(jar)
+    })
+}
+
+/// The cookies sent with a request.
+///
+/// Created with [`all`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Jar {
+    cookies: Vec<(String, String)>,
+}
+
+impl Jar {
+    /// Get the value of the first cookie named `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Get the values of every cookie named `name`, in the order they were
+    /// sent.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |(n, _)| *n == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Iterate over the names and values of the cookies.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The number of cookies.
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    /// Whether no cookies were sent.
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+impl<'a> std::iter::FromIterator<&'a str> for Jar {
+    /// Parse `Cookie` header values, skipping malformed pairs.
+    fn from_iter<I: IntoIterator<Item = &'a str>>(headers: I) -> Jar {
+        let cookies = headers
+            .into_iter()
+            .flat_map(|header| header.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((name.to_owned(), value.to_owned()))
+            })
+            .collect();
+        Jar { cookies }
+    }
+}
+
+/// A cookie to set with a `Set-Cookie` header.
+///
+/// Added to a reply with [`warp::reply::with_cookie`](crate::reply::with_cookie).
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use warp::cookie::{Cookie, SameSite};
+///
+/// let cookie = Cookie::new("theme", "dark")
+///     .path("/")
+///     .max_age(Duration::from_secs(60 * 60 * 24 * 365))
+///     .same_site(SameSite::Lax);
+/// assert_eq!(
+///     cookie.to_string(),
+///     "theme=dark; Path=/; Max-Age=31536000; SameSite=Lax",
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+    partitioned: bool,
+}
+
+/// The `SameSite` attribute of a [`Cookie`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SameSite {
+    /// Only send the cookie with same-site requests.
+    Strict,
+    /// Also send the cookie when navigating to the site from elsewhere.
+    Lax,
+    /// Send the cookie with cross-site requests too. Browsers require
+    /// [`Secure`](Cookie::secure) with this.
+    None,
+}
+
+impl Cookie {
+    /// Create a cookie named `name` with `value`.
+    ///
+    /// Without [`max_age`](Cookie::max_age) or [`expires`](Cookie::expires),
+    /// the cookie lasts until the browser is closed.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Cookie {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+            partitioned: false,
+        }
+    }
+
+    /// Create a tombstone removing the cookie named `name`.
+    ///
+    /// It is empty and already expired. To remove a cookie set with a
+    /// `Path` or `Domain`, the same ones must be set on the tombstone.
+    pub fn removal(name: impl Into<String>) -> Cookie {
+        Cookie::new(name, "")
+            .max_age(Duration::from_secs(0))
+            .expires(SystemTime::UNIX_EPOCH)
+    }
+
+    /// Set the `Path` the cookie is sent for.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Set the `Domain` the cookie is sent to.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Set the `Max-Age` of the cookie, rounded down to seconds.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set when the cookie `Expires`. `Max-Age` takes precedence in
+    /// browsers supporting it.
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Set whether the cookie is only sent over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set whether the cookie is hidden from scripts.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Set the `SameSite` attribute of the cookie.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Set whether the cookie is `Partitioned`, that is, kept separately
+    /// for each top-level site embedding this one.
+    ///
+    /// Partitioned cookies are always `Secure`.
+    pub fn partitioned(mut self, partitioned: bool) -> Self {
+        self.partitioned = partitioned;
+        self
+    }
+
+    /// The name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Encode the cookie as a `Set-Cookie` header value.
+    ///
+    /// Returns `None` if the name isn't a token, the value has characters
+    /// not allowed in cookies, such as spaces, `;` or `,`, or an attribute
+    /// has control characters or `;`.
+    pub(crate) fn to_header_value(&self) -> Option<HeaderValue> {
+        let valid = !self.name.is_empty()
+            && self.name.bytes().all(is_token)
+            && self.value.bytes().all(is_cookie_octet)
+            && self.path.iter().chain(&self.domain).all(|attr| {
+                attr.bytes()
+                    .all(|b| (b' '..=b'~').contains(&b) && b != b';')
+            });
+        if !valid {
+            return None;
+        }
+        HeaderValue::from_str(&self.to_string()).ok()
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(ref path) = self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(ref domain) = self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", httpdate::fmt_http_date(expires))?;
+        }
+        if self.secure || self.partitioned {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            let same_site = match same_site {
+                SameSite::Strict => "Strict",
+                SameSite::Lax => "Lax",
+                SameSite::None => "None",
+            };
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        if self.partitioned {
+            f.write_str("; Partitioned")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_token(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Cookie, Jar, SameSite};
+
+    #[test]
+    fn set_cookie_attributes() {
+        let cookie = Cookie::new("id", "a3fWa")
+            .path("/docs")
+            .domain("example.com")
+            .max_age(Duration::from_secs(3600))
+            .expires(SystemTime::UNIX_EPOCH + Duration::from_secs(1_445_412_480))
+            .http_only(true)
+            .same_site(SameSite::None)
+            .partitioned(true);
+        assert_eq!(
+            cookie.to_string(),
+            "id=a3fWa; Path=/docs; Domain=example.com; Max-Age=3600; \
+             Expires=Wed, 21 Oct 2015 07:28:00 GMT; Secure; HttpOnly; SameSite=None; Partitioned"
+        );
+        assert!(cookie.to_header_value().is_some());
+
+        assert_eq!(
+            Cookie::removal("id").path("/").to_string(),
+            "id=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn invalid_cookies_not_encoded() {
+        assert!(Cookie::new("", "v").to_header_value().is_none());
+        assert!(Cookie::new("a b", "v").to_header_value().is_none());
+        assert!(Cookie::new("a", "x;y").to_header_value().is_none());
+        assert!(Cookie::new("a", "x y").to_header_value().is_none());
+        assert!(Cookie::new("a", "v").path("/;x").to_header_value().is_none());
+    }
+
+    #[test]
+    fn jar_parses_all_headers() {
+        let jar = ["a=1; b=\"two\"; junk", "a=3;c="].iter().copied().collect::<Jar>();
+        assert_eq!(jar.len(), 4);
+        assert_eq!(jar.get("a"), Some("1"));
+        assert_eq!(jar.get_all("a").collect::<Vec<_>>(), ["1", "3"]);
+        assert_eq!(jar.get("b"), Some("two"));
+        assert_eq!(jar.get("c"), Some(""));
+        assert_eq!(jar.get("junk"), None);
+    }
+}
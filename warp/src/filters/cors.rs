@@ -255,7 +255,7 @@ mod internal {
     use http::header;
     use pin_project::pin_project;
     use super::Configured;
-    use crate::filter::{Filter, FilterBase, Internal, One};
+    use crate::filter::{Describe, Filter, FilterBase, Internal, One};
     use crate::generic::Either;
     use crate::reject::{CombineRejection, Rejection};
     #[derive(Clone, Debug)]
@@ -281,6 +281,9 @@ mod internal {
         fn filter(&self, _: Internal) -> Self::Future {
             loop {}
         }
+        fn describe(&self, _: Internal) -> Describe {
+            self.inner.describe(Internal)
+        }
     }
     #[derive(Debug)]
     pub struct Preflight {
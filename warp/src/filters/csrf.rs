@@ -0,0 +1,549 @@
+//! CSRF Protection
+//!
+//! The [`protect`] wrapper defends against cross-site request forgery with
+//! double-submit tokens: a random token is set in a cookie, and requests
+//! with unsafe methods (anything but `GET`, `HEAD`, `OPTIONS` and `TRACE`)
+//! must send it back, either in the `X-CSRF-Token` header or in a
+//! `csrf_token` form field. Other sites can make browsers send the cookie,
+//! but can't read it to send the token as well.
+//!
+//! Unsafe requests must also come from the same origin: their `Origin`
+//! header (or `Referer`, if there's no `Origin`) must match the scheme, host
+//! and port the request was sent to. The host is found by
+//! [`host::optional`](crate::host::optional), and the scheme is taken from
+//! the request URI, or is `https` unless [`secure`](Protect::secure) is off.
+//!
+//! Forms rendered by the server get the token with [`token`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use warp::csrf::CsrfToken;
+//! use warp::Filter;
+//!
+//! let form = warp::get()
+//!     .and(warp::csrf::token())
+//!     .map(|token: CsrfToken| {
+//!         warp::reply::html(format!(
+//!             r#"<form method="post">
+//!                 <input type="hidden" name="csrf_token" value="{}">
+//!                 <button>Delete</button>
+//!             </form>"#,
+//!             token,
+//!         ))
+//!     });
+//! let delete = warp::post()
+//!     .and(warp::body::content_length_limit(4096))
+//!     .and(warp::body::form())
+//!     .map(|_form: HashMap<String, String>| "deleted");
+//!
+//! let admin = warp::path("admin")
+//!     .and(form.or(delete))
+//!     .with(warp::csrf::protect());
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use http::header::HeaderName;
+use http::uri::{Authority, Uri};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use self::internal::WithCsrf;
+use crate::filter::{Filter, WrapSealed};
+use crate::reject::{CombineRejection, Rejection};
+use crate::reply::Reply;
+
+/// Protect unsafe requests with a double-submit token and an origin check.
+///
+/// The token is read from the `csrf_token` cookie, or issued if missing.
+/// The cookie is `Secure`, `SameSite=Strict` and readable by scripts, so
+/// they can send it back in the `X-CSRF-Token` header.
+///
+/// Tokens in form fields are only looked for in
+/// `application/x-www-form-urlencoded` bodies without the header. The body
+/// is read, and put back for later filters. Bodies over the
+/// [`body_limit`](Protect::body_limit) are rejected with a
+/// `413 Payload Too Large`.
+///
+/// Requests failing the checks are rejected with a [`CsrfForbidden`],
+/// rendered as a `403 Forbidden`.
+pub fn protect() -> Protect {
+    Protect {
+        config: Arc::new(Config {
+            cookie_name: "csrf_token".to_owned(),
+            header: HeaderName::from_static("x-csrf-token"),
+            field: "csrf_token".to_owned(),
+            secure: true,
+            trusted_origins: Vec::new(),
+            body_limit: 64 * 1024,
+        }),
+    }
+}
+
+/// Extract the CSRF token of the current request, to embed in forms.
+///
+/// Rejects if the route isn't wrapped with [`protect`].
+pub fn token() -> impl Filter<Extract = (CsrfToken,), Error = Rejection> + Copy {
+    crate::ext::get::<CsrfToken>()
+}
+
+/// Decorates a [`Filter`] to protect it from cross-site request forgery.
+///
+/// Created with [`protect`].
+#[derive(Clone, Debug)]
+pub struct Protect {
+    config: Arc<Config>,
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+    cookie_name: String,
+    header: HeaderName,
+    field: String,
+    secure: bool,
+    trusted_origins: Vec<Authority>,
+    body_limit: u64,
+}
+
+impl Protect {
+    fn config(mut self, f: impl FnOnce(&mut Config)) -> Self {
+        f(Arc::make_mut(&mut self.config));
+        self
+    }
+
+    /// Set the name of the token cookie. Defaults to `csrf_token`.
+    pub fn cookie_name(self, name: impl Into<String>) -> Self {
+        self.config(|config| config.cookie_name = name.into())
+    }
+
+    /// Read the token from the header `name`. Defaults to `X-CSRF-Token`.
+    pub fn header(self, name: HeaderName) -> Self {
+        self.config(|config| config.header = name)
+    }
+
+    /// Read the token from the form field `name`. Defaults to `csrf_token`.
+    pub fn field(self, name: impl Into<String>) -> Self {
+        self.config(|config| config.field = name.into())
+    }
+
+    /// Set whether the token cookie is only sent over HTTPS. Defaults to
+    /// `true`.
+    ///
+    /// Unless the request URI says otherwise, this is also the scheme
+    /// expected of the origin: `https` if set, `http` if not.
+    pub fn secure(self, secure: bool) -> Self {
+        self.config(|config| config.secure = secure)
+    }
+
+    /// Also accept unsafe requests from `origin`, such as
+    /// `admin.example.com`, served with the same scheme as this site.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `origin` isn't a valid authority.
+    pub fn trusted_origin(self, origin: &str) -> Self {
+        let origin = origin.parse::<Authority>().expect("invalid trusted origin");
+        self.config(|config| config.trusted_origins.push(origin))
+    }
+
+    /// Set the size limit of form bodies read for a token, in bytes.
+    /// Defaults to 64 KiB.
+    pub fn body_limit(self, limit: u64) -> Self {
+        self.config(|config| config.body_limit = limit)
+    }
+}
+
+impl<F> WrapSealed<F> for Protect
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Future: Send,
+    F::Error: CombineRejection<Rejection>,
+{
+    type Wrapped = WithCsrf<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithCsrf {
+            filter,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// The CSRF token of a request.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CsrfToken(String);
+
+impl CsrfToken {
+    fn generate() -> CsrfToken {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        CsrfToken(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    fn from_cookie(value: &str) -> Option<CsrfToken> {
+        let valid = value.len() == 32 && value.bytes().all(|b| b.is_ascii_hexdigit());
+        if valid {
+            Some(CsrfToken(value.to_owned()))
+        } else {
+            None
+        }
+    }
+
+    /// Compare with a submitted token, in constant time.
+    fn matches(&self, submitted: &str) -> bool {
+        let (a, b) = (self.0.as_bytes(), submitted.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// The token as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CsrfToken").finish()
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Whether the `Origin` or `Referer` of a request is `host` or trusted,
+/// served over `scheme`.
+///
+/// Requests with neither header are let through, relying on the token.
+fn same_origin(
+    origin: Option<&str>,
+    referer: Option<&str>,
+    scheme: &str,
+    host: Option<&Authority>,
+    trusted: &[Authority],
+) -> bool {
+    let source = match origin.or(referer) {
+        Some(source) => source,
+        None => return true,
+    };
+    let source = match source.parse::<Uri>() {
+        Ok(source) => source,
+        Err(_) => return false,
+    };
+    let authority = match source.authority() {
+        Some(authority) => authority,
+        None => return false,
+    };
+    match source.scheme_str() {
+        Some(source) if source.eq_ignore_ascii_case(scheme) => (),
+        _ => return false,
+    }
+    let default_port = if scheme.eq_ignore_ascii_case("https") {
+        443
+    } else {
+        80
+    };
+    let port = |authority: &Authority| authority.port_u16().unwrap_or(default_port);
+    host.into_iter().chain(trusted).any(|expected| {
+        expected.host().eq_ignore_ascii_case(authority.host()) && port(expected) == port(authority)
+    })
+}
+
+/// A request was rejected by [`protect`].
+pub struct CsrfForbidden {
+    kind: Forbidden,
+}
+
+#[derive(Debug)]
+enum Forbidden {
+    CrossOrigin,
+    MissingToken,
+    InvalidToken,
+}
+
+impl fmt::Debug for CsrfForbidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CsrfForbidden").field(&self.kind).finish()
+    }
+}
+
+impl fmt::Display for CsrfForbidden {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Forbidden::CrossOrigin => f.write_str("Cross-origin request forbidden"),
+            Forbidden::MissingToken => f.write_str("CSRF token missing"),
+            Forbidden::InvalidToken => f.write_str("CSRF token invalid"),
+        }
+    }
+}
+
+impl StdError for CsrfForbidden {}
+
+mod internal {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use bytes::{Bytes, BytesMut};
+    use http::header::{CONTENT_LENGTH, CONTENT_TYPE, COOKIE, ORIGIN, REFERER};
+    use http::Method;
+    use hyper::body::HttpBody;
+    use hyper::Body;
+
+    use super::{same_origin, Config, CsrfForbidden, CsrfToken, Forbidden};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
+    use crate::filters::cookie::{Cookie, Jar, SameSite};
+    use crate::filters::host;
+    use crate::reject::{self, CombineRejection, Rejection};
+    use crate::reply::{self, Reply, Response};
+    use crate::route;
+
+    #[allow(missing_debug_implementations)]
+    pub struct Protected(Response);
+
+    impl Reply for Protected {
+        #[inline]
+        fn into_response(self) -> Response {
+            self.0
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone)]
+    pub struct WithCsrf<F> {
+        pub(super) filter: F,
+        pub(super) config: Arc<Config>,
+    }
+
+    impl<F> FilterBase for WithCsrf<F>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+        F::Future: Send,
+        F::Error: CombineRejection<Rejection>,
+    {
+        type Extract = (Protected,);
+        type Error = <F::Error as CombineRejection<Rejection>>::One;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let filter = self.filter.clone();
+            let config = self.config.clone();
+            Box::pin(async move {
+                let existing = route::with(|route| {
+                    route
+                        .headers()
+                        .get_all(COOKIE)
+                        .iter()
+                        .filter_map(|value| value.to_str().ok())
+                        .collect::<Jar>()
+                        .get(&config.cookie_name)
+                        .and_then(CsrfToken::from_cookie)
+                });
+                let issued = existing.is_none();
+                let token = existing.unwrap_or_else(CsrfToken::generate);
+                route::with(|route| route.extensions_mut().insert(token.clone()));
+
+                verify(&config, &token).await.map_err(Self::Error::from)?;
+
+                let reply = filter.filter(Internal).await.map_err(Self::Error::from)?;
+                if !issued {
+                    return Ok((Protected(reply.into_response()),));
+                }
+                let cookie = Cookie::new(config.cookie_name.clone(), token.0)
+                    .path("/")
+                    .secure(config.secure)
+                    .same_site(SameSite::Strict);
+                Ok((Protected(reply::with_cookie(reply, cookie).into_response()),))
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    fn forbidden(kind: Forbidden) -> Rejection {
+        tracing::debug!("csrf check failed: {:?}", kind);
+        reject::known(CsrfForbidden { kind })
+    }
+
+    async fn verify(config: &Config, token: &CsrfToken) -> Result<(), Rejection> {
+        let (safe, from_header) = route::with(|route| {
+            let safe = matches!(
+                *route.method(),
+                Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+            );
+            let header = route
+                .headers()
+                .get(&config.header)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned);
+            (safe, header)
+        });
+        if safe {
+            return Ok(());
+        }
+
+        let (host,) = host::optional().filter(Internal).await?;
+        let same_origin = route::with(|route| {
+            let header = |name| route.headers().get(name).and_then(|v| v.to_str().ok());
+            let scheme = match route.uri().scheme_str() {
+                Some(scheme) => scheme,
+                None if config.secure => "https",
+                None => "http",
+            };
+            same_origin(
+                header(ORIGIN),
+                header(REFERER),
+                scheme,
+                host.as_ref(),
+                &config.trusted_origins,
+            )
+        });
+        if !same_origin {
+            return Err(forbidden(Forbidden::CrossOrigin));
+        }
+
+        let submitted = match from_header {
+            Some(submitted) => Some(submitted),
+            None => from_form(config).await?,
+        };
+        match submitted {
+            Some(submitted) if token.matches(&submitted) => Ok(()),
+            Some(_) => Err(forbidden(Forbidden::InvalidToken)),
+            None => Err(forbidden(Forbidden::MissingToken)),
+        }
+    }
+
+    /// Find the token in a form body, putting the body back afterwards.
+    async fn from_form(config: &Config) -> Result<Option<String>, Rejection> {
+        let body = route::with(|route| {
+            let length = route
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            if let Some(length) = length.filter(|&length| length > config.body_limit) {
+                tracing::debug!("csrf form content-length {} is over limit", length);
+                return Err(reject::payload_too_large());
+            }
+            let is_form = route
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<mime::Mime>().ok())
+                .map_or(false, |ct| {
+                    ct.type_() == mime::APPLICATION && ct.subtype() == mime::WWW_FORM_URLENCODED
+                });
+            if is_form {
+                Ok(route.take_body())
+            } else {
+                Ok(None)
+            }
+        })?;
+        let body = match body {
+            Some(body) => body,
+            None => return Ok(None),
+        };
+        let bytes = read_limited(body, config.body_limit).await?;
+        let field = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+            .ok()
+            .and_then(|fields| {
+                fields
+                    .into_iter()
+                    .find(|(name, _)| *name == config.field)
+                    .map(|(_, value)| value)
+            });
+        route::with(|route| route.set_body(Body::from(bytes)));
+        Ok(field)
+    }
+
+    /// Read a whole body, rejecting once it grows over `limit` bytes.
+    pub(super) async fn read_limited(mut body: Body, limit: u64) -> Result<Bytes, Rejection> {
+        let mut bytes = BytesMut::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|err| {
+                tracing::debug!("csrf form body read error: {}", err);
+                reject::known(crate::body::BodyReadError(err))
+            })?;
+            if (bytes.len() + chunk.len()) as u64 > limit {
+                tracing::debug!("csrf form body is over limit {}", limit);
+                return Err(reject::payload_too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes.freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::uri::Authority;
+    use http::StatusCode;
+    use hyper::Body;
+
+    use super::internal::read_limited;
+    use super::{same_origin, CsrfToken};
+    use crate::reject::IsReject;
+
+    #[test]
+    fn tokens_compared() {
+        let token = CsrfToken::generate();
+        assert_eq!(token.as_str().len(), 32);
+        assert_ne!(token, CsrfToken::generate());
+        assert_eq!(CsrfToken::from_cookie(token.as_str()), Some(token.clone()));
+        assert!(token.matches(token.as_str()));
+        assert!(!token.matches(&token.as_str()[1..]));
+        assert!(!token.matches(&"0".repeat(32)));
+        assert_eq!(CsrfToken::from_cookie("not-a-token"), None);
+    }
+
+    #[test]
+    fn origins_checked() {
+        let host = "example.com".parse::<Authority>().unwrap();
+        let host = Some(&host);
+        let trusted = ["admin.example.com:8443".parse::<Authority>().unwrap()];
+
+        let https = |origin, referer| same_origin(origin, referer, "https", host, &[]);
+        let http = |origin, referer| same_origin(origin, referer, "http", host, &[]);
+
+        assert!(https(None, None));
+        assert!(https(Some("https://EXAMPLE.com"), None));
+        assert!(https(Some("https://example.com:443"), None));
+        assert!(https(None, Some("https://example.com/form?x=1")));
+        assert!(http(Some("http://example.com"), None));
+        let hosts = [host.unwrap().clone()];
+        assert!(same_origin(Some("https://example.com"), None, "https", None, &hosts));
+        assert!(same_origin(Some("https://admin.example.com:8443"), None, "https", host, &trusted));
+
+        assert!(!https(Some("http://example.com"), None));
+        assert!(!https(Some("http://example.com:443"), None));
+        assert!(!https(None, Some("http://example.com/form?x=1")));
+        assert!(!http(Some("https://example.com"), None));
+        assert!(!https(Some("https://example.com:8080"), None));
+        assert!(!https(Some("null"), None));
+        assert!(!https(Some("//example.com"), None));
+        assert!(!same_origin(Some("https://evil.com"), None, "https", host, &trusted));
+        assert!(!same_origin(Some("https://example.com"), None, "https", None, &[]));
+        assert!(!https(None, Some("https://evil.com/")));
+    }
+
+    #[tokio::test]
+    async fn form_bodies_limited() {
+        let chunks = || {
+            let chunks = vec![Ok::<_, std::io::Error>("csrf_token="), Ok("0123456789")];
+            Body::wrap_stream(futures_util::stream::iter(chunks))
+        };
+        assert_eq!(read_limited(chunks(), 21).await.unwrap(), "csrf_token=0123456789");
+        let err = read_limited(chunks(), 20).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -1,28 +1,36 @@
 //! File System Filters
 
 use std::convert::Infallible;
+use std::fmt;
 use std::fs::Metadata;
 use std::future::Future;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
+use std::time::{SystemTime, UNIX_EPOCH};
 use bytes::{Bytes, BytesMut};
 use futures_util::future::Either;
 use futures_util::{future, ready, stream, FutureExt, Stream, StreamExt, TryFutureExt};
 use headers::{
-    AcceptRanges, ContentLength, ContentRange, ContentType, HeaderMapExt,
-    IfModifiedSince, IfRange, IfUnmodifiedSince, LastModified, Range,
+    AcceptRanges, CacheControl, ContentLength, ContentRange, ContentType, ETag, HeaderMapExt,
+    IfMatch, IfModifiedSince, IfNoneMatch, IfRange, IfUnmodifiedSince, LastModified, Range,
 };
+use http::header::{HeaderValue, ACCEPT, CONTENT_ENCODING, CONTENT_TYPE, VARY};
 use http::StatusCode;
 use hyper::Body;
+use mime::Mime;
 use mime_guess;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 
 use tokio::fs::File as TkFile;
 use tokio::io::AsyncSeekExt;
-use tokio_util::io::poll_read_buf;
-use crate::filter::{Filter, FilterClone, One};
+use sha2::{Digest, Sha256};
+use tokio_util::io::{poll_read_buf, ReaderStream};
+use crate::filter::{filter_fn_one, Describe, Filter, FilterBase, FilterClone, Internal, One};
+use crate::filters::header::AcceptEncoding;
 use crate::reject::{self, Rejection};
 use crate::reply::{Reply, Response};
 
@@ -41,51 +49,611 @@ use crate::reply::{Reply, Response};
 
 
 
-pub fn file(
-    path: impl Into<PathBuf>,
-) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
-    let path = Arc::new(path.into());
-    crate::any()
-        .map(move || {
-            tracing::trace!("file: {:?}", path);
-            ArcPath(path.clone())
-        })
-        .and(conditionals())
-        .and_then(file_reply)
+pub fn file(path: impl Into<PathBuf>) -> ServeFile {
+    ServeFile {
+        path: Arc::new(path.into()),
+        tagging: Tagging::Weak,
+    }
+}
+
+/// A `Filter` serving a single file.
+///
+/// Created with [`file`].
+#[derive(Clone, Debug)]
+pub struct ServeFile {
+    path: Arc<PathBuf>,
+    tagging: Tagging,
+}
+
+impl ServeFile {
+    /// Tag the file with a strong `ETag`, hashed from its content.
+    ///
+    /// By default, weak `ETag`s are derived from the file's inode, size and
+    /// modification time. Strong ones are cached until the file changes, so
+    /// the content is only hashed once per version.
+    ///
+    /// Hashing reads the whole file, and each precompressed sidecar served,
+    /// before the reply is sent. The tags of up to 4096 files are cached;
+    /// past that, cached tags are evicted at random, and their files hashed
+    /// again when next served.
+    pub fn strong_etags(self) -> ServeFile {
+        ServeFile {
+            tagging: Tagging::strong(),
+            ..self
+        }
+    }
+
+    fn files(&self) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
+        let path = self.path.clone();
+        let tagging = self.tagging.clone();
+        crate::any()
+            .map(move || {
+                tracing::trace!("file: {:?}", path);
+                ArcPath(path.clone())
+            })
+            .and(conditionals())
+            .and_then(move |path, conditionals| file_reply(path, conditionals, tagging.clone()))
+    }
+}
+
+type ServeFut = Pin<Box<dyn Future<Output = Result<One<File>, Rejection>> + Send>>;
+
+impl FilterBase for ServeFile {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFut;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        Box::pin(self.files().filter(Internal))
+    }
+
+    fn describe(&self, _: Internal) -> Describe {
+        self.files().describe(Internal)
+    }
+}
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+pub fn dir(path: impl Into<PathBuf>) -> Dir {
+    Dir {
+        base: Arc::new(path.into()),
+        precompressed: Arc::new([]),
+        autoindex: false,
+        show_hidden: false,
+        spa_fallback: None,
+        tagging: Tagging::Weak,
+    }
+}
+
+/// A `Filter` serving files from a directory.
+///
+/// Created with [`dir`].
+#[derive(Clone, Debug)]
+pub struct Dir {
+    base: Arc<PathBuf>,
+    precompressed: Arc<[Encoding]>,
+    autoindex: bool,
+    show_hidden: bool,
+    spa_fallback: Option<Arc<PathBuf>>,
+    tagging: Tagging,
+}
+
+impl Dir {
+    /// Serve precompressed sidecar files, such as `app.js.br` for `app.js`.
+    ///
+    /// The first of `encodings` with a sidecar, among those the request's
+    /// `Accept-Encoding` prefers most, is served with a `Content-Encoding`
+    /// and the original file's `Content-Type`. Conditional and range
+    /// requests are answered from the sidecar. Otherwise the original file
+    /// is served as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use warp::fs::Encoding;
+    ///
+    /// let route = warp::fs::dir("./static").precompressed(&[Encoding::Brotli, Encoding::Gzip]);
+    /// ```
+    pub fn precompressed(self, encodings: &[Encoding]) -> Dir {
+        Dir {
+            precompressed: encodings.into(),
+            ..self
+        }
+    }
+
+    /// List the contents of directories that have no `index.html`.
+    ///
+    /// Listings are HTML tables of each entry's name, size and modification
+    /// time, which can be sorted with the `sort` (`name`, `size` or
+    /// `modified`) and `order` (`asc` or `desc`) query parameters. Requests
+    /// preferring `Accept: application/json` get the listing as JSON
+    /// instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::path("artifacts")
+    ///     .and(warp::fs::dir("./artifacts").autoindex().show_hidden(true));
+    /// ```
+    pub fn autoindex(self) -> Dir {
+        Dir {
+            autoindex: true,
+            ..self
+        }
+    }
+
+    /// Set whether listings include hidden files, whose names start with
+    /// a `.`.
+    ///
+    /// Defaults to `false`.
+    pub fn show_hidden(self, show: bool) -> Dir {
+        Dir {
+            show_hidden: show,
+            ..self
+        }
+    }
+
+    /// Serve `path`, relative to the directory, for navigation requests
+    /// that match no file, as single-page apps do for client-side routes.
+    ///
+    /// Navigation requests are those accepting `text/html` whose last path
+    /// segment has no file extension. Other requests, such as for a missing
+    /// `/assets/app.js`, are still rejected as not found. The fallback is
+    /// sent with `Cache-Control: no-cache`, so clients revalidate it.
+    ///
+    /// Since whether a path gets the fallback depends on the request's
+    /// `Accept`, every reply from the directory then has `Vary: Accept`,
+    /// so caches don't serve a file's reply for a navigation or vice versa.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let route = warp::fs::dir("./dist").spa_fallback("index.html");
+    /// ```
+    pub fn spa_fallback(self, path: impl Into<PathBuf>) -> Dir {
+        Dir {
+            spa_fallback: Some(Arc::new(path.into())),
+            ..self
+        }
+    }
+
+    /// Tag files with strong `ETag`s, hashed from their content.
+    ///
+    /// See [`ServeFile::strong_etags`].
+    pub fn strong_etags(self) -> Dir {
+        Dir {
+            tagging: Tagging::strong(),
+            ..self
+        }
+    }
+
+    fn files(&self) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
+        let dir = self.clone();
+        crate::get()
+            .or(crate::head())
+            .unify()
+            .and(path_from_tail(self.base.clone()))
+            .and(conditionals())
+            .and(crate::header::optional::<String>("accept-encoding"))
+            .and(listing_request())
+            .and_then(move |path: ArcPath, conditionals: Conditionals, accept: Option<_>, listing| {
+                let dir = dir.clone();
+                async move {
+                    let fallback = dir
+                        .spa_fallback
+                        .clone()
+                        .filter(|_| is_navigation(&listing))
+                        .map(|fallback| (fallback, conditionals.clone(), accept.clone()));
+                    let reply = dir_reply(
+                        path.clone(),
+                        conditionals,
+                        accept,
+                        dir.precompressed.clone(),
+                        dir.tagging.clone(),
+                    )
+                    .await;
+                    let reply = match reply {
+                        Err(rejection) if dir.autoindex && rejection.is_not_found() => {
+                            autoindex_reply(path, listing, &dir.base, dir.show_hidden)
+                                .await
+                                .ok_or(rejection)
+                        }
+                        reply => reply,
+                    };
+                    let mut file = match (reply, fallback) {
+                        (Err(rejection), Some((fallback, conditionals, accept)))
+                            if rejection.is_not_found() =>
+                        {
+                            let path = ArcPath(Arc::new(dir.base.join(fallback.as_ref())));
+                            tracing::trace!("dir: spa fallback to {:?}", path.as_ref());
+                            let mut file = dir_reply(
+                                path,
+                                conditionals,
+                                accept,
+                                dir.precompressed,
+                                dir.tagging,
+                            )
+                            .await?;
+                            let headers = file.resp.headers_mut();
+                            headers.typed_insert(CacheControl::new().with_no_cache());
+                            file
+                        }
+                        (reply, _) => reply?,
+                    };
+                    if dir.spa_fallback.is_some() {
+                        let headers = file.resp.headers_mut();
+                        if !headers.get_all(VARY).iter().any(|value| value == "Accept") {
+                            headers.append(VARY, HeaderValue::from_static("Accept"));
+                        }
+                    }
+                    Ok::<_, Rejection>(file)
+                }
+            })
+    }
 }
 
+impl FilterBase for Dir {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFut;
 
+    fn filter(&self, _: Internal) -> Self::Future {
+        Box::pin(self.files().filter(Internal))
+    }
 
+    fn describe(&self, _: Internal) -> Describe {
+        self.files().describe(Internal)
+    }
+}
 
+/// The encoding of a precompressed sidecar file.
+///
+/// See [`Dir::precompressed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// `br`, from files ending in `.br`.
+    Brotli,
+    /// `gzip`, from files ending in `.gz`.
+    Gzip,
+    /// `zstd`, from files ending in `.zst`.
+    Zstd,
+}
 
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Zstd => "zstd",
+        }
+    }
 
+    fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => ".br",
+            Encoding::Gzip => ".gz",
+            Encoding::Zstd => ".zst",
+        }
+    }
 
+    fn sidecar(self, path: &Path) -> PathBuf {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(self.extension());
+        sidecar.into()
+    }
+}
+
+async fn dir_reply(
+    path: ArcPath,
+    conditionals: Conditionals,
+    accept: Option<String>,
+    precompressed: Arc<[Encoding]>,
+    tagging: Tagging,
+) -> Result<File, Rejection> {
+    if precompressed.is_empty() {
+        return file_reply(path, conditionals, tagging).await;
+    }
+    let accept = accept.unwrap_or_default();
+    let ranked = AcceptEncoding::parse(&accept).ranked(&precompressed, Encoding::name);
+    for encoding in ranked {
+        let sidecar = encoding.sidecar(path.as_ref());
+        let f = match TkFile::open(&sidecar).await {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+        match f.metadata().await {
+            Ok(meta) if meta.is_file() => (),
+            _ => continue,
+        }
+        tracing::trace!("dir: serving precompressed {:?}", sidecar);
+        let mut file = file_conditional(f, path, sidecar, conditionals, tagging).await?;
+        let headers = file.resp.headers_mut();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+        headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+        return Ok(file);
+    }
+    let mut file = file_reply(path, conditionals, tagging).await?;
+    file.resp
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+    Ok(file)
+}
+/// A file compiled into the binary, served by [`embedded`].
+///
+/// Tables of these are generated by [`embed_dir`] from a build script, and
+/// included with [`include_embedded!`](crate::include_embedded).
+#[derive(Clone, Copy)]
+pub struct EmbeddedFile {
+    path: &'static str,
+    contents: &'static [u8],
+    etag: &'static str,
+}
+
+impl EmbeddedFile {
+    /// Create an embedded file at `path`, relative to the directory it was
+    /// embedded from and separated by `/`, with the quoted strong `etag`.
+    pub const fn new(path: &'static str, contents: &'static [u8], etag: &'static str) -> Self {
+        EmbeddedFile {
+            path,
+            contents,
+            etag,
+        }
+    }
+
+    /// The path of this file, relative to the directory it was embedded from.
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+}
 
+impl fmt::Debug for EmbeddedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedFile")
+            .field("path", &self.path)
+            .field("len", &self.contents.len())
+            .field("etag", &self.etag)
+            .finish()
+    }
+}
 
+/// Creates a `Filter` that serves files compiled into the binary.
+///
+/// Files are matched by the rest of the request path, as with [`dir`],
+/// with `index.html` served for directories. They are answered like files
+/// on disk, with the same content types, conditional requests and ranges,
+/// and tagged with strong `ETag`s hashed when they were embedded.
+///
+/// # Example
+///
+/// In the `main` of `build.rs`:
+///
+/// ```no_run
+/// warp::fs::embed_dir("ui", "ui/dist").unwrap();
+/// ```
+///
+/// And in the crate:
+///
+/// ```ignore
+/// use warp::fs::{EmbeddedFile, Encoding};
+/// use warp::Filter;
+///
+/// static UI: &[EmbeddedFile] = warp::include_embedded!("ui");
+///
+/// let route = warp::path("ui").and(warp::fs::embedded(UI).precompressed(&[Encoding::Brotli]));
+/// ```
+pub fn embedded(files: &'static [EmbeddedFile]) -> Embedded {
+    Embedded {
+        files: Arc::new(files.iter().map(|file| (file.path, file)).collect()),
+        precompressed: Arc::new([]),
+    }
+}
 
+/// A `Filter` serving files compiled into the binary.
+///
+/// Created with [`embedded`].
+#[derive(Clone, Debug)]
+pub struct Embedded {
+    files: Arc<HashMap<&'static str, &'static EmbeddedFile>>,
+    precompressed: Arc<[Encoding]>,
+}
 
+impl Embedded {
+    /// Serve embedded precompressed variants, such as `app.js.br` for
+    /// `app.js`.
+    ///
+    /// See [`Dir::precompressed`].
+    pub fn precompressed(self, encodings: &[Encoding]) -> Embedded {
+        Embedded {
+            precompressed: encodings.into(),
+            ..self
+        }
+    }
 
+    fn files(&self) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
+        let embedded = self.clone();
+        crate::get()
+            .or(crate::head())
+            .unify()
+            .and(crate::path::tail())
+            .and(conditionals())
+            .and(crate::header::optional::<String>("accept-encoding"))
+            .and_then(move |tail: crate::path::Tail, conditionals, accept| {
+                future::ready(embedded.reply(tail.as_str(), conditionals, accept))
+            })
+    }
 
+    fn lookup(&self, tail: &str) -> Option<&'static EmbeddedFile> {
+        let path = percent_decode_str(tail).decode_utf8().ok()?;
+        if path.is_empty() || path.ends_with('/') {
+            return self.files.get(format!("{}index.html", path).as_str()).copied();
+        }
+        self.files
+            .get(path.as_ref())
+            .or_else(|| self.files.get(format!("{}/index.html", path).as_str()))
+            .copied()
+    }
 
+    fn reply(
+        &self,
+        tail: &str,
+        conditionals: Conditionals,
+        accept: Option<String>,
+    ) -> Result<File, Rejection> {
+        let file = self.lookup(tail).ok_or_else(|| {
+            tracing::debug!("embedded file not found: {:?}", tail);
+            reject::not_found()
+        })?;
+        let accept = accept.unwrap_or_default();
+        let (encoding, served) = AcceptEncoding::parse(&accept)
+            .ranked(&self.precompressed, Encoding::name)
+            .into_iter()
+            .find_map(|encoding| {
+                let sidecar = format!("{}{}", file.path, encoding.extension());
+                let sidecar = self.files.get(sidecar.as_str())?;
+                Some((Some(encoding), *sidecar))
+            })
+            .unwrap_or((None, file));
 
+        let etag = served.etag.parse::<ETag>().ok();
+        let mut resp = match conditionals.check(None, etag.as_ref()) {
+            Cond::NoBody(resp) => resp,
+            Cond::WithBody(range) => {
+                let len = served.contents.len() as u64;
+                let mime = mime_guess::from_path(file.path).first_or_octet_stream();
+                body_response(Source::Static(served.contents), range, len, mime, None)
+            }
+        };
+        insert_etag(&mut resp, etag);
+        let headers = resp.headers_mut();
+        if let Some(encoding) = encoding {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+        }
+        if !self.precompressed.is_empty() {
+            headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+        }
+        Ok(File {
+            resp,
+            path: ArcPath(Arc::new(file.path.into())),
+        })
+    }
+}
 
+impl FilterBase for Embedded {
+    type Extract = One<File>;
+    type Error = Rejection;
+    type Future = ServeFut;
 
+    fn filter(&self, _: Internal) -> Self::Future {
+        Box::pin(self.files().filter(Internal))
+    }
 
+    fn describe(&self, _: Internal) -> Describe {
+        self.files().describe(Internal)
+    }
+}
 
+/// Embed the files under `dir` for [`embedded`], from a build script.
+///
+/// The table of files is written to `$OUT_DIR/{name}.rs`, to be included
+/// with [`include_embedded!`](crate::include_embedded), and Cargo is told
+/// to rerun the build script when anything under `dir` changes.
+pub fn embed_dir(name: &str, dir: impl AsRef<Path>) -> io::Result<()> {
+    let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "OUT_DIR is not set, embed_dir runs in build scripts",
+        )
+    })?;
+    let dir = dir.as_ref().canonicalize()?;
+    let table = embedded_table(&dir)?;
+    println!("cargo:rerun-if-changed={}", dir.display());
+    std::fs::write(Path::new(&out_dir).join(format!("{}.rs", name)), table)
+}
 
+/// The source of a table of the files under `dir`, each hashed for its
+/// `ETag` and included with `include_bytes!`.
+fn embedded_table(dir: &Path) -> io::Result<String> {
+    let mut paths = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(next) = dirs.pop() {
+        for entry in std::fs::read_dir(next)? {
+            let path = entry?.path();
+            let meta = std::fs::metadata(&path)?;
+            if meta.is_dir() {
+                dirs.push(path);
+            } else if meta.is_file() {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
 
+    let mut table = String::from("&[\n");
+    for path in paths {
+        let relative = path
+            .strip_prefix(dir)
+            .expect("walked from dir")
+            .iter()
+            .map(|part| part.to_str())
+            .collect::<Option<Vec<_>>>();
+        let (relative, absolute) = match (relative, path.to_str()) {
+            (Some(relative), Some(absolute)) => (relative.join("/"), absolute),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("embedded path is not UTF-8: {:?}", path),
+                ))
+            }
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(std::fs::read(&path)?);
+        table.push_str(&format!(
+            "    EmbeddedFile::new({:?}, include_bytes!({:?}), {:?}),\n",
+            relative,
+            absolute,
+            digest_tag(hasher),
+        ));
+    }
+    table.push(']');
+    Ok(table)
+}
 
-pub fn dir(
-    path: impl Into<PathBuf>,
-) -> impl FilterClone<Extract = One<File>, Error = Rejection> {
-    let base = Arc::new(path.into());
-    crate::get()
-        .or(crate::head())
-        .unify()
-        .and(path_from_tail(base))
-        .and(conditionals())
-        .and_then(file_reply)
+/// Include a table of files embedded by [`fs::embed_dir`](crate::fs::embed_dir)
+/// under `name`, for [`fs::embedded`](crate::fs::embedded).
+///
+/// # Example
+///
+/// ```ignore
+/// static UI: &[warp::fs::EmbeddedFile] = warp::include_embedded!("ui");
+/// ```
+#[macro_export]
+macro_rules! include_embedded {
+    ($name:literal) => {{
+        use $crate::fs::EmbeddedFile;
+        const FILES: &[EmbeddedFile] = include!(concat!(env!("OUT_DIR"), "/", $name, ".rs"));
+        FILES
+    }};
 }
+
 fn path_from_tail(
     base: Arc<PathBuf>,
 ) -> impl FilterClone<Extract = One<ArcPath>, Error = Rejection> {
@@ -107,10 +675,35 @@ fn path_from_tail(
         })
 }
 fn sanitize_path(base: impl AsRef<Path>, tail: &str) -> Result<PathBuf, Rejection> {
-    loop {}
+    let mut buf = PathBuf::from(base.as_ref());
+    let p = match percent_decode_str(tail).decode_utf8() {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::debug!("dir: failed to decode route={:?}: {:?}", tail, err);
+            return Err(reject::not_found());
+        }
+    };
+    tracing::trace!("dir? base={:?}, route={:?}", base.as_ref(), p);
+    for seg in p.split('/') {
+        if seg.starts_with("..") {
+            tracing::warn!("dir: rejecting segment starting with '..'");
+            return Err(reject::not_found());
+        } else if seg.contains('\\') {
+            tracing::warn!("dir: rejecting segment containing backslash (\\)");
+            return Err(reject::not_found());
+        } else if cfg!(windows) && seg.contains(':') {
+            tracing::warn!("dir: rejecting segment containing colon (:)");
+            return Err(reject::not_found());
+        } else {
+            buf.push(seg);
+        }
+    }
+    Ok(buf)
 }
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct Conditionals {
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
     if_modified_since: Option<IfModifiedSince>,
     if_unmodified_since: Option<IfUnmodifiedSince>,
     if_range: Option<IfRange>,
@@ -121,8 +714,52 @@ enum Cond {
     WithBody(Option<Range>),
 }
 impl Conditionals {
-    fn check(self, last_modified: Option<LastModified>) -> Cond {
-        loop {}
+    /// Evaluate the preconditions in the order of RFC 9110, section 13.2.2.
+    fn check(self, last_modified: Option<LastModified>, etag: Option<&ETag>) -> Cond {
+        let modified = last_modified.map(SystemTime::from);
+        let no_body = |status| {
+            let mut res = Response::new(Body::empty());
+            *res.status_mut() = status;
+            Cond::NoBody(res)
+        };
+
+        if let Some(if_match) = self.if_match {
+            let matched = if_match.is_any()
+                || etag.map_or(false, |etag| if_match.precondition_passes(etag));
+            if !matched {
+                tracing::debug!("if-match precondition failed");
+                return no_body(StatusCode::PRECONDITION_FAILED);
+            }
+        } else if let Some(since) = self.if_unmodified_since {
+            let unmodified = modified.map_or(false, |time| since.precondition_passes(time));
+            if !unmodified {
+                tracing::debug!("if-unmodified-since precondition failed");
+                return no_body(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+
+        if let Some(if_none_match) = self.if_none_match {
+            let matched = if_none_match == IfNoneMatch::any()
+                || etag.map_or(false, |etag| !if_none_match.precondition_passes(etag));
+            if matched {
+                tracing::trace!("if-none-match matched, not modified");
+                return no_body(StatusCode::NOT_MODIFIED);
+            }
+        } else if let Some(since) = self.if_modified_since {
+            if modified.map_or(false, |time| !since.is_modified(time)) {
+                tracing::trace!("if-modified-since, not modified");
+                return no_body(StatusCode::NOT_MODIFIED);
+            }
+        }
+
+        if let Some(if_range) = self.if_range {
+            if if_range.is_modified(etag, last_modified.as_ref()) {
+                tracing::trace!("if-range changed, sending the full file");
+                return Cond::WithBody(None);
+            }
+        }
+
+        Cond::WithBody(self.range)
     }
 }
 fn conditionals() -> impl Filter<
@@ -133,12 +770,20 @@ fn conditionals() -> impl Filter<
         .and(crate::header::optional2())
         .and(crate::header::optional2())
         .and(crate::header::optional2())
-        .map(|if_modified_since, if_unmodified_since, if_range, range| Conditionals {
-            if_modified_since,
-            if_unmodified_since,
-            if_range,
-            range,
-        })
+        .and(crate::header::optional2())
+        .and(crate::header::optional2())
+        .map(
+            |if_match, if_none_match, if_modified_since, if_unmodified_since, if_range, range| {
+                Conditionals {
+                    if_match,
+                    if_none_match,
+                    if_modified_since,
+                    if_unmodified_since,
+                    if_range,
+                    range,
+                }
+            },
+        )
 }
 
 #[derive(Debug)]
@@ -167,28 +812,32 @@ impl File {
     
     
     pub fn path(&self) -> &Path {
-        loop {}
+        self.path.as_ref()
     }
 }
 #[derive(Clone, Debug)]
 struct ArcPath(Arc<PathBuf>);
 impl AsRef<Path> for ArcPath {
     fn as_ref(&self) -> &Path {
-        loop {}
+        (*self.0).as_ref()
     }
 }
 impl Reply for File {
     fn into_response(self) -> Response {
-        loop {}
+        self.resp
     }
 }
 fn file_reply(
     path: ArcPath,
     conditionals: Conditionals,
+    tagging: Tagging,
 ) -> impl Future<Output = Result<File, Rejection>> + Send {
     TkFile::open(path.clone())
         .then(move |res| match res {
-            Ok(f) => Either::Left(file_conditional(f, path, conditionals)),
+            Ok(f) => {
+                let served = path.as_ref().to_owned();
+                Either::Left(file_conditional(f, path, served, conditionals, tagging))
+            }
             Err(err) => {
                 let rej = match err.kind() {
                     io::ErrorKind::NotFound => {
@@ -214,61 +863,737 @@ fn file_reply(
         })
 }
 async fn file_metadata(f: TkFile) -> Result<(TkFile, Metadata), Rejection> {
-    loop {}
+    match f.metadata().await {
+        Ok(meta) => Ok((f, meta)),
+        Err(err) => {
+            tracing::debug!("file metadata error: {}", err);
+            Err(reject::not_found())
+        }
+    }
 }
+/// Reply with the `served` file `f`, which is `path` or a sidecar of it.
 fn file_conditional(
     f: TkFile,
     path: ArcPath,
+    served: PathBuf,
     conditionals: Conditionals,
+    tagging: Tagging,
 ) -> impl Future<Output = Result<File, Rejection>> + Send {
     file_metadata(f)
-        .map_ok(move |(file, meta)| {
-            let mut len = meta.len();
+        .and_then(move |(mut file, meta)| async move {
+            let etag = tagging.tag(&mut file, &served, &meta).await;
+            Ok((file, meta, etag))
+        })
+        .map_ok(move |(file, meta, etag)| {
+            let len = meta.len();
             let modified = meta.modified().ok().map(LastModified::from);
-            let resp = match conditionals.check(modified) {
+            let mut resp = match conditionals.check(modified, etag.as_ref()) {
                 Cond::NoBody(resp) => resp,
                 Cond::WithBody(range) => {
-                    bytes_range(range, len)
-                        .map(|(start, end)| {
-                            let sub_len = end - start;
-                            let buf_size = optimal_buf_size(&meta);
-                            let stream = file_stream(file, buf_size, (start, end));
-                            let body = Body::wrap_stream(stream);
-                            let mut resp = Response::new(body);
-                            if sub_len != len {
-                                *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
-                                resp.headers_mut()
-                                    .typed_insert(
-                                        ContentRange::bytes(start..end, len)
-                                            .expect("valid ContentRange"),
-                                    );
-                                len = sub_len;
-                            }
-                            let mime = mime_guess::from_path(path.as_ref())
-                                .first_or_octet_stream();
-                            resp.headers_mut().typed_insert(ContentLength(len));
-                            resp.headers_mut().typed_insert(ContentType::from(mime));
-                            resp.headers_mut().typed_insert(AcceptRanges::bytes());
-                            if let Some(last_modified) = modified {
-                                resp.headers_mut().typed_insert(last_modified);
-                            }
-                            resp
-                        })
-                        .unwrap_or_else(|BadRange| {
-                            let mut resp = Response::new(Body::empty());
-                            *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
-                            resp.headers_mut()
-                                .typed_insert(ContentRange::unsatisfied_bytes(len));
-                            resp
-                        })
+                    let source = Source::File(file, optimal_buf_size(&meta));
+                    let mime = mime_guess::from_path(path.as_ref()).first_or_octet_stream();
+                    body_response(source, range, len, mime, modified)
                 }
             };
+            insert_etag(&mut resp, etag);
             File { resp, path }
         })
 }
+
+/// Where the bytes of a reply's body are read from.
+enum Source {
+    /// An open file, read `buf_size` bytes at a time.
+    File(TkFile, usize),
+    /// Bytes compiled into the binary.
+    Static(&'static [u8]),
+}
+
+impl Source {
+    fn range(self, (start, end): (u64, u64)) -> Body {
+        match self {
+            Source::File(file, buf_size) => {
+                Body::wrap_stream(file_stream(file, buf_size, (start, end)))
+            }
+            Source::Static(bytes) => Body::from(&bytes[start as usize..end as usize]),
+        }
+    }
+
+    fn byteranges(self, parts: ByteRanges) -> Body {
+        match self {
+            Source::File(file, buf_size) => Body::wrap_stream(parts.into_stream(file, buf_size)),
+            Source::Static(bytes) => {
+                let trailer = parts.trailer;
+                let chunks = parts
+                    .parts
+                    .into_iter()
+                    .flat_map(move |(head, (start, end))| {
+                        let part = Bytes::from_static(&bytes[start as usize..end as usize]);
+                        [head, part]
+                    })
+                    .chain(Some(trailer))
+                    .map(Ok::<_, io::Error>);
+                Body::wrap_stream(stream::iter(chunks))
+            }
+        }
+    }
+}
+
+/// Respond with the `len` bytes of `source`, or the parts of them `range`
+/// asks for.
+fn body_response(
+    source: Source,
+    range: Option<Range>,
+    len: u64,
+    mime: Mime,
+    modified: Option<LastModified>,
+) -> Response {
+    let ranges = match bytes_ranges(range, len) {
+        Ok(ranges) => ranges,
+        Err(BadRange) => {
+            let mut resp = Response::new(Body::empty());
+            *resp.status_mut() = StatusCode::RANGE_NOT_SATISFIABLE;
+            resp.headers_mut().typed_insert(ContentRange::unsatisfied_bytes(len));
+            return resp;
+        }
+    };
+    let mut resp = match ranges[..] {
+        [(start, end)] => {
+            let mut resp = Response::new(source.range((start, end)));
+            if end - start != len {
+                *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+                resp.headers_mut().typed_insert(
+                    ContentRange::bytes(start..end, len).expect("valid ContentRange"),
+                );
+            }
+            resp.headers_mut().typed_insert(ContentLength(end - start));
+            resp.headers_mut().typed_insert(ContentType::from(mime));
+            resp
+        }
+        _ => {
+            let parts = ByteRanges::new(&ranges, len, &mime);
+            let length = parts.content_length();
+            let content_type = parts.content_type();
+            let mut resp = Response::new(source.byteranges(parts));
+            *resp.status_mut() = StatusCode::PARTIAL_CONTENT;
+            resp.headers_mut().typed_insert(ContentLength(length));
+            resp.headers_mut().insert(CONTENT_TYPE, content_type);
+            resp
+        }
+    };
+    resp.headers_mut().typed_insert(AcceptRanges::bytes());
+    if let Some(last_modified) = modified {
+        resp.headers_mut().typed_insert(last_modified);
+    }
+    resp
+}
+
+/// Tag successful and not modified responses with `etag`.
+fn insert_etag(resp: &mut Response, etag: Option<ETag>) {
+    let tagged = matches!(
+        resp.status(),
+        StatusCode::OK | StatusCode::PARTIAL_CONTENT | StatusCode::NOT_MODIFIED
+    );
+    if let (true, Some(etag)) = (tagged, etag) {
+        resp.headers_mut().typed_insert(etag);
+    }
+}
+
+/// How files are tagged with an `ETag`.
+#[derive(Clone, Debug)]
+enum Tagging {
+    /// Weak tags, from the file's inode, size and modification time.
+    Weak,
+    /// Strong tags, from a hash of the file's content, cached by path along
+    /// with the version of the file it was hashed from.
+    Strong(Arc<Mutex<TagCache>>),
+}
+
+/// The most strong tags cached by a [`Tagging::Strong`].
+const MAX_CACHED_TAGS: usize = 4096;
+
+#[derive(Debug)]
+struct TagCache {
+    tags: HashMap<PathBuf, (String, ETag)>,
+    max: usize,
+}
+
+impl TagCache {
+    fn insert(&mut self, path: PathBuf, version: String, etag: ETag) {
+        if self.tags.len() >= self.max && !self.tags.contains_key(&path) {
+            // The map's iteration order is random, so this evicts an
+            // arbitrary tag.
+            if let Some(evicted) = self.tags.keys().next().cloned() {
+                self.tags.remove(&evicted);
+            }
+        }
+        self.tags.insert(path, (version, etag));
+    }
+}
+
+impl Tagging {
+    fn strong() -> Tagging {
+        Tagging::strong_with_max(MAX_CACHED_TAGS)
+    }
+
+    fn strong_with_max(max: usize) -> Tagging {
+        Tagging::Strong(Arc::new(Mutex::new(TagCache {
+            tags: HashMap::new(),
+            max,
+        })))
+    }
+
+    async fn tag(&self, file: &mut TkFile, served: &Path, meta: &Metadata) -> Option<ETag> {
+        let version = file_version(meta);
+        let cache = match self {
+            Tagging::Weak => return format!("W/\"{}\"", version).parse().ok(),
+            Tagging::Strong(cache) => cache,
+        };
+        if let Some((cached, etag)) = cache.lock().unwrap().tags.get(served) {
+            if *cached == version {
+                return Some(etag.clone());
+            }
+        }
+        let etag = match content_tag(file).await {
+            Ok(etag) => etag,
+            Err(err) => {
+                tracing::debug!("etag: cannot hash {:?}: {}", served, err);
+                return None;
+            }
+        };
+        cache
+            .lock()
+            .unwrap()
+            .insert(served.to_owned(), version, etag.clone());
+        Some(etag)
+    }
+}
+
+/// Identify the version of a file from its metadata.
+fn file_version(meta: &Metadata) -> String {
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let mut version = format!(
+        "{:x}-{:x}.{:x}",
+        meta.len(),
+        modified.as_secs(),
+        modified.subsec_nanos()
+    );
+    if let Some(inode) = get_inode(meta) {
+        version = format!("{:x}-{}", inode, version);
+    }
+    version
+}
+
+/// Hash the content of `file`, leaving it rewound.
+async fn content_tag(file: &mut TkFile) -> io::Result<ETag> {
+    let mut hasher = Sha256::new();
+    let mut chunks = ReaderStream::new(&mut *file);
+    while let Some(chunk) = chunks.next().await {
+        hasher.update(&chunk?);
+    }
+    file.seek(io::SeekFrom::Start(0)).await?;
+    Ok(digest_tag(hasher).parse().expect("valid ETag"))
+}
+
+/// The quoted `ETag` for content hashed by `hasher`.
+fn digest_tag(hasher: Sha256) -> String {
+    let digest = hasher.finalize();
+    let hex = digest[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    format!("\"{}\"", hex)
+}
+/// What a directory listing is rendered from, besides the directory, and
+/// what decides if a SPA fallback is served.
+#[derive(Debug)]
+struct ListingRequest {
+    path: String,
+    query: Option<String>,
+    accept: Option<String>,
+}
+
+fn listing_request() -> impl Filter<Extract = One<ListingRequest>, Error = Infallible> + Copy {
+    filter_fn_one(|route| {
+        future::ok(ListingRequest {
+            path: route.full_path().to_owned(),
+            query: route.query().map(str::to_owned),
+            accept: route
+                .headers()
+                .get(ACCEPT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned),
+        })
+    })
+}
+
+/// List the directory `path` was resolved to, if it named a directory
+/// without an `index.html`. Listings of subdirectories of `root` link to
+/// their parent.
+async fn autoindex_reply(
+    path: ArcPath,
+    request: ListingRequest,
+    root: &Path,
+    show_hidden: bool,
+) -> Option<File> {
+    let path: &Path = path.as_ref();
+    if path.file_name()? != "index.html" || request.path.ends_with("/index.html") {
+        return None;
+    }
+    let dir = path.parent()?;
+    let mut entries = match read_listing(dir, show_hidden).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            tracing::debug!("autoindex: cannot list {:?}: {}", dir, err);
+            return None;
+        }
+    };
+    let sort = Sort::from_query(request.query.as_deref().unwrap_or_default());
+    sort.apply(&mut entries);
+
+    let mut base = request.path;
+    if !base.ends_with('/') {
+        base.push('/');
+    }
+    let (body, content_type) = if wants_json(request.accept.as_deref()) {
+        (render_json(&base, &entries), "application/json")
+    } else {
+        (render_html(&base, &entries, sort, dir != root), "text/html; charset=utf-8")
+    };
+    let mut resp = Response::new(Body::from(body));
+    let headers = resp.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(VARY, HeaderValue::from_static("Accept"));
+    Some(File {
+        resp,
+        path: ArcPath(Arc::new(dir.to_owned())),
+    })
+}
+
+/// An entry of a directory listing.
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+async fn read_listing(dir: &Path, show_hidden: bool) -> io::Result<Vec<Entry>> {
+    let mut read = tokio::fs::read_dir(dir).await?;
+    let mut entries = Vec::new();
+    while let Some(entry) = read.next_entry().await? {
+        let name = match entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(name) => {
+                tracing::debug!("autoindex: skipping non-UTF-8 name {:?}", name);
+                continue;
+            }
+        };
+        if !show_hidden && name.starts_with('.') {
+            continue;
+        }
+        // Follow symlinks, like serving the entry would.
+        let meta = match tokio::fs::metadata(entry.path()).await {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        entries.push(Entry {
+            name,
+            is_dir: meta.is_dir(),
+            size: if meta.is_dir() { 0 } else { meta.len() },
+            modified: meta.modified().ok(),
+        });
+    }
+    Ok(entries)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortBy {
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortBy {
+    fn as_str(self) -> &'static str {
+        match self {
+            SortBy::Name => "name",
+            SortBy::Size => "size",
+            SortBy::Modified => "modified",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Sort {
+    by: SortBy,
+    descending: bool,
+}
+
+impl Sort {
+    fn from_query(query: &str) -> Sort {
+        let mut sort = Sort {
+            by: SortBy::Name,
+            descending: false,
+        };
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            match (key, value) {
+                ("sort", "name") => sort.by = SortBy::Name,
+                ("sort", "size") => sort.by = SortBy::Size,
+                ("sort", "modified") => sort.by = SortBy::Modified,
+                ("order", "asc") => sort.descending = false,
+                ("order", "desc") => sort.descending = true,
+                _ => (),
+            }
+        }
+        sort
+    }
+
+    /// Sort `entries`, keeping directories before files.
+    fn apply(self, entries: &mut [Entry]) {
+        entries.sort_by(|a, b| {
+            let ordering = match self.by {
+                SortBy::Name => a.name.cmp(&b.name),
+                SortBy::Size => a.size.cmp(&b.size).then_with(|| a.name.cmp(&b.name)),
+                SortBy::Modified => a.modified.cmp(&b.modified).then_with(|| a.name.cmp(&b.name)),
+            };
+            let ordering = if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            };
+            b.is_dir.cmp(&a.is_dir).then(ordering)
+        });
+    }
+
+    /// The query sorting by `by`, reversing the order if already sorted by it.
+    fn query_for(self, by: SortBy) -> String {
+        let order = if self.by == by && !self.descending {
+            "desc"
+        } else {
+            "asc"
+        };
+        format!("?sort={}&order={}", by.as_str(), order)
+    }
+}
+
+/// The quality the `Accept` header gives `media_type`, ignoring wildcards.
+fn accept_quality(accept: Option<&str>, media_type: &str) -> f32 {
+    accept
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';').map(str::trim);
+            if !params.next()?.eq_ignore_ascii_case(media_type) {
+                return None;
+            }
+            Some(match params.find_map(|param| param.strip_prefix("q=")) {
+                Some(q) => q.parse::<f32>().unwrap_or(0.0),
+                None => 1.0,
+            })
+        })
+        .next()
+        .unwrap_or(0.0)
+}
+
+/// Whether `application/json` is preferred over `text/html`.
+fn wants_json(accept: Option<&str>) -> bool {
+    let json = accept_quality(accept, "application/json");
+    json > 0.0 && json > accept_quality(accept, "text/html")
+}
+
+/// Whether a request is a browser navigation, which accepts HTML and names
+/// no file with an extension, rather than a request for an asset.
+fn is_navigation(request: &ListingRequest) -> bool {
+    let last = request.path.rsplit('/').next().unwrap_or_default();
+    accept_quality(request.accept.as_deref(), "text/html") > 0.0
+        && Path::new(last).extension().is_none()
+}
+
+/// Characters escaped in a path segment.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+fn href(base: &str, entry: &Entry) -> String {
+    let mut href = format!("{}{}", base, utf8_percent_encode(&entry.name, SEGMENT));
+    if entry.is_dir {
+        href.push('/');
+    }
+    href
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_html(base: &str, entries: &[Entry], sort: Sort, parent: bool) -> String {
+    use std::fmt::Write;
+
+    let title = escape_html(&percent_decode_str(base).decode_utf8_lossy());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Index of {title}</title>\n</head>\n<body>\n<h1>Index of {title}</h1>\n\
+         <table>\n<thead><tr>",
+        title = title,
+    );
+    for (by, label) in [
+        (SortBy::Name, "Name"),
+        (SortBy::Size, "Size"),
+        (SortBy::Modified, "Modified"),
+    ] {
+        let _ = write!(
+            html,
+            "<th><a href=\"{}\">{}</a></th>",
+            escape_html(&sort.query_for(by)),
+            label
+        );
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    if parent {
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}../\">../</a></td><td></td><td></td></tr>",
+            escape_html(base)
+        );
+    }
+    for entry in entries {
+        let size = if entry.is_dir {
+            "-".to_owned()
+        } else {
+            entry.size.to_string()
+        };
+        let modified = entry
+            .modified
+            .map(httpdate::fmt_http_date)
+            .unwrap_or_default();
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>",
+            escape_html(&href(base, entry)),
+            escape_html(&entry.name),
+            if entry.is_dir { "/" } else { "" },
+            size,
+            modified
+        );
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+fn render_json(base: &str, entries: &[Entry]) -> String {
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            serde_json::json!({
+                "name": entry.name,
+                "href": href(base, entry),
+                "type": if entry.is_dir { "directory" } else { "file" },
+                "size": if entry.is_dir { None } else { Some(entry.size) },
+                "modified": entry
+                    .modified
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_secs()),
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "path": base, "entries": entries }).to_string()
+}
+
+/// The most ranges answered in one response, after coalescing. Requests for
+/// more are served the full file instead.
+const MAX_RANGES: usize = 16;
+
 struct BadRange;
-fn bytes_range(range: Option<Range>, max_len: u64) -> Result<(u64, u64), BadRange> {
-    loop {}
+
+/// Resolve the `Range` header against a file of `max_len` bytes.
+///
+/// Unsatisfiable ranges are dropped, and overlapping or adjacent ones are
+/// coalesced, so the returned ranges are sorted and disjoint.
+fn bytes_ranges(range: Option<Range>, max_len: u64) -> Result<Vec<(u64, u64)>, BadRange> {
+    use std::ops::Bound;
+
+    let range = if let Some(range) = range {
+        range
+    } else {
+        return Ok(vec![(0, max_len)]);
+    };
+
+    let mut specs = 0;
+    let mut ranges = range
+        .iter()
+        .inspect(|_| specs += 1)
+        .filter_map(|bounds| {
+            let (start, end) = match bounds {
+                (Bound::Included(start), Bound::Included(end)) if start <= end => {
+                    (start, end.saturating_add(1).min(max_len))
+                }
+                (Bound::Included(start), Bound::Unbounded) => (start, max_len),
+                (Bound::Unbounded, Bound::Included(suffix)) => {
+                    (max_len.saturating_sub(suffix), max_len)
+                }
+                _ => return None,
+            };
+            if start < end {
+                Some((start, end))
+            } else {
+                tracing::trace!("unsatisfiable byte range: {}-{}/{}", start, end, max_len);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if specs == 0 {
+        return Ok(vec![(0, max_len)]);
+    }
+
+    ranges.sort_unstable();
+    let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match coalesced.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => coalesced.push((start, end)),
+        }
+    }
+
+    if coalesced.is_empty() {
+        Err(BadRange)
+    } else if coalesced.len() > MAX_RANGES {
+        tracing::debug!("too many byte ranges ({}), sending the full file", coalesced.len());
+        Ok(vec![(0, max_len)])
+    } else {
+        Ok(coalesced)
+    }
+}
+
+/// The layout of a `multipart/byteranges` body.
+///
+/// Each part's delimiter and headers are built up front, so the length is
+/// known before any of the file is read.
+struct ByteRanges {
+    boundary: String,
+    parts: Vec<(Bytes, (u64, u64))>,
+    trailer: Bytes,
+}
+
+impl ByteRanges {
+    fn new(ranges: &[(u64, u64)], len: u64, mime: &Mime) -> ByteRanges {
+        ByteRanges::with_boundary(ranges, len, mime, boundary())
+    }
+
+    fn with_boundary(
+        ranges: &[(u64, u64)],
+        len: u64,
+        mime: &Mime,
+        boundary: String,
+    ) -> ByteRanges {
+        let parts = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, end))| {
+                let head = format!(
+                    "{}--{}\r\ncontent-type: {}\r\ncontent-range: bytes {}-{}/{}\r\n\r\n",
+                    if i == 0 { "" } else { "\r\n" },
+                    boundary,
+                    mime,
+                    start,
+                    end - 1,
+                    len,
+                );
+                (Bytes::from(head), (start, end))
+            })
+            .collect();
+        let trailer = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+        ByteRanges {
+            boundary,
+            parts,
+            trailer,
+        }
+    }
+
+    fn content_type(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!("multipart/byteranges; boundary={}", self.boundary))
+            .expect("valid boundary")
+    }
+
+    fn content_length(&self) -> u64 {
+        let parts = self
+            .parts
+            .iter()
+            .map(|(head, (start, end))| head.len() as u64 + (end - start))
+            .sum::<u64>();
+        parts + self.trailer.len() as u64
+    }
+
+    /// Stream each part's headers followed by its range of `file`.
+    fn into_stream(
+        self,
+        file: TkFile,
+        buf_size: usize,
+    ) -> impl Stream<Item = Result<Bytes, io::Error>> + Send {
+        use std::io::SeekFrom;
+        use tokio::io::AsyncReadExt;
+
+        let state = (file, self.parts.into_iter(), Some(self.trailer), 0, BytesMut::new());
+        stream::try_unfold(state, move |(mut file, mut parts, mut trailer, left, mut buf)| {
+            async move {
+                if left == 0 {
+                    return match parts.next() {
+                        Some((head, (start, end))) => {
+                            file.seek(SeekFrom::Start(start)).await?;
+                            Ok(Some((head, (file, parts, trailer, end - start, buf))))
+                        }
+                        None => Ok(trailer
+                            .take()
+                            .map(|trailer| (trailer, (file, parts, None, 0, buf)))),
+                    };
+                }
+                reserve_at_least(&mut buf, buf_size);
+                let n = file.read_buf(&mut buf).await? as u64;
+                if n == 0 {
+                    tracing::debug!("file read found EOF before expected length");
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                let mut chunk = buf.split().freeze();
+                let left = if n > left {
+                    chunk.truncate(left as usize);
+                    0
+                } else {
+                    left - n
+                };
+                Ok(Some((chunk, (file, parts, trailer, left, buf))))
+            }
+        })
+    }
+}
+
+/// A random boundary, unlikely to appear in the file.
+fn boundary() -> String {
+    format!("{:032x}", rand::random::<u128>())
 }
 fn file_stream(
     mut file: TkFile,
@@ -323,19 +1648,34 @@ fn file_stream(
         .flatten()
 }
 fn reserve_at_least(buf: &mut BytesMut, cap: usize) {
-    loop {}
+    if buf.capacity() - buf.len() < cap {
+        buf.reserve(cap);
+    }
 }
 const DEFAULT_READ_BUF_SIZE: usize = 8_192;
 fn optimal_buf_size(metadata: &Metadata) -> usize {
-    loop {}
+    let block_size = get_block_size(metadata);
+    // If the file is smaller than a block, don't reserve a bigger buffer
+    // than needed.
+    std::cmp::min(block_size as u64, metadata.len()) as usize
 }
 #[cfg(unix)]
 fn get_block_size(metadata: &Metadata) -> usize {
-    loop {}
+    use std::os::unix::fs::MetadataExt;
+    std::cmp::max(metadata.blksize() as usize, DEFAULT_READ_BUF_SIZE)
 }
 #[cfg(not(unix))]
 fn get_block_size(_metadata: &Metadata) -> usize {
-    loop {}
+    DEFAULT_READ_BUF_SIZE
+}
+#[cfg(unix)]
+fn get_inode(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+#[cfg(not(unix))]
+fn get_inode(_metadata: &Metadata) -> Option<u64> {
+    None
 }
 unit_error! {
     pub (crate) FileOpenError : "file open error"
@@ -345,8 +1685,102 @@ unit_error! {
 }
 #[cfg(test)]
 mod tests {
-    use super::sanitize_path;
+    use std::future::Future;
+    use std::path::{Path, PathBuf};
+    use std::pin::Pin;
+    use std::time::{Duration, SystemTime};
+
+    use futures_util::future::poll_fn;
+    use hyper::Body;
+
+    use headers::{
+        ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince,
+        LastModified, Range,
+    };
+    use http::header::{CONTENT_ENCODING, CONTENT_TYPE};
+    use http::StatusCode;
+
+    use super::{
+        body_response, boundary, bytes_ranges, embedded, embedded_table, is_navigation,
+        read_listing, render_html, render_json, sanitize_path, wants_json, ByteRanges, Cond,
+        Conditionals, EmbeddedFile, Encoding, Entry, ListingRequest, Sort, SortBy, Source,
+        Tagging, MAX_RANGES,
+    };
+    use crate::filter::{FilterBase, Internal};
+    use crate::filters::header::AcceptEncoding;
+    use crate::reject::Rejection;
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
     use bytes::BytesMut;
+
+    /// A directory of files under the system's temporary directory, removed
+    /// when dropped. Paths ending with a `/` are created as directories.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[(&str, &str)]) -> Fixture {
+            let dir = std::env::temp_dir().join(format!("warp-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            for &(path, contents) in files {
+                let path = dir.join(path);
+                if path.to_str().unwrap().ends_with('/') {
+                    std::fs::create_dir_all(&path).unwrap();
+                } else {
+                    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    std::fs::write(&path, contents).unwrap();
+                }
+            }
+            Fixture(dir)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// The headers of a browser navigating to a page.
+    const NAVIGATION: &[(&str, &str)] = &[("accept", "text/html,*/*;q=0.8")];
+
+    /// `GET` `path` from `filter`, with `headers`.
+    async fn get<F>(filter: &F, path: &str, headers: &[(&str, &str)]) -> Result<Response, Rejection>
+    where
+        F: FilterBase<Error = Rejection>,
+        F::Extract: Reply,
+    {
+        let mut req = http::Request::get(path);
+        for &(name, value) in headers {
+            req = req.header(name, value);
+        }
+        let route = Route::new(req.body(Body::empty()).unwrap(), None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        let extract = poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx))).await?;
+        Ok(extract.into_response())
+    }
+
+    async fn body(res: Response) -> String {
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    fn vary(res: &Response) -> Vec<&str> {
+        let vary = res.headers().get_all(http::header::VARY);
+        vary.iter().map(|value| value.to_str().unwrap()).collect()
+    }
+
+    /// Conditionals of a request without any conditional headers.
+    fn unconditional() -> Conditionals {
+        Conditionals {
+            if_match: None,
+            if_none_match: None,
+            if_modified_since: None,
+            if_unmodified_since: None,
+            if_range: None,
+            range: None,
+        }
+    }
+
     #[test]
     fn test_sanitize_path() {
         loop {}
@@ -355,4 +1789,474 @@ mod tests {
     fn test_reserve_at_least() {
         loop {}
     }
+    #[test]
+    fn precompressed_sidecars_ranked() {
+        let encodings = [Encoding::Brotli, Encoding::Gzip, Encoding::Zstd];
+        let ranked = |accept| AcceptEncoding::parse(accept).ranked(&encodings, Encoding::name);
+        assert_eq!(ranked("gzip, br"), [Encoding::Brotli, Encoding::Gzip]);
+        assert_eq!(ranked("gzip, br;q=0.5"), [Encoding::Gzip, Encoding::Brotli]);
+        assert_eq!(ranked("*, br;q=0"), [Encoding::Gzip, Encoding::Zstd]);
+        assert_eq!(ranked(""), []);
+
+        let path = Path::new("static/app.js");
+        assert_eq!(Encoding::Brotli.sidecar(path), Path::new("static/app.js.br"));
+        assert_eq!(Encoding::Gzip.sidecar(path), Path::new("static/app.js.gz"));
+        assert_eq!(Encoding::Zstd.sidecar(path), Path::new("static/app.js.zst"));
+    }
+
+    #[test]
+    fn listings_sorted_and_rendered() {
+        let entry = |name: &str, is_dir, size| Entry {
+            name: name.to_owned(),
+            is_dir,
+            size,
+            modified: None,
+        };
+        let mut entries = vec![
+            entry("b.txt", false, 1),
+            entry("a <&> b.txt", false, 20),
+            entry("docs", true, 0),
+        ];
+        let sort = Sort::from_query("sort=size&order=desc");
+        assert_eq!(sort.by, SortBy::Size);
+        sort.apply(&mut entries);
+        let names = entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["docs", "a <&> b.txt", "b.txt"]);
+
+        let html = render_html("/files/", &entries, sort, true);
+        let escaped = r#"<a href="/files/a%20%3C&amp;%3E%20b.txt">a &lt;&amp;&gt; b.txt</a>"#;
+        assert!(html.contains(escaped));
+        assert!(html.contains(r#"<a href="/files/docs/">docs/</a>"#));
+        assert!(html.contains(r#"<a href="/files/../">../</a>"#));
+        assert!(html.contains(r#"<a href="?sort=size&amp;order=asc">Size</a>"#));
+        let html = render_html("/files/", &entries, sort, false);
+        assert!(!html.contains("../"));
+
+        let json = render_json("/files/", &entries);
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["entries"][0]["type"], "directory");
+        assert_eq!(json["entries"][0]["size"], serde_json::Value::Null);
+        assert_eq!(json["entries"][1]["size"], 20);
+        assert_eq!(json["entries"][2]["href"], "/files/b.txt");
+
+        assert!(wants_json(Some("application/json")));
+        assert!(!wants_json(Some("text/html, application/json;q=0.9")));
+        assert!(!wants_json(Some("*/*")));
+        assert!(!wants_json(None));
+    }
+
+    #[test]
+    fn spa_fallback_only_for_navigation() {
+        let request = |path: &str, accept: Option<&str>| ListingRequest {
+            path: path.to_owned(),
+            query: None,
+            accept: accept.map(str::to_owned),
+        };
+        let html = Some("text/html,application/xhtml+xml,*/*;q=0.8");
+
+        assert!(is_navigation(&request("/", html)));
+        assert!(is_navigation(&request("/users/42/settings", html)));
+        assert!(is_navigation(&request("/v1.2/about", html)));
+        assert!(!is_navigation(&request("/assets/missing.js", html)));
+        assert!(!is_navigation(&request("/users/42", Some("application/json"))));
+        assert!(!is_navigation(&request("/users/42", Some("text/html;q=0"))));
+        assert!(!is_navigation(&request("/users/42", None)));
+    }
+
+    #[tokio::test]
+    async fn dir_serves_files() {
+        let fixture = Fixture::new(
+            "dir",
+            &[("hello.txt", "hello"), ("docs/index.html", "<h1>docs</h1>")],
+        );
+        let files = super::dir(fixture.0.clone());
+
+        let res = get(&files, "/hello.txt", &[]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/plain");
+        let etag = res.headers()[http::header::ETAG].to_str().unwrap();
+        assert!(etag.starts_with("W/"), "{}", etag);
+        assert_eq!(body(res).await, "hello");
+
+        let res = get(&files, "/docs/", &[]).await.unwrap();
+        assert_eq!(body(res).await, "<h1>docs</h1>");
+
+        for path in &["/missing.txt", "/../hello.txt", "/.%2e/hello.txt"] {
+            let err = get(&files, path, &[]).await.unwrap_err();
+            assert!(err.is_not_found(), "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn dir_serves_precompressed_files() {
+        let fixture = Fixture::new(
+            "precompressed",
+            &[("app.js", "let app;"), ("app.js.br", "brotli")],
+        );
+        let files = super::dir(fixture.0.clone()).precompressed(&[Encoding::Brotli]);
+
+        let res = get(&files, "/app.js", &[("accept-encoding", "gzip, br")]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_ENCODING], "br");
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/javascript");
+        assert_eq!(vary(&res), ["Accept-Encoding"]);
+        assert_eq!(body(res).await, "brotli");
+
+        let res = get(&files, "/app.js", &[("accept-encoding", "gzip")]).await.unwrap();
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(vary(&res), ["Accept-Encoding"]);
+        assert_eq!(body(res).await, "let app;");
+    }
+
+    #[tokio::test]
+    async fn dir_lists_directories() {
+        let fixture = Fixture::new(
+            "listing",
+            &[("top.txt", "top"), (".secret", ""), ("sub/nested.txt", "nested")],
+        );
+        let files = super::dir(fixture.0.clone()).autoindex();
+
+        let res = get(&files, "/", NAVIGATION).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CONTENT_TYPE], "text/html; charset=utf-8");
+        assert_eq!(vary(&res), ["Accept"]);
+        let html = body(res).await;
+        assert!(!html.contains("../"), "{}", html);
+        assert!(html.contains("top.txt"));
+        assert!(html.contains("sub/"));
+        assert!(!html.contains(".secret"));
+
+        let res = get(&files, "/sub/", NAVIGATION).await.unwrap();
+        let html = body(res).await;
+        assert!(html.contains(r#"<a href="/sub/../">../</a>"#), "{}", html);
+        assert!(html.contains("nested.txt"));
+
+        let res = get(&files, "/", &[("accept", "application/json")]).await.unwrap();
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/json");
+        let json: serde_json::Value = serde_json::from_str(&body(res).await).unwrap();
+        let mut names = json["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["sub", "top.txt"]);
+
+        let files = super::dir(fixture.0.clone()).autoindex().show_hidden(true);
+        let res = get(&files, "/", NAVIGATION).await.unwrap();
+        assert!(body(res).await.contains(".secret"));
+    }
+
+    #[tokio::test]
+    async fn dir_tags_files_strongly() {
+        let fixture = Fixture::new("strong", &[("hello.txt", "hello")]);
+        let files = super::dir(fixture.0.clone()).strong_etags();
+
+        let res = get(&files, "/hello.txt", &[]).await.unwrap();
+        let etag = res.headers()[http::header::ETAG].to_str().unwrap().to_owned();
+        assert!(etag.starts_with('"'), "{}", etag);
+
+        let res = get(&files, "/hello.txt", &[("if-none-match", &etag)]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn spa_fallback_varies_every_reply() {
+        let fixture = Fixture::new(
+            "spa",
+            &[("index.html", "<h1>app</h1>"), ("assets/app.js", "let app;")],
+        );
+        let plain = super::dir(fixture.0.clone());
+        let res = get(&plain, "/assets/app.js", &[]).await.unwrap();
+        assert!(vary(&res).is_empty());
+
+        let spa = super::dir(fixture.0.clone()).spa_fallback("index.html");
+        let res = get(&spa, "/assets/app.js", &[]).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(vary(&res), ["Accept"]);
+        assert!(res.headers().get(http::header::CACHE_CONTROL).is_none());
+
+        let res = get(&spa, "/users/42", NAVIGATION).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(vary(&res), ["Accept"]);
+        assert_eq!(res.headers()[http::header::CACHE_CONTROL], "no-cache");
+        assert_eq!(body(res).await, "<h1>app</h1>");
+
+        let err = get(&spa, "/assets/missing.js", NAVIGATION).await.unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[tokio::test]
+    async fn listings_hide_hidden_files() {
+        let fixture = Fixture::new(
+            "hidden",
+            &[("sub/", ""), ("visible.txt", "hi"), (".hidden", "")],
+        );
+
+        let names = |entries: Vec<Entry>| {
+            let mut names = entries.into_iter().map(|e| e.name).collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let listed = read_listing(&fixture.0, false).await.unwrap();
+        assert_eq!(names(listed), ["sub", "visible.txt"]);
+        let listed = read_listing(&fixture.0, true).await.unwrap();
+        assert_eq!(names(listed), [".hidden", "sub", "visible.txt"]);
+    }
+
+    #[test]
+    fn preconditions_evaluated_in_order() {
+        let etag = "\"v2\"".parse::<ETag>().unwrap();
+        let other = "\"v1\"".parse::<ETag>().unwrap();
+        let weak = "W/\"v2\"".parse::<ETag>().unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let before = modified - Duration::from_secs(10);
+        let status = |conditionals: Conditionals, etag: Option<&ETag>| {
+            match conditionals.check(Some(LastModified::from(modified)), etag) {
+                Cond::NoBody(res) => res.status(),
+                Cond::WithBody(_) => StatusCode::OK,
+            }
+        };
+
+        // If-Match takes precedence over If-Unmodified-Since.
+        let conditionals = Conditionals {
+            if_match: Some(IfMatch::from(etag.clone())),
+            if_unmodified_since: Some(IfUnmodifiedSince::from(before)),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&etag)), StatusCode::OK);
+        let conditionals = Conditionals {
+            if_match: Some(IfMatch::from(etag.clone())),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&weak)), StatusCode::PRECONDITION_FAILED);
+        let conditionals = Conditionals {
+            if_unmodified_since: Some(IfUnmodifiedSince::from(before)),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&etag)), StatusCode::PRECONDITION_FAILED);
+
+        // If-None-Match takes precedence over If-Modified-Since.
+        let conditionals = Conditionals {
+            if_none_match: Some(IfNoneMatch::from(other)),
+            if_modified_since: Some(IfModifiedSince::from(modified)),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&etag)), StatusCode::OK);
+        let conditionals = Conditionals {
+            if_none_match: Some(IfNoneMatch::from(weak)),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&etag)), StatusCode::NOT_MODIFIED);
+        let conditionals = Conditionals {
+            if_modified_since: Some(IfModifiedSince::from(modified)),
+            ..unconditional()
+        };
+        assert_eq!(status(conditionals, Some(&etag)), StatusCode::NOT_MODIFIED);
+    }
+
+    #[tokio::test]
+    async fn etags_tagged_from_files() {
+        let fixture = Fixture::new(
+            "etags",
+            &[("a.txt", "same content"), ("b.txt", "same content")],
+        );
+        let (a, b) = (fixture.0.join("a.txt"), fixture.0.join("b.txt"));
+
+        let tag = |tagging: Tagging, path: std::path::PathBuf| async move {
+            let mut file = tokio::fs::File::open(&path).await.unwrap();
+            let meta = file.metadata().await.unwrap();
+            tagging.tag(&mut file, &path, &meta).await.unwrap()
+        };
+        // Only strong tags match strongly.
+        let is_strong = |etag: &ETag| IfMatch::from(etag.clone()).precondition_passes(etag);
+        let weak = tag(Tagging::Weak, a.clone()).await;
+        assert!(!is_strong(&weak));
+        assert_ne!(weak, tag(Tagging::Weak, b.clone()).await);
+
+        let strong = Tagging::strong();
+        let tagged = tag(strong.clone(), a.clone()).await;
+        assert!(is_strong(&tagged));
+        assert_eq!(tagged, tag(strong.clone(), a.clone()).await);
+        assert_eq!(tagged, tag(strong, b.clone()).await);
+
+        let bounded = Tagging::strong_with_max(1);
+        assert_eq!(tagged, tag(bounded.clone(), a.clone()).await);
+        assert_eq!(tagged, tag(bounded.clone(), b.clone()).await);
+        assert_eq!(tagged, tag(bounded.clone(), a.clone()).await);
+        if let Tagging::Strong(cache) = bounded {
+            let cache = cache.lock().unwrap();
+            assert_eq!(cache.tags.keys().collect::<Vec<_>>(), [&a]);
+        }
+    }
+
+    #[test]
+    fn byte_ranges_resolved_and_coalesced() {
+        let ranges = |spec: &str| {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(http::header::RANGE, spec.parse().unwrap());
+            bytes_ranges(headers.typed_get::<Range>(), 100).ok()
+        };
+
+        assert_eq!(bytes_ranges(None, 100).ok(), Some(vec![(0, 100)]));
+        assert_eq!(ranges("bytes=10-19"), Some(vec![(10, 20)]));
+        assert_eq!(ranges("bytes=90-"), Some(vec![(90, 100)]));
+        assert_eq!(ranges("bytes=-5"), Some(vec![(95, 100)]));
+        assert_eq!(ranges("bytes=95-200"), Some(vec![(95, 100)]));
+        assert_eq!(ranges("bytes=50-59, 0-9"), Some(vec![(0, 10), (50, 60)]));
+        assert_eq!(ranges("bytes=0-9, 5-14, 15-19, 30-39"), Some(vec![(0, 20), (30, 40)]));
+        assert_eq!(ranges("bytes=0-9, 200-300"), Some(vec![(0, 10)]));
+        assert_eq!(ranges("bytes=200-300"), None);
+        assert_eq!(ranges("bytes=-0"), None);
+        assert_eq!(ranges("bytes=x"), Some(vec![(0, 100)]));
+
+        let many = (0..=MAX_RANGES as u64)
+            .map(|i| format!("{}-{}", i * 2, i * 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(ranges(&format!("bytes={}", many)), Some(vec![(0, 100)]));
+    }
+
+    #[test]
+    fn byteranges_layout() {
+        let parts = ByteRanges::with_boundary(
+            &[(0, 3), (10, 12)],
+            20,
+            &mime::TEXT_PLAIN,
+            "XYZ".to_owned(),
+        );
+        assert_eq!(parts.content_type(), "multipart/byteranges; boundary=XYZ");
+
+        let heads = parts.parts.iter().map(|(head, _)| head.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            heads[0],
+            "--XYZ\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-2/20\r\n\r\n"
+        );
+        assert_eq!(
+            heads[1],
+            "\r\n--XYZ\r\ncontent-type: text/plain\r\ncontent-range: bytes 10-11/20\r\n\r\n"
+        );
+        assert_eq!(parts.trailer, "\r\n--XYZ--\r\n");
+        let expected = heads.iter().map(|head| head.len()).sum::<usize>() + 3 + 2 + 11;
+        assert_eq!(parts.content_length(), expected as u64);
+
+        assert_eq!(boundary().len(), 32);
+        assert_ne!(boundary(), boundary());
+    }
+
+    #[tokio::test]
+    async fn file_byteranges_streamed() {
+        let fixture = Fixture::new("byteranges", &[("digits.txt", "0123456789abcdefghij")]);
+        let path = fixture.0.join("digits.txt");
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::RANGE, "bytes=0-2, 10-15".parse().unwrap());
+        let file = tokio::fs::File::open(&path).await.unwrap();
+        // A small buffer, so ranges take several reads.
+        let source = Source::File(file, 4);
+        let res = body_response(source, headers.typed_get(), 20, mime::TEXT_PLAIN, None);
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers()["content-type"].to_str().unwrap().to_owned();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let length = res.headers()["content-length"].to_str().unwrap().parse::<usize>();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(length, Ok(body.len()));
+        let expected = format!(
+            "--{b}\r\ncontent-type: text/plain\r\ncontent-range: bytes 0-2/20\r\n\r\n\
+             012\r\n--{b}\r\ncontent-type: text/plain\r\n\
+             content-range: bytes 10-15/20\r\n\r\nabcdef\r\n--{b}--\r\n",
+            b = boundary,
+        );
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn embedded_files_served() {
+        static FILES: &[EmbeddedFile] = &[
+            EmbeddedFile::new("index.html", b"<h1>home</h1>", "\"home\""),
+            EmbeddedFile::new("docs/index.html", b"<h1>docs</h1>", "\"docs\""),
+            EmbeddedFile::new("app.js", b"let answer = 42;", "\"app\""),
+            EmbeddedFile::new("app.js.br", b"brotli", "\"app-br\""),
+            EmbeddedFile::new("hello world.txt", b"hi", "\"hello\""),
+        ];
+        let embedded = embedded(FILES).precompressed(&[Encoding::Brotli]);
+
+        let path = |tail: &str| embedded.lookup(tail).map(EmbeddedFile::path);
+        assert_eq!(path(""), Some("index.html"));
+        assert_eq!(path("docs"), Some("docs/index.html"));
+        assert_eq!(path("docs/"), Some("docs/index.html"));
+        assert_eq!(path("hello%20world.txt"), Some("hello world.txt"));
+        assert_eq!(path("missing.js"), None);
+
+        let res = embedded.reply("app.js", unconditional(), None).unwrap().resp;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["content-type"], "text/javascript");
+        assert_eq!(res.headers()["etag"], "\"app\"");
+        assert_eq!(res.headers()["vary"], "Accept-Encoding");
+        assert!(res.headers().get("content-encoding").is_none());
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "let answer = 42;");
+
+        let accept = Some("gzip, br".to_owned());
+        let res = embedded.reply("app.js", unconditional(), accept).unwrap().resp;
+        assert_eq!(res.headers()["content-type"], "text/javascript");
+        assert_eq!(res.headers()["content-encoding"], "br");
+        assert_eq!(res.headers()["etag"], "\"app-br\"");
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "brotli");
+
+        let conditionals = Conditionals {
+            if_none_match: Some(IfNoneMatch::from("\"app\"".parse::<ETag>().unwrap())),
+            ..unconditional()
+        };
+        let res = embedded.reply("app.js", conditionals, None).unwrap().resp;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()["etag"], "\"app\"");
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::RANGE, "bytes=0-2, 13-".parse().unwrap());
+        let conditionals = Conditionals {
+            range: headers.typed_get::<Range>(),
+            ..unconditional()
+        };
+        let res = embedded.reply("app.js", conditionals, None).unwrap().resp;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = res.headers()["content-type"].to_str().unwrap().to_owned();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let length = res.headers()["content-length"].to_str().unwrap().parse::<usize>();
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(length, Ok(body.len()));
+        let expected = format!(
+            "--{b}\r\ncontent-type: text/javascript\r\ncontent-range: bytes 0-2/16\r\n\r\n\
+             let\r\n--{b}\r\ncontent-type: text/javascript\r\n\
+             content-range: bytes 13-15/16\r\n\r\n42;\r\n--{b}--\r\n",
+            b = boundary,
+        );
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn embedded_table_generated() {
+        let fixture = Fixture::new("embedded", &[("index.html", ""), ("assets/app.js", "")]);
+        let dir = fixture.0.canonicalize().unwrap();
+
+        let table = embedded_table(&dir).unwrap();
+        let include = |path: &Path| format!("include_bytes!({:?})", path.to_str().unwrap());
+        // The SHA-256 of no content.
+        let etag = r#""\"e3b0c44298fc1c149afbf4c8996fb924\"""#;
+        let expected = format!(
+            "&[\n    EmbeddedFile::new(\"assets/app.js\", {}, {}),\n    \
+             EmbeddedFile::new(\"index.html\", {}, {}),\n]",
+            include(&dir.join("assets").join("app.js")),
+            etag,
+            include(&dir.join("index.html")),
+            etag,
+        );
+        assert_eq!(table, expected);
+    }
 }
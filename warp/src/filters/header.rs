@@ -12,7 +12,8 @@ use headers::{Header, HeaderMapExt};
 use http::header::HeaderValue;
 use http::HeaderMap;
 
-use crate::filter::{filter_fn, filter_fn_one, Filter, One};
+use crate::filter::{described, filter_fn, filter_fn_one, Filter, One};
+use crate::filters::openapi::Meta;
 use crate::reject::{self, Rejection};
 
 
@@ -37,7 +38,7 @@ use crate::reject::{self, Rejection};
 pub fn header<T: FromStr + Send + 'static>(
     name: &'static str,
 ) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
-    filter_fn_one(move |route| {
+    let filter = filter_fn_one(move |route| {
         tracing::trace!("header({:?})", name);
         let route = route
             .headers()
@@ -46,6 +47,10 @@ pub fn header<T: FromStr + Send + 'static>(
             .and_then(|value| value.to_str().map_err(|_| reject::invalid_header(name)))
             .and_then(|s| T::from_str(s).map_err(|_| reject::invalid_header(name)));
         future::ready(route)
+    });
+    described(filter, move || Meta::Header {
+        name,
+        required: true,
     })
 }
 
@@ -79,7 +84,7 @@ pub fn optional<T>(
 where
     T: FromStr + Send + 'static,
 {
-    filter_fn_one(move |route| {
+    let filter = filter_fn_one(move |route| {
         tracing::trace!("optional({:?})", name);
         let result = route.headers().get(name).map(|value| {
             value
@@ -94,6 +99,10 @@ where
             Some(Err(e)) => future::err(e),
             None => future::ok(None),
         }
+    });
+    described(filter, move || Meta::Header {
+        name,
+        required: false,
     })
 }
 
@@ -228,3 +237,62 @@ pub fn value(
 pub fn headers_cloned() -> impl Filter<Extract = One<HeaderMap>, Error = Infallible> + Copy {
     filter_fn_one(|route| future::ok(route.headers().clone()))
 }
+
+/// The q-values of an `Accept-Encoding` header.
+#[derive(Debug)]
+pub(crate) struct AcceptEncoding<'a> {
+    listed: Vec<(&'a str, f32)>,
+    wildcard: Option<f32>,
+}
+
+impl<'a> AcceptEncoding<'a> {
+    pub(crate) fn parse(value: &'a str) -> AcceptEncoding<'a> {
+        let mut accept = AcceptEncoding {
+            listed: Vec::new(),
+            wildcard: None,
+        };
+        for item in value.split(',') {
+            let mut params = item.split(';').map(str::trim);
+            let coding = params.next().unwrap_or_default();
+            let q = match params.find_map(|param| param.strip_prefix("q=")) {
+                Some(q) => q.parse::<f32>().unwrap_or(0.0),
+                None => 1.0,
+            };
+            if coding == "*" {
+                accept.wildcard = Some(q);
+            } else if !coding.is_empty() {
+                accept.listed.push((coding, q));
+            }
+        }
+        accept
+    }
+
+    /// The q-value of `coding`, which is 0 if it isn't acceptable.
+    pub(crate) fn quality(&self, coding: &str) -> f32 {
+        self.listed
+            .iter()
+            .find(|(listed, _)| {
+                listed.eq_ignore_ascii_case(coding)
+                    || (coding == "gzip" && listed.eq_ignore_ascii_case("x-gzip"))
+            })
+            .map(|&(_, q)| q)
+            .or(self.wildcard)
+            .unwrap_or(0.0)
+    }
+
+    /// The acceptable `codings`, highest q-value first, keeping the given
+    /// order between codings accepted equally.
+    pub(crate) fn ranked<T: Copy>(
+        &self,
+        codings: &[T],
+        name: impl Fn(T) -> &'static str,
+    ) -> Vec<T> {
+        let mut ranked = codings
+            .iter()
+            .map(|&coding| (coding, self.quality(name(coding))))
+            .filter(|&(_, q)| q > 0.0)
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked.into_iter().map(|(coding, _)| coding).collect()
+    }
+}
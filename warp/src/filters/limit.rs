@@ -0,0 +1,727 @@
+//! Limiting Filters
+//!
+//! Wrappers that protect routes from being overwhelmed, either by capping
+//! the requests in flight with [`concurrency`], or by capping how often
+//! each client may call them with [`rate`].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use tokio::sync::Semaphore;
+
+use self::internal::{WithConcurrency, WithRate};
+use crate::filter::{Filter, WrapSealed};
+use crate::reject::{CombineRejection, Rejection};
+use crate::reply::Reply;
+
+/// Limit the number of requests in flight through a route to `max`.
+///
+/// A request holds one of the `max` slots until its reply body has been
+/// fully sent (or dropped), not just until its handler returns, so slow
+/// streaming bodies count against the limit too.
+///
+/// By default, requests arriving while all slots are taken are shed
+/// immediately with an [`Overloaded`](crate::reject::Overloaded) rejection,
+/// which renders as `503 Service Unavailable` with a `Retry-After` header.
+/// Being a rejection, it lets the request fall through to the route's `or`
+/// siblings. Use [`queue`](Concurrency::queue) to let requests wait for a
+/// slot instead.
+///
+/// The slot is taken before the wrapped filter runs, as there's no telling
+/// whether it matches a request without running its handler too. So while
+/// the slots are taken, requests it would have rejected, such as ones for
+/// another path or method, are shed as well, and their `404` or `405`
+/// becomes a `503`. Keep the path and method filters outside the wrapper,
+/// as in the example below, so only requests for the route count.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use warp::Filter;
+///
+/// let limit = warp::limit::concurrency(16)
+///     .queue(64, Duration::from_secs(2))
+///     .retry_after(Duration::from_secs(5));
+///
+/// let route = warp::path("report")
+///     .and(warp::get())
+///     .and(warp::any().map(|| "an expensive report").with(limit));
+/// ```
+pub fn concurrency(max: usize) -> Concurrency {
+    Concurrency {
+        semaphore: Arc::new(Semaphore::new(max)),
+        queue: None,
+        retry_after: Duration::from_secs(1),
+    }
+}
+
+/// Decorates a [`Filter`] to limit how many requests it handles at once.
+///
+/// Created with [`concurrency`]. Every route wrapped with the same
+/// `Concurrency` (or a clone of it) shares the same slots.
+#[derive(Clone, Debug)]
+pub struct Concurrency {
+    semaphore: Arc<Semaphore>,
+    queue: Option<Queue>,
+    retry_after: Duration,
+}
+
+#[derive(Clone, Debug)]
+struct Queue {
+    max: usize,
+    timeout: Duration,
+    waiting: Arc<AtomicUsize>,
+}
+
+impl Concurrency {
+    /// Let up to `max` requests wait for a free slot, each for at most
+    /// `timeout`, before shedding them.
+    pub fn queue(mut self, max: usize, timeout: Duration) -> Self {
+        self.queue = Some(Queue {
+            max,
+            timeout,
+            waiting: Arc::new(AtomicUsize::new(0)),
+        });
+        self
+    }
+
+    /// Set the `Retry-After` sent with shed requests, rounded up to whole
+    /// seconds. Defaults to 1 second.
+    pub fn retry_after(mut self, duration: Duration) -> Self {
+        self.retry_after = duration;
+        self
+    }
+}
+
+impl<F> WrapSealed<F> for Concurrency
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Future: Send,
+    F::Error: CombineRejection<Rejection>,
+{
+    type Wrapped = WithConcurrency<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithConcurrency {
+            filter,
+            limit: self.clone(),
+        }
+    }
+}
+
+/// Limit how often each client may make requests through a route, with
+/// a token bucket per key extracted by `key`.
+///
+/// Each bucket holds up to `capacity` tokens and is refilled at a steady
+/// rate of `capacity` tokens per `period`. Every request takes a token;
+/// requests finding their bucket empty are rejected with a
+/// [`TooManyRequests`](crate::reject::TooManyRequests), which renders as
+/// `429 Too Many Requests` with a `Retry-After` header. Requests the wrapped
+/// filter rejects, such as ones for another path or method, give their
+/// token back, so they don't count against the limit.
+///
+/// The key can come from any filter extracting a single value, such as the
+/// client address, a header, or an authenticated user. Requests rejected
+/// by `key` are rejected before taking a token.
+///
+/// Replies carry `RateLimit-Limit`, `RateLimit-Remaining` and
+/// `RateLimit-Reset` headers describing the client's bucket. Buckets that
+/// have refilled completely are indistinguishable from new ones, so they
+/// are dropped periodically, keeping memory proportional to the clients
+/// seen within the last `period`.
+///
+/// # Example
+///
+/// ```
+/// use std::net::SocketAddr;
+/// use std::time::Duration;
+/// use warp::Filter;
+///
+/// // 100 requests per minute per client IP, in bursts of up to 100.
+/// let per_ip = warp::addr::remote().map(|addr: Option<SocketAddr>| addr.map(|addr| addr.ip()));
+/// let limit = warp::limit::rate(per_ip, 100, Duration::from_secs(60));
+///
+/// // 10 requests per second per API key.
+/// let per_key = warp::header::<String>("x-api-key");
+/// let api_limit = warp::limit::rate(per_key, 10, Duration::from_secs(1));
+///
+/// let route = warp::path("search")
+///     .map(|| "results")
+///     .with(limit)
+///     .with(api_limit);
+/// ```
+pub fn rate<K, T>(key: K, capacity: u32, period: Duration) -> Rate<K, T>
+where
+    K: Filter<Extract = (T,)>,
+    T: Clone + Eq + Hash,
+{
+    assert!(capacity > 0, "rate limit capacity must be at least 1");
+    assert!(period > Duration::from_secs(0), "rate limit period must not be zero");
+    Rate {
+        key,
+        buckets: Arc::new(Buckets {
+            capacity,
+            period,
+            state: Mutex::new(BucketsState {
+                buckets: HashMap::new(),
+                swept: Instant::now(),
+            }),
+        }),
+    }
+}
+
+/// Decorates a [`Filter`] to limit how often each client may call it.
+///
+/// Created with [`rate`]. Every route wrapped with the same `Rate` (or a
+/// clone of it) shares the same buckets.
+#[derive(Debug)]
+pub struct Rate<K, T> {
+    key: K,
+    buckets: Arc<Buckets<T>>,
+}
+
+impl<K: Clone, T> Clone for Rate<K, T> {
+    fn clone(&self) -> Self {
+        Rate {
+            key: self.key.clone(),
+            buckets: self.buckets.clone(),
+        }
+    }
+}
+
+impl<F, K, T> WrapSealed<F> for Rate<K, T>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Future: Send,
+    F::Error: CombineRejection<Rejection>,
+    K: Filter<Extract = (T,)> + Clone + Send + Sync + 'static,
+    K::Future: Send,
+    K::Error: Into<Rejection>,
+    T: Clone + Eq + Hash + Send + Sync + 'static,
+{
+    type Wrapped = WithRate<F, K, T>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithRate {
+            filter,
+            rate: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets<T> {
+    capacity: u32,
+    period: Duration,
+    state: Mutex<BucketsState<T>>,
+}
+
+#[derive(Debug)]
+struct BucketsState<T> {
+    buckets: HashMap<T, Bucket>,
+    swept: Instant,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// The state of a bucket after a request took a token from it.
+#[derive(Debug, PartialEq)]
+struct Taken {
+    remaining: u32,
+    reset: Duration,
+}
+
+/// A bucket was empty.
+#[derive(Debug, PartialEq)]
+struct Empty {
+    retry_after: Duration,
+    reset: Duration,
+}
+
+impl<T: Eq + Hash> Buckets<T> {
+    fn take(&self, key: T, now: Instant) -> Result<Taken, Empty> {
+        let capacity = f64::from(self.capacity);
+        let per_sec = capacity / self.period.as_secs_f64();
+
+        let mut state = self.state.lock().unwrap();
+        if now.saturating_duration_since(state.swept) >= self.period {
+            let period = self.period;
+            state
+                .buckets
+                .retain(|_, bucket| now.saturating_duration_since(bucket.updated) < period);
+            state.swept = now;
+        }
+
+        let bucket = state.buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(Taken {
+                remaining: bucket.tokens as u32,
+                reset: Duration::from_secs_f64((capacity - bucket.tokens) / per_sec),
+            })
+        } else {
+            Err(Empty {
+                retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec),
+                reset: Duration::from_secs_f64((capacity - bucket.tokens) / per_sec),
+            })
+        }
+    }
+
+    /// Return the token a request took from `key`'s bucket.
+    fn give_back(&self, key: &T) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(bucket) = state.buckets.get_mut(key) {
+            bucket.tokens = (bucket.tokens + 1.0).min(f64::from(self.capacity));
+        }
+    }
+}
+
+pub(crate) fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+pub(crate) fn insert_rate_limit(headers: &mut HeaderMap, limit: u32, remaining: u32, reset: Duration) {
+    headers.insert(
+        HeaderName::from_static("ratelimit-limit"),
+        HeaderValue::from(limit),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-remaining"),
+        HeaderValue::from(remaining),
+    );
+    headers.insert(
+        HeaderName::from_static("ratelimit-reset"),
+        HeaderValue::from(ceil_secs(reset)),
+    );
+}
+
+mod internal {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::StreamExt;
+    use http::header::{HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
+    use hyper::body::HttpBody;
+    use hyper::Body;
+    use tokio::sync::OwnedSemaphorePermit;
+
+    use std::hash::Hash;
+    use std::time::Instant;
+
+    use super::{Concurrency, Queue, Rate};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
+    use crate::reject::{self, CombineRejection, Rejection};
+    use crate::reply::{Reply, Response};
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone)]
+    pub struct WithConcurrency<F> {
+        pub(super) filter: F,
+        pub(super) limit: Concurrency,
+    }
+
+    impl<F> FilterBase for WithConcurrency<F>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+        F::Future: Send,
+        F::Error: CombineRejection<Rejection>,
+    {
+        type Extract = (Limited,);
+        type Error = <F::Error as CombineRejection<Rejection>>::One;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let filter = self.filter.clone();
+            let limit = self.limit.clone();
+            Box::pin(async move {
+                let permit = match limit.semaphore.clone().try_acquire_owned() {
+                    Ok(permit) => Some(permit),
+                    Err(_) => match limit.queue {
+                        Some(ref queue) => wait(&limit, queue).await,
+                        None => None,
+                    },
+                };
+                let permit = match permit {
+                    Some(permit) => permit,
+                    None => {
+                        tracing::debug!("concurrency limit reached, shedding request");
+                        return Err(Self::Error::from(reject::overloaded(limit.retry_after)));
+                    }
+                };
+
+                let reply = filter.filter(Internal).await.map_err(Self::Error::from)?;
+                let res = reply.into_response();
+                Ok((Limited(hold(res, permit)),))
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    async fn wait(limit: &Concurrency, queue: &Queue) -> Option<OwnedSemaphorePermit> {
+        // Leave the queue even if the request is dropped while waiting.
+        struct Waiting<'a>(&'a AtomicUsize);
+        impl Drop for Waiting<'_> {
+            fn drop(&mut self) {
+                self.0.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+
+        let waiting = Waiting(&queue.waiting);
+        if waiting.0.fetch_add(1, Ordering::AcqRel) >= queue.max {
+            return None;
+        }
+        let acquire = limit.semaphore.clone().acquire_owned();
+        let permit = tokio::time::timeout(queue.timeout, acquire).await;
+        drop(waiting);
+        match permit {
+            Ok(Ok(permit)) => Some(permit),
+            // Timed out, or the semaphore was closed.
+            _ => None,
+        }
+    }
+
+    /// Keep `permit` alive until the response body is done.
+    pub(super) fn hold(res: Response, permit: OwnedSemaphorePermit) -> Response {
+        if body_is_done(res.body()) {
+            return res;
+        }
+        let (mut parts, body) = res.into_parts();
+        // The wrapped stream loses the body's size, so keep it around as a
+        // `Content-Length` instead of switching to chunked encoding.
+        if let Some(len) = HttpBody::size_hint(&body).exact() {
+            if !parts.headers.contains_key(CONTENT_LENGTH)
+                && !parts.headers.contains_key(TRANSFER_ENCODING)
+            {
+                parts.headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            }
+        }
+        let body = body.map(move |chunk| {
+            let _permit = &permit;
+            chunk
+        });
+        Response::from_parts(parts, Body::wrap_stream(body))
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone)]
+    pub struct WithRate<F, K, T> {
+        pub(super) filter: F,
+        pub(super) rate: Rate<K, T>,
+    }
+
+    impl<F, K, T> FilterBase for WithRate<F, K, T>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+        F::Future: Send,
+        F::Error: CombineRejection<Rejection>,
+        K: Filter<Extract = (T,)> + Clone + Send + Sync + 'static,
+        K::Future: Send,
+        K::Error: Into<Rejection>,
+        T: Clone + Eq + Hash + Send + Sync + 'static,
+    {
+        type Extract = (Limited,);
+        type Error = <F::Error as CombineRejection<Rejection>>::One;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let filter = self.filter.clone();
+            let key = self.rate.key.clone();
+            let buckets = self.rate.buckets.clone();
+            Box::pin(async move {
+                let (key,) = key.filter(Internal).await.map_err(|err| {
+                    let err: Rejection = err.into();
+                    Self::Error::from(err)
+                })?;
+                let taken = match buckets.take(key.clone(), Instant::now()) {
+                    Ok(taken) => taken,
+                    Err(empty) => {
+                        tracing::debug!("rate limit reached, retry after {:?}", empty.retry_after);
+                        let err = reject::too_many_requests(
+                            buckets.capacity,
+                            empty.retry_after,
+                            empty.reset,
+                        );
+                        return Err(Self::Error::from(err));
+                    }
+                };
+
+                let reply = match filter.filter(Internal).await {
+                    Ok(reply) => reply,
+                    Err(err) => {
+                        // The route didn't handle the request, so it
+                        // shouldn't count against the limit.
+                        buckets.give_back(&key);
+                        return Err(Self::Error::from(err));
+                    }
+                };
+                let mut res = reply.into_response();
+                super::insert_rate_limit(
+                    res.headers_mut(),
+                    buckets.capacity,
+                    taken.remaining,
+                    taken.reset,
+                );
+                Ok((Limited(res),))
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            Describe::and(
+                self.rate.key.describe(Internal),
+                self.filter.describe(Internal),
+            )
+        }
+    }
+
+    fn body_is_done(body: &Body) -> bool {
+        HttpBody::is_end_stream(body) || HttpBody::size_hint(body).exact() == Some(0)
+    }
+
+    #[allow(missing_debug_implementations)]
+    pub struct Limited(Response);
+
+    impl Reply for Limited {
+        #[inline]
+        fn into_response(self) -> Response {
+            self.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use futures_util::future::{self, poll_fn};
+    use http::header::{CONTENT_LENGTH, RETRY_AFTER};
+    use http::Method;
+    use hyper::Body;
+    use tokio::sync::Semaphore;
+
+    use super::internal::hold;
+    use super::{Buckets, BucketsState, Empty, Taken};
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::reject::{self, IsReject, Rejection};
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
+    use crate::Request;
+
+    /// Run `filter` on a request with `method`, as the server would.
+    async fn run<F, R>(filter: &F, method: Method) -> Result<Response, F::Error>
+    where
+        F: FilterBase<Extract = (R,)>,
+        R: Reply,
+    {
+        let mut req = Request::new(Body::empty());
+        *req.method_mut() = method;
+        run_request(filter, req).await
+    }
+
+    async fn run_request<F, R>(filter: &F, req: Request) -> Result<Response, F::Error>
+    where
+        F: FilterBase<Extract = (R,)>,
+        R: Reply,
+    {
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        let (reply,) = poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx))).await?;
+        Ok(reply.into_response())
+    }
+
+    /// Replies on `GET`, and rejects other methods.
+    #[derive(Clone)]
+    struct GetOnly;
+
+    impl FilterBase for GetOnly {
+        type Extract = (Response,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(Response,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let method = route::with(|route| route.method().clone());
+            if method == Method::GET {
+                future::ok((Response::new("got".into()),))
+            } else {
+                future::err(reject::method_not_allowed(Method::GET))
+            }
+        }
+    }
+
+    #[test]
+    fn shed_rounds_retry_after_up() {
+        let res = reject::overloaded(Duration::from_millis(1500)).into_response();
+        assert_eq!(res.status(), 503);
+        assert_eq!(res.headers()[RETRY_AFTER], "2");
+    }
+
+    #[tokio::test]
+    async fn shed_requests_rejected() {
+        let filter = GetOnly.with(super::concurrency(0));
+
+        let err = run(&filter, Method::GET).await.unwrap_err();
+        assert_eq!(err.status(), 503);
+        assert!(err.find::<reject::Overloaded>().is_some());
+    }
+
+    #[tokio::test]
+    async fn shedding_covers_unmatched_requests() {
+        let get = |path: &str| http::Request::get(path).body(Body::empty()).unwrap();
+
+        // The path is only known once inside the wrapper, so other paths are
+        // shed too.
+        let inside = crate::path("report").and(GetOnly).with(super::concurrency(0));
+        let err = run_request(&inside, get("/other")).await.unwrap_err();
+        assert_eq!(err.status(), 503);
+
+        // Matched outside, other paths fall through untouched.
+        let outside = crate::path("report").and(GetOnly.with(super::concurrency(0)));
+        let err = run_request(&outside, get("/other")).await.unwrap_err();
+        assert_eq!(err.status(), 404);
+        assert!(err.find::<reject::Overloaded>().is_none());
+        let err = run_request(&outside, get("/report")).await.unwrap_err();
+        assert_eq!(err.status(), 503);
+    }
+
+    #[test]
+    fn hold_releases_permit_with_body() {
+        let semaphore = Arc::new(Semaphore::new(1));
+
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        let res = hold(Response::new("hello".into()), permit);
+        assert_eq!(res.headers()[CONTENT_LENGTH], "5");
+        assert_eq!(semaphore.available_permits(), 0);
+        drop(res);
+        assert_eq!(semaphore.available_permits(), 1);
+
+        let permit = semaphore.clone().try_acquire_owned().unwrap();
+        let res = hold(Response::default(), permit);
+        assert!(!res.headers().contains_key(CONTENT_LENGTH));
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+
+    fn buckets<T>(capacity: u32, period: Duration) -> Buckets<T> {
+        Buckets {
+            capacity,
+            period,
+            state: Mutex::new(BucketsState {
+                buckets: HashMap::new(),
+                swept: Instant::now(),
+            }),
+        }
+    }
+
+    #[test]
+    fn rate_buckets_refill() {
+        let buckets = buckets(2, Duration::from_secs(2));
+        let start = Instant::now();
+
+        let taken = |remaining, reset| Ok(Taken { remaining, reset });
+        assert_eq!(buckets.take("a", start), taken(1, Duration::from_secs(1)));
+        assert_eq!(buckets.take("a", start), taken(0, Duration::from_secs(2)));
+        assert_eq!(
+            buckets.take("a", start),
+            Err(Empty {
+                retry_after: Duration::from_secs(1),
+                reset: Duration::from_secs(2),
+            })
+        );
+        // Other keys have their own bucket.
+        assert_eq!(buckets.take("b", start), taken(1, Duration::from_secs(1)));
+
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(buckets.take("a", later), taken(0, Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn rate_tokens_given_back() {
+        let buckets = buckets(2, Duration::from_secs(2));
+        let start = Instant::now();
+
+        buckets.take("a", start).unwrap();
+        buckets.take("a", start).unwrap();
+        buckets.give_back(&"a");
+        assert_eq!(
+            buckets.take("a", start),
+            Ok(Taken {
+                remaining: 0,
+                reset: Duration::from_secs(2),
+            })
+        );
+        // Never past the capacity.
+        buckets.give_back(&"a");
+        buckets.give_back(&"a");
+        buckets.give_back(&"a");
+        assert_eq!(buckets.take("a", start).unwrap().remaining, 1);
+    }
+
+    /// Keys every request the same.
+    #[derive(Clone)]
+    struct SameKey;
+
+    impl FilterBase for SameKey {
+        type Extract = (&'static str,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(&'static str,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            future::ok(("client",))
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_requests_not_counted() {
+        let filter = GetOnly.with(super::rate(SameKey, 1, Duration::from_secs(60)));
+
+        for _ in 0..3 {
+            let err = run(&filter, Method::POST).await.unwrap_err();
+            assert_eq!(err.status(), 405);
+        }
+        let res = run(&filter, Method::GET).await.unwrap();
+        assert_eq!(res.headers()["ratelimit-remaining"], "0");
+        let err = run(&filter, Method::GET).await.unwrap_err();
+        assert_eq!(err.status(), 429);
+    }
+
+    #[test]
+    fn rate_buckets_swept_when_full() {
+        let buckets = buckets(2, Duration::from_secs(2));
+        let start = Instant::now();
+
+        buckets.take(1, start).unwrap();
+        buckets.take(2, start + Duration::from_secs(1)).unwrap();
+        buckets.take(3, start + Duration::from_secs(2)).unwrap();
+
+        let state = buckets.state.lock().unwrap();
+        let mut keys = state.buckets.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![2, 3]);
+    }
+}
@@ -4,6 +4,7 @@ use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 use http::{self, StatusCode};
 use crate::filter::{Filter, WrapSealed};
+use crate::filters::request_id::RequestId;
 use crate::reject::IsReject;
 use crate::reply::Reply;
 use crate::route::Route;
@@ -37,6 +38,20 @@ pub fn log(name: &'static str) -> Log<impl Fn(Info<'_>) + Copy> {
     Log { func }
 }
 
+/// Log requests like [`log`], followed by the request's
+/// [ID](crate::request_id()), or `-` if it isn't tagged with one.
+pub fn with_request_id(name: &'static str) -> Log<impl Fn(Info<'_>) + Copy> {
+    let func = move |info: Info<'_>| {
+        log::info!(
+            target : name, "{} \"{} {} {:?}\" {} \"{}\" \"{}\" {:?} {}", OptFmt(info.route
+            .remote_addr()), info.method(), info.path(), info.route.version(), info
+            .status().as_u16(), OptFmt(info.referer()), OptFmt(info.user_agent()), info
+            .elapsed(), OptFmt(info.request_id()),
+        );
+    };
+    Log { func }
+}
+
 
 
 
@@ -127,11 +142,21 @@ impl<'a> Info<'a> {
     pub fn request_headers(&self) -> &http::HeaderMap {
         loop {}
     }
+
+    /// View the ID of the request, if tagged with
+    /// [`request_id`](crate::request_id()).
+    pub fn request_id(&self) -> Option<&RequestId> {
+        self.route.extensions().get::<RequestId>()
+    }
 }
 struct OptFmt<T>(Option<T>);
 impl<T: fmt::Display> fmt::Display for OptFmt<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        loop {}
+        if let Some(ref t) = self.0 {
+            fmt::Display::fmt(t, f)
+        } else {
+            f.write_str("-")
+        }
     }
 }
 mod internal {
@@ -142,7 +167,7 @@ mod internal {
     use futures_util::{TryFuture};
     use pin_project::pin_project;
     use super::{Info, Log};
-    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
     use crate::reject::IsReject;
     use crate::reply::{Reply, Response};
     
@@ -173,6 +198,9 @@ mod internal {
         fn filter(&self, _: Internal) -> Self::Future {
             loop {}
         }
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
     }
     #[allow(missing_debug_implementations)]
     #[pin_project]
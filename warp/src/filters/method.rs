@@ -8,9 +8,12 @@
 //! a request, and just extracts the method to be used in your filter chains.
 use futures_util::future;
 use http::Method;
-use crate::filter::{filter_fn, filter_fn_one, Filter, One};
+use crate::filter::{described, filter_fn, filter_fn_one, Filter, One, WrapSealed};
+use crate::filters::openapi::Meta;
 use crate::reject::Rejection;
+use crate::reply::Reply;
 use std::convert::Infallible;
+use self::internal::WithAutoOptions;
 
 
 
@@ -116,20 +119,204 @@ fn method_is<F>(func: F) -> impl Filter<Extract = (), Error = Rejection> + Copy
 where
     F: Fn() -> &'static Method + Copy,
 {
-    filter_fn(move |route| {
+    let filter = filter_fn(move |route| {
         let method = func();
         tracing::trace!("method::{:?}?: {:?}", method, route.method());
         if route.method() == method {
             future::ok(())
         } else {
-            future::err(crate::reject::method_not_allowed())
+            future::err(crate::reject::method_not_allowed(method.clone()))
         }
-    })
+    });
+    described(filter, move || Meta::Method(func().clone()))
 }
+/// Answer `OPTIONS` requests that no route handles with a `204 No Content`,
+/// listing the methods the path does allow in an `Allow` header.
+///
+/// The methods are collected from the `405 Method Not Allowed` rejections of
+/// the wrapped filter, so path filters should come *before* method filters
+/// for the list to only contain the methods of the matching path.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let users = warp::path("users");
+/// let routes = users
+///     .and(warp::get())
+///     .map(|| "list")
+///     .or(users.and(warp::post()).map(|| "create"))
+///     .with(warp::method::auto_options());
+/// // OPTIONS /users => 204, with GET, POST and OPTIONS in `Allow`
+/// ```
+pub fn auto_options() -> AutoOptions {
+    AutoOptions { _priv: () }
+}
+
+/// Decorates a filter to answer `OPTIONS` requests automatically.
+///
+/// Created with [`auto_options`].
+#[derive(Clone, Copy, Debug)]
+pub struct AutoOptions {
+    _priv: (),
+}
+
+impl<F> WrapSealed<F> for AutoOptions
+where
+    F: Filter<Error = Rejection>,
+    F::Extract: Reply,
+{
+    type Wrapped = WithAutoOptions<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithAutoOptions { filter }
+    }
+}
+
+mod internal {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_util::{ready, TryFuture};
+    use http::{Method, StatusCode};
+    use pin_project::pin_project;
+
+    use crate::filter::{Describe, Filter, FilterBase, Internal, One};
+    use crate::generic::Either;
+    use crate::reject::{self, IsReject, Rejection};
+    use crate::reply::{Reply, Response};
+    use crate::route;
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone, Copy)]
+    pub struct WithAutoOptions<F> {
+        pub(super) filter: F,
+    }
+
+    impl<F> FilterBase for WithAutoOptions<F>
+    where
+        F: Filter<Error = Rejection>,
+        F::Extract: Reply,
+    {
+        type Extract = One<Either<One<Allowed>, F::Extract>>;
+        type Error = Rejection;
+        type Future = WithAutoOptionsFuture<F::Future>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let is_options = route::with(|route| route.method() == Method::OPTIONS);
+            WithAutoOptionsFuture {
+                future: self.filter.filter(Internal),
+                is_options,
+            }
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[pin_project]
+    pub struct WithAutoOptionsFuture<F> {
+        #[pin]
+        future: F,
+        is_options: bool,
+    }
+
+    impl<F> Future for WithAutoOptionsFuture<F>
+    where
+        F: TryFuture<Error = Rejection>,
+        F::Ok: Reply,
+    {
+        type Output = Result<One<Either<One<Allowed>, F::Ok>>, Rejection>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let pin = self.project();
+            match ready!(pin.future.try_poll(cx)) {
+                Ok(ex) => Poll::Ready(Ok((Either::B(ex),))),
+                Err(err) if *pin.is_options && err.status() == StatusCode::METHOD_NOT_ALLOWED => {
+                    let mut methods = err.allowed_methods();
+                    methods.push(Method::OPTIONS);
+                    tracing::trace!("auto_options: answering OPTIONS with {:?}", methods);
+                    Poll::Ready(Ok((Either::A((Allowed { methods },)),)))
+                }
+                Err(err) => Poll::Ready(Err(err)),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct Allowed {
+        methods: Vec<Method>,
+    }
+
+    impl Reply for Allowed {
+        fn into_response(self) -> Response {
+            let mut res = Response::default();
+            *res.status_mut() = StatusCode::NO_CONTENT;
+            reject::insert_allow(res.headers_mut(), &self.methods);
+            res
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::future::Future;
+
+    use futures_util::future::poll_fn;
+    use http::header::ALLOW;
+    use http::{Method, StatusCode};
+
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::reject::{IsReject, Rejection};
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
+
+    async fn run<F>(filter: &F, method: Method, path: &str) -> Result<Response, Rejection>
+    where
+        F: FilterBase<Error = Rejection>,
+        F::Extract: Reply,
+    {
+        let req = http::Request::builder().method(method).uri(path);
+        let route = Route::new(req.body(Default::default()).unwrap(), None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        let extract = poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx))).await?;
+        Ok(extract.into_response())
+    }
+
     #[test]
     fn method_size_of() {
         loop {}
     }
+
+    #[tokio::test]
+    async fn auto_options_lists_allowed_methods() {
+        let users = crate::path("users");
+        let routes = users
+            .and(crate::get())
+            .map(|| StatusCode::OK)
+            .or(users.and(crate::post()).map(|| StatusCode::CREATED))
+            .or(users.and(crate::delete()).map(|| StatusCode::ACCEPTED))
+            .with(super::auto_options());
+
+        let res = run(&routes, Method::OPTIONS, "/users").await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let allow = res.headers()[ALLOW].to_str().unwrap();
+        let mut allow = allow.split(", ").collect::<Vec<_>>();
+        allow.sort_unstable();
+        assert_eq!(allow, ["DELETE", "GET", "OPTIONS", "POST"]);
+
+        let res = run(&routes, Method::POST, "/users").await.unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let err = run(&routes, Method::PUT, "/users").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let err = run(&routes, Method::OPTIONS, "/teams").await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert!(err.allowed_methods().is_empty());
+    }
 }
@@ -5,25 +5,38 @@
 
 pub mod addr;
 pub mod any;
+pub mod auth;
 pub mod body;
-#[cfg(any(feature = "compression-brotli", feature = "compression-gzip"))]
+#[cfg(any(
+    feature = "compression-brotli",
+    feature = "compression-gzip",
+    feature = "compression-zstd"
+))]
 pub mod compression;
 pub mod cookie;
 pub mod cors;
+pub mod csrf;
 pub mod ext;
 pub mod fs;
 pub mod header;
 pub mod host;
+pub mod limit;
 pub mod log;
 pub mod method;
 #[cfg(feature = "multipart")]
 pub mod multipart;
+pub mod openapi;
 pub mod path;
 pub mod query;
 pub mod reply;
+pub mod request_id;
+pub mod router;
+#[cfg(feature = "session")]
+pub mod session;
 pub mod sse;
+pub mod tower;
 pub mod trace;
 #[cfg(feature = "websocket")]
 pub mod ws;
 
-pub use crate::filter::BoxedFilter;
+pub use crate::filter::{BoxedFilter, Timeout};
@@ -11,7 +11,8 @@ use futures_util::{future, Stream};
 use headers::ContentType;
 use mime::Mime;
 use multipart::server::Multipart;
-use crate::filter::{Filter, FilterBase, Internal};
+use crate::filter::{Describe, Filter, FilterBase, Internal};
+use crate::filters::openapi::Meta;
 use crate::reject::{self, Rejection};
 const DEFAULT_FORM_DATA_MAX_LENGTH: u64 = 1024 * 1024 * 2;
 
@@ -59,6 +60,12 @@ impl FilterBase for FormOptions {
     fn filter(&self, _: Internal) -> Self::Future {
         loop {}
     }
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::Leaf(Meta::Body {
+            content_type: "multipart/form-data",
+            ty: std::any::type_name::<FormData>(),
+        })
+    }
 }
 impl fmt::Debug for FormData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -0,0 +1,537 @@
+//! OpenAPI Filters
+//!
+//! The built-in `path`, `path::param`, method, `query`, `header` and `body`
+//! filters record what they match on. Once routes are combined with `and`
+//! and `or`, the resulting tree can be walked with [`routes`], or turned
+//! into an [OpenAPI 3](https://spec.openapis.org/oas/v3.0.3) document with
+//! [`document`].
+//!
+//! Replies are built inside handlers, so [`warp::reply::json()`](crate::reply::json)
+//! can't be seen from the outside, and its [`Json`] reply doesn't carry the
+//! type it was serialized from. Wrap a route with [`json_response`] to
+//! document what it returns.
+//! Types used in bodies, queries and responses are described as plain
+//! objects, unless they opt in by implementing [`Schema`] and being
+//! registered with [`Document::schema`].
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use warp::Filter;
+//! use warp::openapi::{self, Schema};
+//!
+//! #[derive(serde::Serialize)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! impl Schema for User {
+//!     fn schema() -> serde_json::Value {
+//!         json!({
+//!             "type": "object",
+//!             "properties": { "name": { "type": "string" } },
+//!         })
+//!     }
+//! }
+//!
+//! let user = warp::get()
+//!     .and(warp::path!("users" / u32))
+//!     .map(|_id| warp::reply::json(&User { name: "...".into() }))
+//!     .with(openapi::json_response::<User>());
+//!
+//! let doc = openapi::document("users", "1.0.0")
+//!     .schema::<User>()
+//!     .build(&user);
+//!
+//! let spec = warp::path!("openapi.json").and(openapi::serve(doc));
+//! let routes = user.or(spec);
+//! ```
+
+use std::any::type_name;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use http::Method;
+use serde_json::{json, Map, Value};
+
+use self::internal::WithResponse;
+use crate::filter::{Filter, Internal, WrapSealed};
+use crate::reply::Json;
+
+/// A piece of metadata recorded by a filter.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Meta {
+    /// A static path segment, from `warp::path(...)`.
+    Path(String),
+    /// A path parameter of the named type, from `warp::path::param()`.
+    Param(&'static str),
+    /// A named path parameter, from a [`typed_path!`](crate::typed_path).
+    NamedParam {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The type the parameter is parsed into.
+        ty: &'static str,
+    },
+    /// A required method, from `warp::get()` and friends.
+    Method(Method),
+    /// A query string deserialized into the named type, from `warp::query()`.
+    Query(&'static str),
+    /// A request header, from `warp::header()` or `warp::header::optional()`.
+    Header {
+        /// The header name.
+        name: &'static str,
+        /// Whether the request is rejected without it.
+        required: bool,
+    },
+    /// A request body, from `warp::body::json()` or `warp::body::form()`.
+    Body {
+        /// The content-type the body is decoded from.
+        content_type: &'static str,
+        /// The type the body is deserialized into.
+        ty: &'static str,
+    },
+    /// A response body, from [`json_response`].
+    Response {
+        /// The content-type of the reply.
+        content_type: &'static str,
+        /// The type serialized into the reply.
+        ty: &'static str,
+    },
+}
+
+/// Types that can describe themselves with a JSON Schema.
+pub trait Schema {
+    /// The JSON Schema of this type.
+    fn schema() -> Value;
+}
+
+/// Walk a filter tree, returning every route it can match in the order
+/// they would be tried.
+pub fn routes<F: Filter>(filter: &F) -> Vec<RouteInfo> {
+    filter
+        .describe(Internal)
+        .flatten()
+        .into_iter()
+        .filter(|meta| !meta.is_empty())
+        .map(|meta| RouteInfo { meta })
+        .collect()
+}
+
+/// A single route of a filter tree, as returned by [`routes`].
+#[derive(Clone, Debug)]
+pub struct RouteInfo {
+    meta: Vec<Meta>,
+}
+
+impl RouteInfo {
+    /// The method this route requires, if any.
+    pub fn method(&self) -> Option<&Method> {
+        self.meta.iter().find_map(|meta| match meta {
+            Meta::Method(method) => Some(method),
+            _ => None,
+        })
+    }
+
+    /// The path template of this route, such as `/users/{id}`.
+    ///
+    /// Parameters of [typed paths](crate::typed_path) are named after their
+    /// fields, others are numbered: `/users/{param1}`.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for (meta, name) in self.meta.iter().zip(self.param_names()) {
+            match (meta, name) {
+                (Meta::Path(seg), _) => {
+                    path.push('/');
+                    path.push_str(seg);
+                }
+                (_, Some(name)) => path.push_str(&format!("/{{{}}}", name)),
+                _ => (),
+            }
+        }
+        if path.is_empty() {
+            path.push('/');
+        }
+        path
+    }
+
+    /// The name of each path parameter in `meta`, `None` for other metadata.
+    fn param_names(&self) -> impl Iterator<Item = Option<String>> + '_ {
+        let mut params = 0;
+        self.meta.iter().map(move |meta| match *meta {
+            Meta::Param(_) => {
+                params += 1;
+                Some(format!("param{}", params))
+            }
+            Meta::NamedParam { name, .. } => Some(name.to_owned()),
+            _ => None,
+        })
+    }
+
+    /// All metadata recorded for this route, in filter order.
+    pub fn meta(&self) -> &[Meta] {
+        &self.meta
+    }
+}
+
+/// Start building an OpenAPI document with the given title and version.
+pub fn document(title: impl Into<String>, version: impl Into<String>) -> Document {
+    Document {
+        title: title.into(),
+        version: version.into(),
+        schemas: HashMap::new(),
+    }
+}
+
+/// A builder for OpenAPI documents.
+#[derive(Clone, Debug)]
+pub struct Document {
+    title: String,
+    version: String,
+    schemas: HashMap<&'static str, (String, Value)>,
+}
+
+impl Document {
+    /// Register the schema of `T`, so references to it use a named
+    /// component instead of a plain object.
+    ///
+    /// The component is named after the type, without its module path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another registered type has the same name, such as
+    /// `a::User` and `b::User`. Register one of them with
+    /// [`schema_named`](Document::schema_named) instead.
+    pub fn schema<T: Schema>(self) -> Self {
+        let name = short_name(type_name::<T>()).to_owned();
+        self.schema_named::<T>(name)
+    }
+
+    /// Register the schema of `T` as the component `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another registered type already uses `name`.
+    pub fn schema_named<T: Schema>(mut self, name: impl Into<String>) -> Self {
+        let ty = type_name::<T>();
+        let name = name.into();
+        let taken = self
+            .schemas
+            .iter()
+            .find(|(other, (other_name, _))| **other != ty && *other_name == name);
+        if let Some((other, _)) = taken {
+            panic!("openapi schema {:?} registered for both {} and {}", name, other, ty);
+        }
+        self.schemas.insert(ty, (name, T::schema()));
+        self
+    }
+
+    /// Build the document from the routes of a filter tree.
+    ///
+    /// Routes without a method filter can't be expressed as an OpenAPI
+    /// operation, and are left out.
+    pub fn build<F: Filter>(&self, filter: &F) -> Value {
+        let mut paths = Map::new();
+        for route in routes(filter) {
+            let method = match route.method() {
+                Some(method) => method.as_str().to_ascii_lowercase(),
+                None => {
+                    tracing::debug!("openapi: skipping route without method: {}", route.path());
+                    continue;
+                }
+            };
+            let item = paths
+                .entry(route.path())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(item) = item {
+                // Like `or`, the first route to match wins.
+                item.entry(method).or_insert_with(|| self.operation(&route));
+            }
+        }
+
+        let mut doc = json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": self.title,
+                "version": self.version,
+            },
+            "paths": paths,
+        });
+        if !self.schemas.is_empty() {
+            let schemas = self
+                .schemas
+                .values()
+                .map(|(name, schema)| (name.clone(), schema.clone()))
+                .collect::<Map<_, _>>();
+            doc["components"] = json!({ "schemas": schemas });
+        }
+        doc
+    }
+
+    fn operation(&self, route: &RouteInfo) -> Value {
+        let mut parameters = Vec::new();
+        let mut op = Map::new();
+        let mut response = json!({ "description": "OK" });
+        for (meta, name) in route.meta().iter().zip(route.param_names()) {
+            match *meta {
+                Meta::Param(ty) | Meta::NamedParam { ty, .. } => {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": primitive(ty).unwrap_or_else(|| json!({ "type": "string" })),
+                    }));
+                }
+                Meta::Query(ty) => parameters.push(json!({
+                    "name": short_name(ty),
+                    "in": "query",
+                    "style": "form",
+                    "explode": true,
+                    "schema": self.schema_for(ty),
+                })),
+                Meta::Header { name, required } => parameters.push(json!({
+                    "name": name,
+                    "in": "header",
+                    "required": required,
+                    "schema": { "type": "string" },
+                })),
+                Meta::Body { content_type, ty } => {
+                    let mut content = Map::new();
+                    content.insert(content_type.to_owned(), json!({ "schema": self.schema_for(ty) }));
+                    op.insert(
+                        "requestBody".to_owned(),
+                        json!({ "required": true, "content": content }),
+                    );
+                }
+                Meta::Response { content_type, ty } => {
+                    let mut content = Map::new();
+                    content.insert(content_type.to_owned(), json!({ "schema": self.schema_for(ty) }));
+                    response["content"] = Value::Object(content);
+                }
+                Meta::Path(_) | Meta::Method(_) => (),
+            }
+        }
+        if !parameters.is_empty() {
+            op.insert("parameters".to_owned(), Value::Array(parameters));
+        }
+        op.insert("responses".to_owned(), json!({ "200": response }));
+        Value::Object(op)
+    }
+
+    fn schema_for(&self, ty: &str) -> Value {
+        if let Some((name, _)) = self.schemas.get(ty) {
+            json!({ "$ref": format!("#/components/schemas/{}", name) })
+        } else {
+            primitive(ty).unwrap_or_else(|| json!({ "type": "object" }))
+        }
+    }
+}
+
+fn primitive(ty: &str) -> Option<Value> {
+    macro_rules! is {
+        ($($t:ty),+) => { $(ty == type_name::<$t>())||+ };
+    }
+    let schema = if is!(u8, u16, u32, i8, i16, i32) {
+        json!({ "type": "integer", "format": "int32" })
+    } else if is!(u64, i64, usize, isize, u128, i128) {
+        json!({ "type": "integer", "format": "int64" })
+    } else if is!(f32) {
+        json!({ "type": "number", "format": "float" })
+    } else if is!(f64) {
+        json!({ "type": "number", "format": "double" })
+    } else if is!(bool) {
+        json!({ "type": "boolean" })
+    } else if is!(String, &str, char) {
+        json!({ "type": "string" })
+    } else {
+        return None;
+    };
+    Some(schema)
+}
+
+fn short_name(ty: &str) -> &str {
+    let ty = ty.split('<').next().unwrap_or(ty);
+    ty.rsplit("::").next().unwrap_or(ty)
+}
+
+/// Serve an OpenAPI document as JSON.
+pub fn serve(document: Value) -> impl Filter<Extract = (Json,), Error = Infallible> + Clone {
+    let document = Arc::new(document);
+    crate::any().map(move || crate::reply::json(&*document))
+}
+
+/// Document that the wrapped route replies with `T` serialized as JSON,
+/// such as with `warp::reply::json()`.
+pub fn json_response<T>() -> Response {
+    Response {
+        content_type: "application/json",
+        ty: type_name::<T>(),
+    }
+}
+
+/// Wraps a route with the description of its response.
+///
+/// Created with [`json_response`].
+#[derive(Clone, Copy, Debug)]
+pub struct Response {
+    content_type: &'static str,
+    ty: &'static str,
+}
+
+impl<F: Filter> WrapSealed<F> for Response {
+    type Wrapped = WithResponse<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithResponse {
+            filter,
+            response: *self,
+        }
+    }
+}
+
+mod internal {
+    use super::{Meta, Response};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone, Copy)]
+    pub struct WithResponse<F> {
+        pub(super) filter: F,
+        pub(super) response: Response,
+    }
+
+    impl<F: Filter> FilterBase for WithResponse<F> {
+        type Extract = F::Extract;
+        type Error = F::Error;
+        type Future = F::Future;
+
+        #[inline]
+        fn filter(&self, _: Internal) -> Self::Future {
+            self.filter.filter(Internal)
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            let response = Meta::Response {
+                content_type: self.response.content_type,
+                ty: self.response.ty,
+            };
+            Describe::and(self.filter.describe(Internal), Describe::Leaf(response))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{document, json_response, routes, Schema};
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::route::{self, Route};
+    use crate::Reply;
+
+    crate::typed_path! {
+        struct UserPost("users" / user_id: u32 / "posts" / slug: String);
+    }
+
+    #[derive(serde_derive::Serialize)]
+    struct Post {
+        title: String,
+    }
+
+    impl Schema for Post {
+        fn schema() -> serde_json::Value {
+            json!({ "type": "object", "properties": { "title": { "type": "string" } } })
+        }
+    }
+
+    #[test]
+    fn document_built_from_routes() {
+        let show = crate::get()
+            .and(UserPost::filter())
+            .map(|route: UserPost| crate::reply::json(&Post { title: route.slug }))
+            .with(json_response::<Post>());
+        let list = crate::get()
+            .and(crate::path!("users" / u32 / "posts"))
+            .map(|_: u32| crate::reply());
+        let api = show.or(list);
+
+        let paths = routes(&api).iter().map(|route| route.path()).collect::<Vec<_>>();
+        assert_eq!(paths, ["/users/{user_id}/posts/{slug}", "/users/{param1}/posts"]);
+
+        let doc = document("posts", "1.0.0").schema::<Post>().build(&api);
+        let show = &doc["paths"]["/users/{user_id}/posts/{slug}"]["get"];
+        assert_eq!(
+            show["parameters"],
+            json!([
+                {
+                    "name": "user_id",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "integer", "format": "int32" },
+                },
+                {
+                    "name": "slug",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                },
+            ])
+        );
+        assert_eq!(
+            show["responses"]["200"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/Post" })
+        );
+        assert_eq!(doc["components"]["schemas"]["Post"], Post::schema());
+
+        let list = &doc["paths"]["/users/{param1}/posts"]["get"];
+        assert_eq!(list["parameters"][0]["name"], "param1");
+        assert!(list["responses"]["200"].get("content").is_none());
+    }
+
+    mod other {
+        pub struct Post;
+
+        impl super::Schema for Post {
+            fn schema() -> serde_json::Value {
+                serde_json::json!({ "type": "string" })
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "registered for both")]
+    fn schema_name_collision_detected() {
+        let _ = document("posts", "1.0.0").schema::<Post>().schema::<other::Post>();
+    }
+
+    #[test]
+    fn schema_named_avoids_collision() {
+        let doc = document("posts", "1.0.0")
+            .schema::<Post>()
+            .schema_named::<other::Post>("OtherPost")
+            .build(&crate::any());
+        let schemas = &doc["components"]["schemas"];
+        assert_eq!(schemas["Post"], Post::schema());
+        assert_eq!(schemas["OtherPost"], other::Post::schema());
+    }
+
+    #[tokio::test]
+    async fn document_served_as_json() {
+        use futures_util::future::poll_fn;
+        use std::future::Future;
+
+        let doc = json!({ "openapi": "3.0.3" });
+        let filter = super::serve(doc.clone());
+        let route = Route::new(crate::Request::default(), None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        let (reply,) = poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx)))
+            .await
+            .unwrap();
+        let res = reply.into_response();
+        assert_eq!(res.headers()["content-type"], "application/json");
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap(), doc);
+    }
+}
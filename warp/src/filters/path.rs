@@ -125,11 +125,13 @@
 //! with an invalid body for route `/right-path-wrong-body` may try matching against `/wrong-path`
 //! and return the error from `/wrong-path` instead of the correct body-related error.
 use self::internal::Opaque;
-use crate::filter::{filter_fn, one, Filter, FilterBase, Internal, One, Tuple};
+use crate::filter::{described, filter_fn, one, Describe, Filter, FilterBase, Internal, One, Tuple};
+use crate::filters::openapi::Meta;
 use crate::reject::{self, Rejection};
-use crate::route::Route;
+use crate::route::{self, Route};
 use futures_util::future;
 use http::uri::PathAndQuery;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 use std::convert::Infallible;
 use std::fmt;
 use std::str::FromStr;
@@ -138,7 +140,14 @@ pub fn path<P>(p: P) -> Exact<Opaque<P>>
 where
     P: AsRef<str>,
 {
-    loop {}
+    let s = p.as_ref();
+    assert!(!s.is_empty(), "exact path segments should not be empty");
+    assert!(
+        !s.contains('/'),
+        "exact path segments should not contain a slash: {:?}",
+        s
+    );
+    Exact(Opaque(p))
 }
 
 #[allow(missing_debug_implementations)]
@@ -153,7 +162,20 @@ where
     type Future = future::Ready<Result<Self::Extract, Self::Error>>;
     #[inline]
     fn filter(&self, _: Internal) -> Self::Future {
-        loop {}
+        route::with(|route| {
+            let p = self.0.as_ref();
+            future::ready(with_segment(route, |seg| {
+                tracing::trace!("{:?}?: {:?}", p, seg);
+                if seg == p {
+                    Ok(())
+                } else {
+                    Err(reject::not_found())
+                }
+            }))
+        })
+    }
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::Leaf(Meta::Path(self.0.as_ref().to_owned()))
     }
 }
 
@@ -169,13 +191,14 @@ pub fn end() -> impl Filter<Extract = (), Error = Rejection> + Copy {
 
 pub fn param<T: FromStr + Send + 'static>(
 ) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
-    filter_segment(|seg| {
+    let filter = filter_segment(|seg| {
         tracing::trace!("param?: {:?}", seg);
         if seg.is_empty() {
             return Err(reject::not_found());
         }
         T::from_str(seg).map(one).map_err(|_| reject::not_found())
-    })
+    });
+    described(filter, || Meta::Param(std::any::type_name::<T>()))
 }
 
 pub fn tail() -> impl Filter<Extract = One<Tail>, Error = Infallible> + Copy {
@@ -197,12 +220,12 @@ pub struct Tail {
 }
 impl Tail {
     pub fn as_str(&self) -> &str {
-        loop {}
+        &self.path.path()[self.start_index..]
     }
 }
 impl fmt::Debug for Tail {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        loop {}
+        fmt::Debug::fmt(self.as_str(), f)
     }
 }
 
@@ -262,10 +285,27 @@ fn with_segment<F, U>(route: &mut Route, func: F) -> Result<U, Rejection>
 where
     F: Fn(&str) -> Result<U, Rejection>,
 {
-    loop {}
+    let seg = segment(route);
+    let ret = func(seg);
+    if ret.is_ok() {
+        let idx = seg.len();
+        route.set_unmatched_path(idx);
+    }
+    ret
+}
+fn segment(route: &Route) -> &str {
+    route
+        .path()
+        .splitn(2, '/')
+        .next()
+        .expect("split always has at least 1")
 }
 fn path_and_query(route: &Route) -> PathAndQuery {
-    loop {}
+    route
+        .uri()
+        .path_and_query()
+        .cloned()
+        .unwrap_or_else(|| PathAndQuery::from_static(""))
 }
 
 #[macro_export]
@@ -314,6 +354,177 @@ macro_rules! __internal_path {
 }
 
 fn _path_macro_compile_fail() {}
+
+/// Characters percent-encoded when a value is written into a path segment.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// A path declared with [`typed_path!`](../../macro.typed_path.html), which
+/// knows how to build a URL back to itself.
+pub trait TypedPath {
+    /// The percent-encoded path of this route, such as `/users/42/posts`.
+    fn url(&self) -> String;
+}
+
+/// Like [`param`], but percent-decodes the segment before parsing it, so
+/// that it reads back what [`TypedPath::url`] wrote, and records the name
+/// of the parameter.
+#[doc(hidden)]
+pub fn __decoded_param<T: FromStr + Send + 'static>(
+    name: &'static str,
+) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
+    let filter = filter_segment(|seg| {
+        tracing::trace!("decoded param?: {:?}", seg);
+        parse_decoded(seg).map(one).ok_or_else(reject::not_found)
+    });
+    described(filter, move || Meta::NamedParam {
+        name,
+        ty: std::any::type_name::<T>(),
+    })
+}
+
+fn parse_decoded<T: FromStr>(seg: &str) -> Option<T> {
+    if seg.is_empty() {
+        return None;
+    }
+    let seg = percent_decode_str(seg).decode_utf8().ok()?;
+    T::from_str(&seg).ok()
+}
+
+#[doc(hidden)]
+pub fn __push_segment(url: &mut String, segment: &dyn fmt::Display) {
+    url.push('/');
+    url.extend(utf8_percent_encode(&segment.to_string(), SEGMENT));
+}
+
+/// Declare a typed route with named parameters.
+///
+/// This generates a struct with a public field per parameter, an associated
+/// `filter()` matching the full path and extracting the struct, and an
+/// implementation of [`TypedPath`](path::TypedPath) to build URLs back to
+/// the route, so links and redirects don't need string concatenation.
+///
+/// Parameter types are written with `Display`, percent-encoded, and
+/// parsed back with `FromStr` after percent-decoding.
+///
+/// # Example
+///
+/// ```
+/// use warp::path::TypedPath;
+/// use warp::Filter;
+///
+/// warp::typed_path! {
+///     /// GET /users/:id/posts
+///     pub struct UserPosts("users" / id: u64 / "posts");
+/// }
+///
+/// let posts = UserPosts::filter().map(|route: UserPosts| {
+///     format!("posts of user {}", route.id)
+/// });
+///
+/// assert_eq!(UserPosts { id: 42 }.url(), "/users/42/posts");
+///
+/// let moved = warp::path("old-posts").map(|| {
+///     warp::redirect::see_other(&UserPosts { id: 42 })
+/// });
+/// ```
+#[macro_export]
+macro_rules! typed_path {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident ($($segments:tt)*);) => {
+        $crate::__internal_typed_path!(@munch [$(#[$attr])* $vis $name] [] [] $($segments)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __internal_typed_path {
+    (@munch $head:tt [$($segs:tt)*] [$($fields:tt)*] $s:literal / $($rest:tt)+) => {
+        $crate::__internal_typed_path!(@munch $head [$($segs)* (lit $s)] [$($fields)*] $($rest)+);
+    };
+    (@munch $head:tt [$($segs:tt)*] [$($fields:tt)*] $s:literal) => {
+        $crate::__internal_typed_path!(@emit $head [$($segs)* (lit $s)] [$($fields)*]);
+    };
+    (@munch $head:tt [$($segs:tt)*] [$($fields:tt)*] $field:ident : $($ty:ident)::+ / $($rest:tt)+) => {
+        $crate::__internal_typed_path!(
+            @munch $head
+            [$($segs)* (param $field $($ty)::+)]
+            [$($fields)* ($field $($ty)::+)]
+            $($rest)+
+        );
+    };
+    (@munch $head:tt [$($segs:tt)*] [$($fields:tt)*] $field:ident : $($ty:ident)::+) => {
+        $crate::__internal_typed_path!(
+            @emit $head
+            [$($segs)* (param $field $($ty)::+)]
+            [$($fields)* ($field $($ty)::+)]
+        );
+    };
+    (@munch $head:tt [$($segs:tt)*] [$($fields:tt)*]) => {
+        $crate::__internal_typed_path!(@emit $head [$($segs)*] [$($fields)*]);
+    };
+    (@emit [$(#[$attr:meta])* $vis:vis $name:ident] [$($seg:tt)*] [$(($field:ident $ty:ty))*]) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq)]
+        $vis struct $name {
+            $(
+                #[allow(missing_docs)]
+                pub $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// A filter matching exactly this path, extracting its parameters.
+            #[allow(dead_code)]
+            $vis fn filter(
+            ) -> impl $crate::Filter<Extract = ($name,), Error = $crate::Rejection> + Copy {
+                let filter = $crate::any();
+                $(
+                    let filter = $crate::Filter::and(
+                        filter,
+                        $crate::__internal_typed_path!(@filter $seg),
+                    );
+                )*
+                $crate::Filter::map(
+                    $crate::Filter::and(filter, $crate::path::end()),
+                    |$($field: $ty),*| $name { $($field),* },
+                )
+            }
+        }
+
+        impl $crate::path::TypedPath for $name {
+            fn url(&self) -> String {
+                let mut url = String::new();
+                $($crate::__internal_typed_path!(@url self url $seg);)*
+                if url.is_empty() {
+                    url.push('/');
+                }
+                url
+            }
+        }
+    };
+    (@filter (lit $s:literal)) => {
+        $crate::path($s)
+    };
+    (@filter (param $field:ident $ty:ty)) => {
+        $crate::path::__decoded_param::<$ty>(stringify!($field))
+    };
+    (@url $this:ident $url:ident (lit $s:literal)) => {
+        $crate::path::__push_segment(&mut $url, &$s)
+    };
+    (@url $this:ident $url:ident (param $field:ident $ty:ty)) => {
+        $crate::path::__push_segment(&mut $url, &$this.$field)
+    };
+}
 mod internal {
     #[allow(missing_debug_implementations)]
     #[derive(Clone, Copy)]
@@ -321,7 +532,7 @@ mod internal {
     impl<T: AsRef<str>> AsRef<str> for Opaque<T> {
         #[inline]
         fn as_ref(&self) -> &str {
-            loop {}
+            self.0.as_ref()
         }
     }
 }
@@ -332,4 +543,74 @@ mod tests {
     fn test_path_exact_size() {
         loop {}
     }
+
+    crate::typed_path! {
+        struct Post("users" / user: u32 / "posts" / slug: String);
+    }
+
+    crate::typed_path! {
+        struct Root();
+    }
+
+    #[test]
+    fn test_typed_path_url() {
+        let post = Post {
+            user: 7,
+            slug: "hello world/?".to_owned(),
+        };
+        assert_eq!(post.url(), "/users/7/posts/hello%20world%2F%3F");
+        assert_eq!(Root {}.url(), "/");
+    }
+
+    #[test]
+    fn test_typed_path_round_trip() {
+        let post = Post {
+            user: 7,
+            slug: "50% off/now? #1".to_owned(),
+        };
+        let url = post.url();
+        let segments = url.split('/').skip(1).collect::<Vec<_>>();
+        assert_eq!(segments.len(), 4);
+        let parsed = Post {
+            user: parse_decoded(segments[1]).unwrap(),
+            slug: parse_decoded(segments[3]).unwrap(),
+        };
+        assert_eq!(parsed, post);
+        assert_eq!(parse_decoded::<u32>(""), None);
+        assert_eq!(parse_decoded::<String>("%FF"), None);
+    }
+
+    /// Run `filter` on a request for `path`, as the server would.
+    async fn run<F: Filter>(filter: &F, path: &str) -> Result<F::Extract, F::Error> {
+        use futures_util::future::poll_fn;
+        use std::future::Future;
+
+        let mut req = crate::Request::new(hyper::Body::empty());
+        *req.uri_mut() = path.parse().unwrap();
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || Box::pin(filter.filter(Internal)));
+        poll_fn(|cx| route::set(&route, || future.as_mut().poll(cx))).await
+    }
+
+    #[tokio::test]
+    async fn test_typed_path_filter() {
+        let (post,) = run(&Post::filter(), "/users/7/posts/50%25%20off%2Fnow")
+            .await
+            .unwrap();
+        assert_eq!(
+            post,
+            Post {
+                user: 7,
+                slug: "50% off/now".to_owned(),
+            }
+        );
+
+        let (root,) = run(&Root::filter(), "/").await.unwrap();
+        assert_eq!(root, Root {});
+
+        let filter = Post::filter();
+        assert!(run(&filter, "/users/seven/posts/a").await.is_err());
+        assert!(run(&filter, "/users/7/posts/a/b").await.is_err());
+        assert!(run(&filter, "/users/7/posts/%FF").await.is_err());
+    }
 }
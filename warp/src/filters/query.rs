@@ -4,7 +4,8 @@ use futures_util::future;
 use serde::de::DeserializeOwned;
 use serde_urlencoded;
 
-use crate::filter::{filter_fn_one, Filter, One};
+use crate::filter::{described, filter_fn_one, Filter, One};
+use crate::filters::openapi::Meta;
 use crate::reject::{self, Rejection};
 
 
@@ -64,7 +65,7 @@ use crate::reject::{self, Rejection};
 
 pub fn query<T: DeserializeOwned + Send + 'static>(
 ) -> impl Filter<Extract = One<T>, Error = Rejection> + Copy {
-    filter_fn_one(|route| {
+    let filter = filter_fn_one(|route| {
         let query_string = route.query().unwrap_or_else(|| {
             tracing::debug!("route was called without a query string, defaulting to empty");
             ""
@@ -75,7 +76,8 @@ pub fn query<T: DeserializeOwned + Send + 'static>(
             reject::invalid_query()
         });
         future::ready(query_encoded)
-    })
+    });
+    described(filter, || Meta::Query(std::any::type_name::<T>()))
 }
 
 
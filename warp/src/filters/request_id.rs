@@ -0,0 +1,292 @@
+//! Request ID Filters
+//!
+//! Tag every request with an ID, so the log lines, spans and replies of a
+//! request can be correlated, even across services.
+//!
+//! The [`request_id`](request_id()) wrapper reuses the ID sent by the client
+//! (or a proxy in front of the server) in `X-Request-Id`, or generates a new
+//! one. The ID is echoed in the reply, recorded by
+//! [`trace::request`](crate::trace::request) and
+//! [`log::with_request_id`](crate::log::with_request_id), available to
+//! [`log::custom`](crate::log::custom) loggers through
+//! [`Info::request_id`](crate::log::Info::request_id), and can be extracted
+//! by handlers with [`current`].
+//!
+//! Rejections are rendered into their default response by the wrapper, so
+//! that it can carry the ID too. Wrap the whole set of routes, after any
+//! [`recover`](crate::Filter::recover), rather than a single branch of an
+//! `or`.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::Filter;
+//! use warp::request_id::RequestId;
+//!
+//! let route = warp::path("hello")
+//!     .and(warp::request_id::current())
+//!     .map(|id: RequestId| format!("handling request {}", id))
+//!     .with(warp::trace::request())
+//!     .with(warp::request_id());
+//! ```
+
+use std::fmt;
+
+use http::header::{HeaderName, HeaderValue};
+
+use self::internal::WithRequestId;
+use crate::filter::{Filter, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::Reply;
+
+/// Tag requests with an ID read from `X-Request-Id`, or generated if
+/// missing, and echo it in the reply's `X-Request-Id`.
+///
+/// Rejections from the wrapped filter are turned into their default
+/// response, carrying the ID like any other reply.
+///
+/// Incoming IDs are only reused if they are at most 128 visible ASCII
+/// characters; anything else is replaced with a generated ID.
+///
+/// To be recorded by [`trace`](crate::trace) spans, this must wrap the
+/// traced filter, that is, come *after* it in the `with` chain.
+pub fn request_id() -> RequestIds {
+    RequestIds {
+        header: HeaderName::from_static("x-request-id"),
+    }
+}
+
+/// Extract the ID of the current request.
+///
+/// Rejects if the route isn't wrapped with [`request_id`](request_id()).
+pub fn current() -> impl Filter<Extract = (RequestId,), Error = Rejection> + Copy {
+    crate::ext::get::<RequestId>()
+}
+
+/// Decorates a [`Filter`] to tag requests with an ID.
+///
+/// Created with [`request_id`](request_id()).
+#[derive(Clone, Debug)]
+pub struct RequestIds {
+    header: HeaderName,
+}
+
+impl RequestIds {
+    /// Read and echo the ID in the header `name` instead of `X-Request-Id`.
+    pub fn header(mut self, name: HeaderName) -> Self {
+        self.header = name;
+        self
+    }
+}
+
+impl<F> WrapSealed<F> for RequestIds
+where
+    F: Filter + Clone + Send,
+    F::Extract: Reply,
+    F::Error: IsReject,
+{
+    type Wrapped = WithRequestId<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithRequestId {
+            filter,
+            ids: self.clone(),
+        }
+    }
+}
+
+/// The ID of a request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(HeaderValue);
+
+impl RequestId {
+    fn from_header(value: &HeaderValue) -> Option<RequestId> {
+        let bytes = value.as_bytes();
+        let valid = !bytes.is_empty()
+            && bytes.len() <= 128
+            && bytes.iter().all(|b| b.is_ascii_graphic());
+        if valid {
+            Some(RequestId(value.clone()))
+        } else {
+            None
+        }
+    }
+
+    fn generate() -> RequestId {
+        let id = format!("{:032x}", rand::random::<u128>());
+        RequestId(HeaderValue::from_str(&id).expect("hex is a valid header value"))
+    }
+
+    /// The ID as a string.
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().expect("request IDs are visible ASCII")
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+mod internal {
+    use std::convert::Infallible;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_util::{ready, TryFuture};
+    use pin_project::pin_project;
+
+    use super::{RequestId, RequestIds};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
+    use crate::reject::IsReject;
+    use crate::reply::{Reply, Response};
+    use crate::route;
+
+    #[allow(missing_debug_implementations)]
+    pub struct Tagged(Response);
+
+    impl Reply for Tagged {
+        #[inline]
+        fn into_response(self) -> Response {
+            self.0
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone)]
+    pub struct WithRequestId<F> {
+        pub(super) filter: F,
+        pub(super) ids: RequestIds,
+    }
+
+    impl<F> FilterBase for WithRequestId<F>
+    where
+        F: Filter + Clone + Send,
+        F::Extract: Reply,
+        F::Error: IsReject,
+    {
+        type Extract = (Tagged,);
+        type Error = Infallible;
+        type Future = WithRequestIdFuture<F::Future>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let id = route::with(|route| {
+                let id = route
+                    .headers()
+                    .get(&self.ids.header)
+                    .and_then(RequestId::from_header)
+                    .unwrap_or_else(RequestId::generate);
+                route.extensions_mut().insert(id.clone());
+                id
+            });
+            WithRequestIdFuture {
+                future: self.filter.filter(Internal),
+                ids: self.ids.clone(),
+                id: Some(id),
+            }
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[pin_project]
+    pub struct WithRequestIdFuture<F> {
+        #[pin]
+        future: F,
+        ids: RequestIds,
+        id: Option<RequestId>,
+    }
+
+    impl<F> Future for WithRequestIdFuture<F>
+    where
+        F: TryFuture,
+        F::Ok: Reply,
+        F::Error: IsReject,
+    {
+        type Output = Result<(Tagged,), Infallible>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let pin = self.project();
+            let mut res = match ready!(pin.future.try_poll(cx)) {
+                Ok(reply) => reply.into_response(),
+                Err(err) => {
+                    tracing::debug!("request_id: rendering rejection {:?}", err);
+                    err.into_response()
+                }
+            };
+            if let Some(id) = pin.id.take() {
+                res.headers_mut().insert(pin.ids.header.clone(), id.0);
+            }
+            Poll::Ready(Ok((Tagged(res),)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use futures_util::future::{self, poll_fn};
+    use http::{HeaderValue, Request, StatusCode};
+
+    use super::{request_id, RequestId};
+    use crate::filter::{FilterBase, Internal, WrapSealed};
+    use crate::reject::{self, Rejection};
+    use crate::reply::Reply;
+    use crate::route::{self, Route};
+
+    /// Rejects every request, as an unmatched route would.
+    #[derive(Clone)]
+    struct NotFound;
+
+    impl FilterBase for NotFound {
+        type Extract = (StatusCode,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(StatusCode,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            future::err(reject::not_found())
+        }
+    }
+
+    #[test]
+    fn incoming_ids_validated() {
+        let id = RequestId::from_header(&HeaderValue::from_static("abc-123")).unwrap();
+        assert_eq!(id.as_str(), "abc-123");
+
+        assert!(RequestId::from_header(&HeaderValue::from_static("")).is_none());
+        assert!(RequestId::from_header(&HeaderValue::from_static("a b")).is_none());
+        let long = "a".repeat(129);
+        assert!(RequestId::from_header(&HeaderValue::from_str(&long).unwrap()).is_none());
+    }
+
+    #[test]
+    fn generated_ids_unique() {
+        let a = RequestId::generate();
+        let b = RequestId::generate();
+        assert_ne!(a, b);
+        assert_eq!(a.as_str().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn rejections_carry_id() {
+        let filter = request_id().wrap(NotFound);
+        let req = Request::builder()
+            .header("x-request-id", "abc-123")
+            .body(Default::default())
+            .unwrap();
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        let result = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await;
+        let (reply,) = result.unwrap();
+        let res = reply.into_response();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers()["x-request-id"], "abc-123");
+    }
+}
@@ -0,0 +1,410 @@
+//! Prefix-trie Router
+//!
+//! Chaining hundreds of routes with [`Filter::or`](crate::Filter::or) means
+//! every request walks the whole chain, re-running the path filters of each
+//! branch until one matches. A [`Router`] instead indexes each route by its
+//! static path prefix and method up front, so a request is dispatched by
+//! walking its path once through a trie, and only the filters registered
+//! under a matching prefix are run.
+//!
+//! The filter of each route sees the path *after* its prefix, so it can
+//! continue with `warp::path::param()`, `warp::path::end()`, and friends.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::{http::Method, Filter};
+//!
+//! let list = warp::path::end().map(|| "all users".to_string()).boxed();
+//! let show = warp::path::param()
+//!     .and(warp::path::end())
+//!     .map(|id: u32| format!("user #{}", id))
+//!     .boxed();
+//! let health = warp::any().map(|| "ok".to_string()).boxed();
+//!
+//! // GET /users
+//! // GET /users/:u32
+//! // *   /health
+//! let routes = warp::router()
+//!     .route(Method::GET, "users", list)
+//!     .route(Method::GET, "users", show)
+//!     .any("health", health)
+//!     .build();
+//! ```
+//!
+//! Routes whose prefixes both match a request are tried in the order they
+//! were added, and their rejections are combined exactly like `or` would.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use http::Method;
+
+use crate::filter::{BoxedFilter, Describe, FilterBase, Internal, Tuple};
+use crate::filters::openapi::Meta;
+use crate::reject::{self, CombineRejection, Rejection};
+use crate::route;
+
+/// Create a new [`Builder`] for a prefix-trie [`Router`].
+///
+/// Every route added to the router must extract the same type `T`, just as
+/// the branches of `or(...).unify()` must.
+pub fn router<T: Tuple>() -> Builder<T> {
+    Builder {
+        root: Node::default(),
+        len: 0,
+    }
+}
+
+/// A builder that collects the routes of a [`Router`].
+pub struct Builder<T: Tuple> {
+    root: Node<Entry<T>>,
+    len: usize,
+}
+
+impl<T: Tuple> Builder<T> {
+    /// Add a route matching requests with `method` whose path starts with
+    /// the static `prefix`, such as `"api/v1/users"`.
+    pub fn route(self, method: Method, prefix: &str, filter: BoxedFilter<T>) -> Self {
+        self.insert(Some(method), prefix, filter)
+    }
+
+    /// Add a route matching requests of any method whose path starts with
+    /// the static `prefix`.
+    pub fn any(self, prefix: &str, filter: BoxedFilter<T>) -> Self {
+        self.insert(None, prefix, filter)
+    }
+
+    /// Finish building, returning a `Router` filter.
+    pub fn build(self) -> Router<T> {
+        Router {
+            root: Arc::new(self.root),
+        }
+    }
+
+    fn insert(mut self, method: Option<Method>, prefix: &str, filter: BoxedFilter<T>) -> Self {
+        let entry = Entry {
+            order: self.len,
+            method,
+            filter,
+        };
+        self.root.insert(prefix, entry);
+        self.len += 1;
+        self
+    }
+}
+
+impl<T: Tuple> fmt::Debug for Builder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Builder").field("routes", &self.len).finish()
+    }
+}
+
+/// A filter dispatching requests through a trie of static path prefixes.
+///
+/// Created with [`router`](router()).
+pub struct Router<T: Tuple> {
+    root: Arc<Node<Entry<T>>>,
+}
+
+impl<T: Tuple> Clone for Router<T> {
+    fn clone(&self) -> Self {
+        Router {
+            root: self.root.clone(),
+        }
+    }
+}
+
+impl<T: Tuple> fmt::Debug for Router<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router").finish()
+    }
+}
+
+impl<T: Tuple + Send + 'static> FilterBase for Router<T> {
+    type Extract = T;
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<T, Rejection>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let (start, candidates) = route::with(|route| {
+            let mut matches = self.root.matches(route.path());
+            matches.sort_by_key(|(_, entry)| entry.order);
+            let candidates = matches
+                .into_iter()
+                .map(|(end, entry)| {
+                    let allowed = entry
+                        .method
+                        .as_ref()
+                        .map_or(true, |method| method == route.method());
+                    tracing::trace!(
+                        "router: prefix match ({} bytes), method allowed: {}",
+                        end,
+                        allowed
+                    );
+                    Candidate {
+                        end,
+                        filter: match entry.method {
+                            Some(ref method) if !allowed => Err(method.clone()),
+                            _ => Ok(entry.filter.clone()),
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            (route.matched_path_index(), candidates)
+        });
+
+        Box::pin(async move {
+            let mut rejection: Option<Rejection> = None;
+            for Candidate { end, filter } in candidates {
+                let result = match filter {
+                    Ok(filter) => {
+                        route::with(|route| {
+                            route.reset_matched_path_index(start);
+                            if end > 0 {
+                                route.set_unmatched_path(end);
+                            }
+                        });
+                        filter.filter(Internal).await
+                    }
+                    Err(method) => Err(reject::method_not_allowed(method)),
+                };
+                match result {
+                    Ok(ex) => return Ok(ex),
+                    Err(err) => {
+                        route::with(|route| route.reset_matched_path_index(start));
+                        rejection = Some(match rejection.take() {
+                            Some(prev) => err.combine(prev),
+                            None => err,
+                        });
+                    }
+                }
+            }
+            Err(rejection.unwrap_or_else(reject::not_found))
+        })
+    }
+
+    fn describe(&self, _: Internal) -> Describe {
+        let mut routes = Vec::new();
+        self.root.walk(&mut Vec::new(), &mut |prefix, entry| {
+            let prefix = prefix
+                .iter()
+                .map(|seg| Describe::Leaf(Meta::Path(seg.clone())))
+                .fold(Describe::Empty, Describe::and);
+            let method = entry
+                .method
+                .clone()
+                .map_or(Describe::Empty, |method| Describe::Leaf(Meta::Method(method)));
+            let route = Describe::and(
+                Describe::and(prefix, method),
+                entry.filter.describe(Internal),
+            );
+            routes.push((entry.order, route));
+        });
+        routes.sort_by_key(|(order, _)| *order);
+        routes
+            .into_iter()
+            .map(|(_, route)| route)
+            .reduce(Describe::or)
+            .unwrap_or(Describe::Empty)
+    }
+}
+
+struct Candidate<T: Tuple> {
+    end: usize,
+    filter: Result<BoxedFilter<T>, Method>,
+}
+
+#[derive(Debug)]
+struct Entry<T: Tuple> {
+    order: usize,
+    method: Option<Method>,
+    filter: BoxedFilter<T>,
+}
+
+#[derive(Debug)]
+struct Node<V> {
+    values: Vec<V>,
+    children: HashMap<String, Node<V>>,
+}
+
+impl<V> Default for Node<V> {
+    fn default() -> Self {
+        Node {
+            values: Vec::new(),
+            children: HashMap::new(),
+        }
+    }
+}
+
+impl<V> Node<V> {
+    fn insert(&mut self, prefix: &str, value: V) {
+        let node = prefix
+            .split('/')
+            .filter(|seg| !seg.is_empty())
+            .fold(self, |node, seg| node.children.entry(seg.to_owned()).or_default());
+        node.values.push(value);
+    }
+
+    fn walk<'a, F>(&'a self, prefix: &mut Vec<String>, f: &mut F)
+    where
+        F: FnMut(&[String], &'a V),
+    {
+        for value in &self.values {
+            f(prefix, value);
+        }
+        for (seg, child) in &self.children {
+            prefix.push(seg.clone());
+            child.walk(prefix, f);
+            prefix.pop();
+        }
+    }
+
+    /// Walk `path` through the trie, returning the values of every node on
+    /// the way along with the byte length of `path` its prefix covers.
+    fn matches(&self, path: &str) -> Vec<(usize, &V)> {
+        let mut matches = self.values.iter().map(|v| (0, v)).collect::<Vec<_>>();
+        let mut node = self;
+        let mut start = 0;
+        for seg in path.split('/') {
+            if seg.is_empty() {
+                break;
+            }
+            node = match node.children.get(seg) {
+                Some(child) => child,
+                None => break,
+            };
+            let end = start + seg.len();
+            matches.extend(node.values.iter().map(|v| (end, v)));
+            start = end + 1;
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    use futures_util::future::{self, poll_fn};
+    use http::{Method, StatusCode};
+    use hyper::Body;
+
+    use super::{router, Node, Router};
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::reject::{self, IsReject, Rejection};
+    use crate::route::{self, Route};
+    use crate::Request;
+
+    /// Extracts its name if the path left after the route's prefix is
+    /// exactly `rest`, and rejects as not found otherwise.
+    #[derive(Clone)]
+    struct Rest(&'static str, &'static str);
+
+    impl FilterBase for Rest {
+        type Extract = (String,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(String,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            if route::with(|route| route.path() == self.1) {
+                future::ok((self.0.to_owned(),))
+            } else {
+                future::err(reject::not_found())
+            }
+        }
+    }
+
+    fn users() -> Router<(String,)> {
+        router()
+            .route(Method::GET, "users", Rest("list", "").boxed())
+            .route(Method::GET, "users", Rest("show", "5").boxed())
+            .route(Method::POST, "users", Rest("create", "").boxed())
+            .any("users/admins", Rest("admins", "").boxed())
+            .build()
+    }
+
+    /// Dispatch a request through `router`, checking the matched path is
+    /// reset to where it started if every route rejects.
+    async fn dispatch(
+        router: &Router<(String,)>,
+        method: Method,
+        path: &str,
+    ) -> Result<String, Rejection> {
+        let mut req = Request::new(Body::empty());
+        *req.method_mut() = method;
+        *req.uri_mut() = path.parse().unwrap();
+        let route = Route::new(req, None);
+        let start = route.borrow().matched_path_index();
+        let mut future = route::set(&route, || router.filter(Internal));
+        let result = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await;
+        if result.is_err() {
+            assert_eq!(route.borrow().matched_path_index(), start);
+        }
+        result.map(|(name,)| name)
+    }
+
+    #[tokio::test]
+    async fn router_dispatches_overlapping_prefixes() {
+        let router = users();
+
+        assert_eq!(dispatch(&router, Method::GET, "/users").await.unwrap(), "list");
+        // `list` rejects, and `show` sees the same rest of the path.
+        assert_eq!(dispatch(&router, Method::GET, "/users/5").await.unwrap(), "show");
+        assert_eq!(dispatch(&router, Method::POST, "/users").await.unwrap(), "create");
+        // The longer prefix is tried after the shorter ones reject.
+        assert_eq!(
+            dispatch(&router, Method::GET, "/users/admins").await.unwrap(),
+            "admins"
+        );
+    }
+
+    #[tokio::test]
+    async fn router_rejects_method_mismatch() {
+        let err = dispatch(&users(), Method::DELETE, "/users")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::METHOD_NOT_ALLOWED);
+        let mut allowed = err.allowed_methods();
+        allowed.dedup();
+        assert_eq!(allowed, [Method::POST, Method::GET]);
+
+        // A route that matched the prefix but not the rest of the path
+        // still loses to the method mismatch of its siblings.
+        let err = dispatch(&users(), Method::GET, "/users/6")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn router_rejects_miss_as_not_found() {
+        let err = dispatch(&users(), Method::GET, "/posts")
+            .await
+            .unwrap_err();
+        assert!(err.is_not_found());
+    }
+
+    #[test]
+    fn trie_matches_prefixes() {
+        let mut root = Node::default();
+        root.insert("", 0);
+        root.insert("users", 1);
+        root.insert("/users/admins/", 2);
+        root.insert("posts", 3);
+
+        assert_eq!(root.matches(""), vec![(0, &0)]);
+        assert_eq!(root.matches("users"), vec![(0, &0), (5, &1)]);
+        assert_eq!(root.matches("users/5"), vec![(0, &0), (5, &1)]);
+        assert_eq!(
+            root.matches("users/admins/5"),
+            vec![(0, &0), (5, &1), (12, &2)]
+        );
+        assert_eq!(root.matches("usersx"), vec![(0, &0)]);
+        assert_eq!(root.matches("posts/"), vec![(0, &0), (5, &3)]);
+    }
+}
@@ -0,0 +1,842 @@
+//! Cookie Sessions
+//!
+//! The [`signed`] and [`encrypted`] wrappers load a [`Session`] from a
+//! cookie protected with a server key, make it available to handlers, and
+//! write it back in a `Set-Cookie` header if it changed.
+//!
+//! By default the whole session is kept in the cookie, so it should stay
+//! small. With a server-side [`Store`], the cookie only holds a random
+//! session ID instead.
+//!
+//! Keys can be rotated: sessions protected with any of the keys added with
+//! [`Sessions::rotate`] are still accepted, and are re-issued with the
+//! current key.
+//!
+//! Cookies carry the time they were issued under the key, so that sessions
+//! with a [`max_age`](Sessions::max_age) expire on the server too, even if a
+//! client keeps sending the cookie. Without a [`Store`], that's the only
+//! way a session ends: [`Session::destroy`] removes the client's cookie,
+//! but copies of it stay valid until they expire.
+//!
+//! # Example
+//!
+//! ```
+//! use warp::session::{self, Key, Session};
+//! use warp::Filter;
+//!
+//! let key = Key::derive_from(b"a secret of at least thirty-two bytes long");
+//!
+//! let visits = warp::path("visits")
+//!     .and(session::get())
+//!     .map(|session: Session| {
+//!         let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+//!         session.insert("visits", visits);
+//!         format!("visit #{}", visits)
+//!     })
+//!     .with(session::encrypted(key).cookie_name("sid"));
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use self::internal::WithSession;
+use crate::filter::{Filter, WrapSealed};
+use crate::reject::{IsReject, Rejection};
+use crate::reply::Reply;
+
+pub use crate::filters::cookie::SameSite;
+
+/// The data of a session.
+pub type Data = Map<String, Value>;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// A future returned by a [`Store`].
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send + 'a>>;
+
+/// Keep sessions in cookies signed with `key`.
+///
+/// Clients can read the contents of signed sessions, but not tamper with
+/// them.
+pub fn signed(key: Key) -> Sessions {
+    Sessions::new(key, Protection::Signed)
+}
+
+/// Keep sessions in cookies encrypted with `key`.
+///
+/// Clients can neither read nor tamper with encrypted sessions.
+pub fn encrypted(key: Key) -> Sessions {
+    Sessions::new(key, Protection::Encrypted)
+}
+
+/// Extract the current [`Session`].
+///
+/// Rejects if the route isn't wrapped with [`signed`] or [`encrypted`].
+pub fn get() -> impl Filter<Extract = (Session,), Error = Rejection> + Copy {
+    crate::ext::get::<Session>()
+}
+
+/// A key used to sign or encrypt session cookies.
+#[derive(Clone)]
+pub struct Key(::cookie::Key);
+
+impl Key {
+    /// Use 64 bytes of random data as a key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is shorter than 64 bytes.
+    pub fn from_bytes(key: &[u8]) -> Key {
+        Key(::cookie::Key::from(key))
+    }
+
+    /// Derive a key from a secret of at least 32 bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than 32 bytes.
+    pub fn derive_from(secret: &[u8]) -> Key {
+        Key(::cookie::Key::derive_from(secret))
+    }
+
+    /// Generate a random key.
+    ///
+    /// Sessions protected with a generated key don't survive a restart of
+    /// the server, unless the key is saved with [`master`](Key::master).
+    pub fn generate() -> Key {
+        Key(::cookie::Key::generate())
+    }
+
+    /// The raw bytes of this key, which can be given back to
+    /// [`from_bytes`](Key::from_bytes).
+    pub fn master(&self) -> &[u8] {
+        self.0.master()
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Key").finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Protection {
+    Signed,
+    Encrypted,
+}
+
+/// Decorates a [`Filter`] to load and save a [`Session`].
+///
+/// Created with [`signed`] or [`encrypted`].
+#[derive(Clone, Debug)]
+pub struct Sessions {
+    config: Arc<Config>,
+}
+
+#[derive(Clone)]
+struct Config {
+    protection: Protection,
+    key: Key,
+    old_keys: Vec<Key>,
+    store: Option<Arc<dyn Store>>,
+    name: String,
+    path: String,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("protection", &self.protection)
+            .field("old_keys", &self.old_keys.len())
+            .field("store", &self.store.is_some())
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("domain", &self.domain)
+            .field("max_age", &self.max_age)
+            .field("secure", &self.secure)
+            .field("http_only", &self.http_only)
+            .field("same_site", &self.same_site)
+            .finish()
+    }
+}
+
+impl Sessions {
+    fn new(key: Key, protection: Protection) -> Sessions {
+        Sessions {
+            config: Arc::new(Config {
+                protection,
+                key,
+                old_keys: Vec::new(),
+                store: None,
+                name: "session".to_owned(),
+                path: "/".to_owned(),
+                domain: None,
+                max_age: None,
+                secure: true,
+                http_only: true,
+                same_site: SameSite::Lax,
+            }),
+        }
+    }
+
+    fn config(mut self, f: impl FnOnce(&mut Config)) -> Self {
+        f(Arc::make_mut(&mut self.config));
+        self
+    }
+
+    /// Still accept sessions protected with a previous `key`.
+    ///
+    /// Such sessions are re-issued with the current key on their next
+    /// request. Can be called multiple times to accept several old keys.
+    pub fn rotate(self, key: Key) -> Self {
+        self.config(|config| config.old_keys.push(key))
+    }
+
+    /// Keep session data in `store`, instead of in the cookie.
+    pub fn store(self, store: impl Store) -> Self {
+        self.config(|config| config.store = Some(Arc::new(store)))
+    }
+
+    /// Set the name of the session cookie. Defaults to `session`.
+    pub fn cookie_name(self, name: impl Into<String>) -> Self {
+        self.config(|config| config.name = name.into())
+    }
+
+    /// Set the `Path` of the session cookie. Defaults to `/`.
+    pub fn path(self, path: impl Into<String>) -> Self {
+        self.config(|config| config.path = path.into())
+    }
+
+    /// Set the `Domain` of the session cookie.
+    pub fn domain(self, domain: impl Into<String>) -> Self {
+        self.config(|config| config.domain = Some(domain.into()))
+    }
+
+    /// Set the `Max-Age` of the session cookie. By default, sessions end
+    /// when the browser is closed.
+    ///
+    /// Cookies older than `max_age` are also ignored by the server. The age
+    /// counts from the last time the session was saved, which happens when
+    /// it changes.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        self.config(|config| config.max_age = Some(max_age))
+    }
+
+    /// Set whether the session cookie is only sent over HTTPS. Defaults to
+    /// `true`.
+    pub fn secure(self, secure: bool) -> Self {
+        self.config(|config| config.secure = secure)
+    }
+
+    /// Set whether the session cookie is hidden from scripts. Defaults to
+    /// `true`.
+    pub fn http_only(self, http_only: bool) -> Self {
+        self.config(|config| config.http_only = http_only)
+    }
+
+    /// Set the `SameSite` of the session cookie. Defaults to `Lax`.
+    pub fn same_site(self, same_site: SameSite) -> Self {
+        self.config(|config| config.same_site = same_site)
+    }
+}
+
+impl<F> WrapSealed<F> for Sessions
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+    F::Future: Send,
+    F::Error: IsReject,
+{
+    type Wrapped = WithSession<F>;
+
+    fn wrap(&self, filter: F) -> Self::Wrapped {
+        WithSession {
+            filter,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// The session of a request.
+///
+/// This is a handle: clones share the same session, so changes made by a
+/// handler are seen by the wrapper saving it.
+#[derive(Clone, Debug)]
+pub struct Session {
+    inner: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    id: Option<String>,
+    data: Data,
+    changed: bool,
+    destroyed: bool,
+}
+
+impl Session {
+    fn new(id: Option<String>, data: Data) -> Session {
+        Session {
+            inner: Arc::new(Mutex::new(State {
+                id,
+                data,
+                ..State::default()
+            })),
+        }
+    }
+
+    /// Get the value of `key`, if set and deserializable as `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let state = self.inner.lock().unwrap();
+        let value = state.data.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    /// Set `key` to `value`.
+    ///
+    /// Values that fail to serialize are skipped.
+    pub fn insert<T: Serialize>(&self, key: impl Into<String>, value: T) {
+        let key = key.into();
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                let mut state = self.inner.lock().unwrap();
+                state.data.insert(key, value);
+                state.changed = true;
+            }
+            Err(err) => {
+                tracing::error!("session value {:?} serialize error: {}", key, err);
+            }
+        }
+    }
+
+    /// Remove `key`, returning whether it was set.
+    pub fn remove(&self, key: &str) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        let removed = state.data.remove(key).is_some();
+        state.changed |= removed;
+        removed
+    }
+
+    /// Remove every key.
+    pub fn clear(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.changed |= !state.data.is_empty();
+        state.data.clear();
+    }
+
+    /// End the session, removing its cookie and its data from the store.
+    ///
+    /// Without a [`Store`], copies of the cookie taken before are still
+    /// accepted until they reach the [`max_age`](Sessions::max_age).
+    pub fn destroy(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+
+    /// Keep the data, but move it to a new session ID, such as after
+    /// logging in, to prevent session fixation.
+    ///
+    /// Only has an effect with a [`Store`].
+    pub fn regenerate(&self) {
+        let mut state = self.inner.lock().unwrap();
+        state.changed = true;
+        state.id = None;
+    }
+}
+
+/// A server-side store of session data.
+pub trait Store: Send + Sync + 'static {
+    /// Load the data of the session `id`, if it exists.
+    fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Data>>;
+
+    /// Save the data of the session `id`.
+    fn save<'a>(&'a self, id: &'a str, data: &'a Data) -> StoreFuture<'a, ()>;
+
+    /// Remove the session `id`.
+    fn destroy<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()>;
+}
+
+/// A [`Store`] keeping sessions in memory.
+///
+/// Sessions are lost when the server restarts, and aren't shared between
+/// servers. Sessions are never expired, so this store is mostly useful for
+/// development and tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, Data>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// The number of sessions in the store.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    /// Whether the store has no sessions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Store for MemoryStore {
+    fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Data>> {
+        let data = self.sessions.lock().unwrap().get(id).cloned();
+        Box::pin(async move { Ok(data) })
+    }
+
+    fn save<'a>(&'a self, id: &'a str, data: &'a Data) -> StoreFuture<'a, ()> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.to_owned(), data.clone());
+        Box::pin(async { Ok(()) })
+    }
+
+    fn destroy<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()> {
+        self.sessions.lock().unwrap().remove(id);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<S: Store> Store for Arc<S> {
+    fn load<'a>(&'a self, id: &'a str) -> StoreFuture<'a, Option<Data>> {
+        (**self).load(id)
+    }
+
+    fn save<'a>(&'a self, id: &'a str, data: &'a Data) -> StoreFuture<'a, ()> {
+        (**self).save(id, data)
+    }
+
+    fn destroy<'a>(&'a self, id: &'a str) -> StoreFuture<'a, ()> {
+        (**self).destroy(id)
+    }
+}
+
+mod codec {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use ::cookie::CookieJar;
+    use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+    use rand::RngCore;
+
+    use super::{Config, Protection};
+    use crate::filters::cookie::Cookie;
+
+    /// Characters percent-encoded in protected values, as they aren't
+    /// allowed in cookies. [`open`] decodes them back.
+    const VALUE: &AsciiSet = &CONTROLS
+        .add(b' ')
+        .add(b'"')
+        .add(b'%')
+        .add(b',')
+        .add(b';')
+        .add(b'\\');
+
+    /// Find the cookie `name` in a `Cookie` header, and verify it with the
+    /// current key, then with the old ones.
+    ///
+    /// Returns the value, and whether it was protected with an old key.
+    pub(super) fn open(config: &Config, header: &str) -> Option<(String, bool)> {
+        open_at(config, header, SystemTime::now())
+    }
+
+    /// Like [`open`], checking the age of the cookie as of `now`.
+    pub(super) fn open_at(
+        config: &Config,
+        header: &str,
+        now: SystemTime,
+    ) -> Option<(String, bool)> {
+        let mut jar = CookieJar::new();
+        header
+            .split(';')
+            .filter_map(|pair| ::cookie::Cookie::parse_encoded(pair.trim().to_owned()).ok())
+            .filter(|cookie| cookie.name() == config.name)
+            .for_each(|cookie| jar.add_original(cookie));
+
+        std::iter::once(&config.key)
+            .chain(&config.old_keys)
+            .enumerate()
+            .find_map(|(i, key)| {
+                let cookie = match config.protection {
+                    Protection::Signed => jar.signed(&key.0).get(&config.name),
+                    Protection::Encrypted => jar.private(&key.0).get(&config.name),
+                }?;
+                Some((cookie.value().to_owned(), i > 0))
+            })
+            .and_then(|(payload, rotated)| {
+                let value = unexpired(config, &payload, now)?;
+                Some((value.to_owned(), rotated))
+            })
+    }
+
+    /// Split a payload into its issue time and value, checking the former
+    /// against the `max_age`.
+    fn unexpired<'a>(config: &Config, payload: &'a str, now: SystemTime) -> Option<&'a str> {
+        let (issued, value) = payload.split_once(':')?;
+        let issued = UNIX_EPOCH + Duration::from_secs(issued.parse().ok()?);
+        if let Some(max_age) = config.max_age {
+            let age = now.duration_since(issued).unwrap_or_default();
+            if age > max_age {
+                tracing::debug!("session cookie expired {:?} ago", age - max_age);
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    /// Protect `value` with the current key, returning the cookie to set.
+    pub(super) fn seal(config: &Config, value: String) -> Cookie {
+        seal_at(config, value, SystemTime::now())
+    }
+
+    /// Protect `value` as if issued at `now`.
+    pub(super) fn seal_at(config: &Config, value: String, now: SystemTime) -> Cookie {
+        let issued = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let mut jar = CookieJar::new();
+        let payload = format!("{}:{}", issued, value);
+        let cookie = ::cookie::Cookie::new(config.name.clone(), payload);
+        match config.protection {
+            Protection::Signed => jar.signed_mut(&config.key.0).add(cookie),
+            Protection::Encrypted => jar.private_mut(&config.key.0).add(cookie),
+        }
+        let sealed = jar.get(&config.name).expect("cookie was just added").value();
+        build(config, utf8_percent_encode(sealed, VALUE).to_string())
+    }
+
+    /// The cookie removing the session cookie.
+    pub(super) fn removal(config: &Config) -> Cookie {
+        build(config, String::new())
+            .max_age(Duration::from_secs(0))
+            .expires(SystemTime::UNIX_EPOCH)
+    }
+
+    fn build(config: &Config, value: String) -> Cookie {
+        let mut cookie = Cookie::new(config.name.clone(), value)
+            .path(config.path.clone())
+            .secure(config.secure)
+            .http_only(config.http_only)
+            .same_site(config.same_site);
+        if let Some(ref domain) = config.domain {
+            cookie = cookie.domain(domain.clone());
+        }
+        if let Some(max_age) = config.max_age {
+            cookie = cookie.max_age(max_age);
+        }
+        cookie
+    }
+
+    /// A new random session ID.
+    pub(super) fn new_id() -> String {
+        let mut id = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut id);
+        base64::encode_config(id, base64::URL_SAFE_NO_PAD)
+    }
+}
+
+mod internal {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use http::header::{COOKIE, SET_COOKIE};
+
+    use super::{codec, Config, Data, Session};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
+    use crate::filters::cookie::Cookie;
+    use crate::reject::IsReject;
+    use crate::reply::{Reply, Response};
+    use crate::route;
+
+    #[allow(missing_debug_implementations)]
+    pub struct WithSessionReply(Response);
+
+    impl Reply for WithSessionReply {
+        #[inline]
+        fn into_response(self) -> Response {
+            self.0
+        }
+    }
+
+    #[allow(missing_debug_implementations)]
+    #[derive(Clone)]
+    pub struct WithSession<F> {
+        pub(super) filter: F,
+        pub(super) config: Arc<Config>,
+    }
+
+    impl<F> FilterBase for WithSession<F>
+    where
+        F: Filter + Clone + Send + Sync + 'static,
+        F::Extract: Reply,
+        F::Future: Send,
+        F::Error: IsReject,
+    {
+        type Extract = (WithSessionReply,);
+        type Error = F::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let filter = self.filter.clone();
+            let config = self.config.clone();
+            Box::pin(async move {
+                let opened = route::with(|route| {
+                    route
+                        .headers()
+                        .get_all(COOKIE)
+                        .iter()
+                        .filter_map(|value| value.to_str().ok())
+                        .find_map(|header| codec::open(&config, header))
+                });
+                let (session, rotated) = load(&config, opened).await;
+                route::with(|route| route.extensions_mut().insert(session.clone()));
+
+                let reply = filter.filter(Internal).await?;
+                let mut res = reply.into_response();
+                if let Some(cookie) = save(&config, &session, rotated).await {
+                    match cookie.to_header_value() {
+                        Some(value) => {
+                            res.headers_mut().append(SET_COOKIE, value);
+                        }
+                        None => tracing::error!("session cookie invalid: {:?}", cookie.name()),
+                    }
+                }
+                Ok((WithSessionReply(res),))
+            })
+        }
+
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
+    }
+
+    async fn load(config: &Config, opened: Option<(String, bool)>) -> (Session, bool) {
+        let (value, rotated) = match opened {
+            Some(opened) => opened,
+            None => return (Session::new(None, Data::new()), false),
+        };
+        match config.store {
+            Some(ref store) => match store.load(&value).await {
+                Ok(Some(data)) => (Session::new(Some(value), data), rotated),
+                Ok(None) => (Session::new(None, Data::new()), false),
+                Err(err) => {
+                    tracing::error!("session store load error: {}", err);
+                    (Session::new(None, Data::new()), false)
+                }
+            },
+            None => match serde_json::from_str(&value) {
+                Ok(data) => (Session::new(None, data), rotated),
+                Err(err) => {
+                    tracing::debug!("session cookie invalid JSON: {}", err);
+                    (Session::new(None, Data::new()), false)
+                }
+            },
+        }
+    }
+
+    /// Save the session, returning the cookie to set, if any.
+    async fn save(config: &Config, session: &Session, rotated: bool) -> Option<Cookie> {
+        let (old_id, id, data, destroyed) = {
+            let mut state = session.inner.lock().unwrap();
+            if !state.changed && !state.destroyed && !rotated {
+                return None;
+            }
+            let old_id = state.id.clone();
+            if state.id.is_none() && config.store.is_some() && !state.destroyed {
+                state.id = Some(codec::new_id());
+            }
+            (old_id, state.id.clone(), state.data.clone(), state.destroyed)
+        };
+
+        let store = match config.store {
+            Some(ref store) => store,
+            None if destroyed => return Some(codec::removal(config)),
+            None => {
+                let value = serde_json::to_string(&data).expect("JSON map always serializes");
+                return Some(codec::seal(config, value));
+            }
+        };
+
+        // The session moved to a new ID, or ended.
+        if let Some(ref old_id) = old_id {
+            if destroyed || Some(old_id) != id.as_ref() {
+                if let Err(err) = store.destroy(old_id).await {
+                    tracing::error!("session store destroy error: {}", err);
+                }
+            }
+        }
+        if destroyed {
+            return Some(codec::removal(config));
+        }
+        let id = id.expect("session ID assigned above");
+        if let Err(err) = store.save(&id, &data).await {
+            tracing::error!("session store save error: {}", err);
+            return None;
+        }
+        Some(codec::seal(config, id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use futures_util::future::{self, poll_fn};
+    use http::header::{COOKIE, SET_COOKIE};
+    use http::Request;
+    use hyper::Body;
+
+    use super::{codec, encrypted, signed, Key, SameSite, Session, Sessions};
+    use crate::filter::{FilterBase, Internal, WrapSealed};
+    use crate::reject::Rejection;
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
+
+    /// Counts the visits of a session, as a handler would.
+    #[derive(Clone)]
+    struct Visits;
+
+    impl FilterBase for Visits {
+        type Extract = (Response,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(Response,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let session = route::with(|route| route.extensions().get::<Session>().cloned());
+            let session = session.expect("session set by the wrapper");
+            let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+            session.insert("visits", visits);
+            future::ok((Response::new(visits.to_string().into()),))
+        }
+    }
+
+    /// Visit through `sessions` with `cookie`, returning the visit count
+    /// and the session cookie set, if any.
+    async fn visit(sessions: &Sessions, cookie: Option<&str>) -> (String, Option<String>) {
+        let filter = sessions.wrap(Visits);
+        let mut req = Request::builder();
+        if let Some(cookie) = cookie {
+            req = req.header(COOKIE, cookie);
+        }
+        let route = Route::new(req.body(Body::empty()).unwrap(), None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        let result = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await;
+        let res = result.unwrap().0.into_response();
+        let set_cookie = res.headers().get(SET_COOKIE).map(|value| {
+            let value = value.to_str().unwrap();
+            value.split(';').next().unwrap().to_owned()
+        });
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        (String::from_utf8(body.to_vec()).unwrap(), set_cookie)
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let old = Key::generate();
+        let new = Key::generate();
+
+        for sessions in [signed(old.clone()), encrypted(old.clone())] {
+            let set_cookie = codec::seal(&sessions.config, r#"{"a":1}"#.to_owned());
+            assert!(set_cookie.to_header_value().is_some(), "{}", set_cookie);
+            let set_cookie = set_cookie.to_string();
+            let header = set_cookie.split(';').next().unwrap();
+            assert_eq!(
+                codec::open(&sessions.config, header),
+                Some((r#"{"a":1}"#.to_owned(), false))
+            );
+
+            let mut rotated = sessions.clone().rotate(Key::generate()).rotate(old.clone());
+            Arc::make_mut(&mut rotated.config).key = new.clone();
+            assert_eq!(
+                codec::open(&rotated.config, &format!("other=1; {}", header)),
+                Some((r#"{"a":1}"#.to_owned(), true))
+            );
+
+            let mut unrelated = sessions.clone();
+            Arc::make_mut(&mut unrelated.config).key = new.clone();
+            assert_eq!(codec::open(&unrelated.config, header), None);
+        }
+    }
+
+    #[test]
+    fn expired_cookies_ignored() {
+        let issued = SystemTime::now() - Duration::from_secs(7200);
+        let sessions = signed(Key::generate());
+        let set_cookie = codec::seal_at(&sessions.config, "{}".to_owned(), issued).to_string();
+        let header = set_cookie.split(';').next().unwrap();
+        assert!(codec::open(&sessions.config, header).is_some());
+
+        let sessions = sessions.max_age(Duration::from_secs(3600));
+        assert_eq!(codec::open(&sessions.config, header), None);
+        let later = issued + Duration::from_secs(3599);
+        assert!(codec::open_at(&sessions.config, header, later).is_some());
+    }
+
+    #[tokio::test]
+    async fn sessions_expire_through_wrapper() {
+        let sessions = signed(Key::generate()).max_age(Duration::from_secs(3600));
+
+        let (visits, cookie) = visit(&sessions, None).await;
+        assert_eq!(visits, "1");
+        let cookie = cookie.expect("new session saved");
+        let (visits, _) = visit(&sessions, Some(&cookie)).await;
+        assert_eq!(visits, "2");
+
+        // A copy of a cookie from two hours ago starts over.
+        let issued = SystemTime::now() - Duration::from_secs(7200);
+        let stale = codec::seal_at(&sessions.config, r#"{"visits":5}"#.to_owned(), issued);
+        let stale = stale.to_string();
+        let (visits, _) = visit(&sessions, stale.split(';').next()).await;
+        assert_eq!(visits, "1");
+    }
+
+    #[test]
+    fn removal_expires_cookie() {
+        let sessions = signed(Key::generate())
+            .cookie_name("sid")
+            .max_age(Duration::from_secs(3600));
+        let removal = codec::removal(&sessions.config).to_string();
+        assert!(removal.starts_with("sid=;"), "{}", removal);
+        assert!(removal.contains("Max-Age=0"), "{}", removal);
+        assert!(removal.contains("Expires=Thu, 01 Jan 1970"), "{}", removal);
+    }
+
+    #[test]
+    fn cookie_attributes_from_config() {
+        let sessions = encrypted(Key::generate())
+            .cookie_name("sid")
+            .path("/app")
+            .domain("example.com")
+            .max_age(Duration::from_secs(3600))
+            .same_site(SameSite::Strict);
+        let cookie = codec::seal(&sessions.config, "{}".to_owned()).to_string();
+        let (_, attributes) = cookie.split_once("; ").unwrap();
+        assert_eq!(
+            attributes,
+            "Path=/app; Domain=example.com; Max-Age=3600; Secure; HttpOnly; SameSite=Strict"
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! Tower interop
+//!
+//! [`warp::service()`](crate::service()) turns a filter into a tower
+//! `Service`. The filters here go the other way:
+//!
+//! - [`service`] mounts any tower `Service` as a filter, forwarding the
+//!   request to it.
+//! - [`Filter::layer`](crate::Filter::layer) wraps a filter in any tower
+//!   `Layer`, so middleware written for tower can be used in the middle of
+//!   a filter chain, around the filters that follow.
+//!
+//! # Example
+//!
+//! ```
+//! use std::convert::Infallible;
+//! use warp::hyper::{service::service_fn, Body, Request, Response};
+//! use warp::Filter;
+//!
+//! let svc = service_fn(|_req: Request<Body>| async {
+//!     Ok::<_, Infallible>(Response::new(Body::from("from tower")))
+//! });
+//! let route = warp::path("legacy").and(warp::tower::service(svc));
+//! ```
+
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::error::Error as StdError;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::future::poll_fn;
+use futures_util::{ready, stream, TryFuture};
+use http::Extensions;
+use hyper::body::HttpBody;
+use hyper::Body;
+use pin_project::pin_project;
+use tower_service::Service;
+
+use crate::filter::{Describe, Filter, FilterBase, Internal};
+use crate::reject::{self, IsReject, Rejection};
+use crate::reply::{Reply, Response};
+use crate::route::{self, Route};
+use crate::Request;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Forward requests to a tower `Service`, replying with its response.
+///
+/// The service receives the whole request, including the parts of the path
+/// already matched by previous filters. Since extensions can't be cloned,
+/// they stay with the filter chain instead, for the filters and wrappers
+/// around this one. Errors returned by the service reject with a
+/// [`ServiceError`], rendered as a `500 Internal Server Error`.
+pub fn service<S, B>(service: S) -> Forward<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + Sync + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    Forward {
+        service,
+        layered: false,
+    }
+}
+
+/// A filter forwarding requests to a tower `Service`.
+///
+/// Created with [`service`] or [`Filter::layer`](crate::Filter::layer).
+#[derive(Clone, Debug)]
+pub struct Forward<S> {
+    service: S,
+    layered: bool,
+}
+
+impl<S> Forward<S> {
+    pub(crate) fn layered<F, L>(filter: F, layer: L) -> Self
+    where
+        L: tower_layer::Layer<FilterService<F>, Service = S>,
+    {
+        Forward {
+            service: layer.layer(FilterService { filter }),
+            layered: true,
+        }
+    }
+}
+
+impl<S, B> FilterBase for Forward<S>
+where
+    S: Service<Request, Response = http::Response<B>> + Clone + Send + Sync + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Extract = (Response,);
+    type Error = Rejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Extract, Self::Error>> + Send>>;
+
+    fn filter(&self, _: Internal) -> Self::Future {
+        let mut service = self.service.clone();
+        let layered = self.layered;
+        Box::pin(async move {
+            let (body,) = crate::filters::body::body().filter(Internal).await?;
+            let req = route::with(|route| {
+                let mut req = http::Request::new(body);
+                *req.method_mut() = route.method().clone();
+                *req.uri_mut() = route.uri().clone();
+                *req.version_mut() = route.version();
+                *req.headers_mut() = route.headers().clone();
+                if layered {
+                    // The wrapped filter hands them back with its response.
+                    *req.extensions_mut() = std::mem::take(route.extensions_mut());
+                }
+                req.extensions_mut().insert(Resume {
+                    remote_addr: route.remote_addr(),
+                    path: route.full_path().to_owned(),
+                    index: route.matched_path_index(),
+                });
+                req
+            });
+
+            poll_fn(|cx| service.poll_ready(cx))
+                .await
+                .map_err(service_error)?;
+            let mut res = service.call(req).await.map_err(service_error)?;
+
+            if let Some(HandedBack(extensions)) = res.extensions_mut().remove::<HandedBack>() {
+                route::with(|route| *route.extensions_mut() = extensions);
+            }
+            // A filter wrapped by a layer rejected, pass it on.
+            if let Some(Rejected(rejection, body)) = res.extensions_mut().remove::<Rejected>() {
+                // Let `or` siblings read a body the filter didn't.
+                if let Some(body) = body {
+                    route::with(|route| route.set_body(body));
+                }
+                return Err(rejection);
+            }
+            Ok((into_response(res),))
+        })
+    }
+
+    fn describe(&self, _: Internal) -> Describe {
+        Describe::Empty
+    }
+}
+
+fn service_error<E: Into<BoxError>>(err: E) -> Rejection {
+    let err = err.into();
+    tracing::error!("tower service error: {}", err);
+    reject::known(ServiceError { source: err })
+}
+
+fn into_response<B>(res: http::Response<B>) -> Response
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+{
+    let (parts, body) = res.into_parts();
+    let mut body = Box::pin(body);
+    let chunks = stream::poll_fn(move |cx| {
+        let chunk = ready!(body.as_mut().poll_data(cx));
+        Poll::Ready(chunk.map(|chunk| chunk.map(Into::into).map_err(Into::into)))
+    });
+    Response::from_parts(parts, Body::wrap_stream::<_, Bytes, BoxError>(chunks))
+}
+
+/// Where the outer filter chain was when forwarding into a layer.
+#[derive(Clone, Debug)]
+struct Resume {
+    remote_addr: Option<SocketAddr>,
+    path: String,
+    index: usize,
+}
+
+/// The request extensions, handed back by a filter wrapped by a layer
+/// through the response extensions, so the outer filter chain keeps them.
+struct HandedBack(Extensions);
+
+/// A rejection from a filter wrapped by a layer, smuggled out through
+/// the response extensions along with the body, if the filter didn't
+/// read it.
+struct Rejected(Rejection, Option<Body>);
+
+/// The tower `Service` a [`Filter::layer`](crate::Filter::layer) wraps.
+///
+/// It runs the filter on requests coming out of the layer, continuing where
+/// the outer filter chain left off, unless the layer rewrote the path.
+///
+/// If the filter rejects, the service still responds, with the response the
+/// rejection would render as by default. The rejection itself is passed
+/// on to the outer filter chain, so `or` and `recover` keep working, but
+/// any changes the layer makes to that response are discarded.
+///
+/// Either way, the request extensions are handed back to the outer filter
+/// chain in the response extensions, unless the layer drops them.
+#[derive(Clone, Debug)]
+pub struct FilterService<F> {
+    filter: F,
+}
+
+impl<F> Service<Request> for FilterService<F>
+where
+    F: Filter,
+    F::Extract: Reply,
+    F::Error: IsReject + Into<Rejection>,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = FilterServiceFuture<F::Future>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let resume = req.extensions_mut().remove::<Resume>();
+        let remote_addr = resume.as_ref().and_then(|resume| resume.remote_addr);
+        let route = Route::new(req, remote_addr);
+        if let Some(resume) = resume {
+            let mut route = route.borrow_mut();
+            if route.full_path() == resume.path {
+                route.reset_matched_path_index(resume.index);
+            }
+        }
+        let future = route::set(&route, || self.filter.filter(Internal));
+        FilterServiceFuture { future, route }
+    }
+}
+
+/// The response future of a [`FilterService`].
+#[allow(missing_debug_implementations)]
+#[pin_project]
+pub struct FilterServiceFuture<F> {
+    #[pin]
+    future: F,
+    route: RefCell<Route>,
+}
+
+impl<F> Future for FilterServiceFuture<F>
+where
+    F: TryFuture,
+    F::Ok: Reply,
+    F::Error: IsReject + Into<Rejection>,
+{
+    type Output = Result<Response, Infallible>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let pin = self.project();
+        let fut = pin.future;
+        let result = ready!(route::set(pin.route, || fut.try_poll(cx)));
+        let mut route = pin.route.borrow_mut();
+        let mut res = match result {
+            Ok(ok) => ok.into_response(),
+            Err(err) => {
+                let mut res = err.into_response();
+                let body = route.take_body();
+                res.extensions_mut().insert(Rejected(err.into(), body));
+                res
+            }
+        };
+        let extensions = std::mem::take(route.extensions_mut());
+        res.extensions_mut().insert(HandedBack(extensions));
+        Poll::Ready(Ok(res))
+    }
+}
+
+/// A tower service forwarded to by [`service`] returned an error.
+pub struct ServiceError {
+    source: BoxError,
+}
+
+impl fmt::Debug for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ServiceError").field(&self.source).finish()
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Service error: {}", self.source)
+    }
+}
+
+impl StdError for ServiceError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_util::future::{self, poll_fn};
+    use http::header::{HeaderValue, SERVER};
+    use http::{Method, StatusCode};
+    use hyper::service::service_fn;
+    use hyper::Body;
+    use tower_service::Service;
+
+    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::reject::{self, IsReject, Rejection};
+    use crate::reply::{Reply, Response};
+    use crate::route::{self, Route};
+    use crate::Request;
+
+    /// Run a filter returning responses on a request, as the server would.
+    async fn forward<F>(filter: &F, req: Request) -> Result<Response, Rejection>
+    where
+        F: FilterBase<Extract = (Response,), Error = Rejection>,
+        F::Future: Unpin,
+    {
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        let (res,) = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await?;
+        Ok(res)
+    }
+
+    fn request(method: Method, path: &str, body: &'static str) -> Request {
+        let mut req = Request::new(Body::from(body));
+        *req.method_mut() = method;
+        *req.uri_mut() = path.parse().unwrap();
+        req
+    }
+
+    async fn body_string(res: Response) -> String {
+        let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    struct Text(&'static str);
+
+    impl Reply for Text {
+        fn into_response(self) -> Response {
+            Response::new(Body::from(self.0))
+        }
+    }
+
+    /// Replies with its text on `GET`, and rejects other methods.
+    #[derive(Clone)]
+    struct GetOnly(&'static str);
+
+    impl FilterBase for GetOnly {
+        type Extract = (Text,);
+        type Error = Rejection;
+        type Future = future::Ready<Result<(Text,), Rejection>>;
+
+        fn filter(&self, _: Internal) -> Self::Future {
+            let method = route::with(|route| route.method().clone());
+            if method == Method::GET {
+                future::ok((Text(self.0),))
+            } else {
+                future::err(reject::method_not_allowed(Method::GET))
+            }
+        }
+    }
+
+    /// A tower layer setting `Server` on responses.
+    struct ServerName;
+
+    impl<S> tower_layer::Layer<S> for ServerName {
+        type Service = WithServerName<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            WithServerName(inner)
+        }
+    }
+
+    #[derive(Clone)]
+    struct WithServerName<S>(S);
+
+    impl<S> Service<Request> for WithServerName<S>
+    where
+        S: Service<Request, Response = Response>,
+        S::Future: Send + 'static,
+    {
+        type Response = Response;
+        type Error = S::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.0.poll_ready(cx)
+        }
+
+        fn call(&mut self, req: Request) -> Self::Future {
+            let future = self.0.call(req);
+            Box::pin(async move {
+                let mut res = future.await?;
+                res.headers_mut()
+                    .insert(SERVER, HeaderValue::from_static("tower"));
+                Ok(res)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn service_forwarded_whole_request() {
+        let svc = service_fn(|req: Request| async move {
+            let head = format!("{} {} ", req.method(), req.uri().path());
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            let mut res = http::Response::new(Body::from(head + &String::from_utf8_lossy(&body)));
+            *res.status_mut() = StatusCode::CREATED;
+            Ok::<_, hyper::Error>(res)
+        });
+        let filter = super::service(svc);
+
+        let res = forward(&filter, request(Method::PUT, "/a/b", "hi"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(body_string(res).await, "PUT /a/b hi");
+    }
+
+    #[tokio::test]
+    async fn service_errors_rejected() {
+        let svc = service_fn(|_: Request| async {
+            Err::<http::Response<Body>, _>(std::io::Error::new(std::io::ErrorKind::Other, "down"))
+        });
+        let err = forward(&super::service(svc), request(Method::GET, "/", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn layered_filter_replies() {
+        let filter = GetOnly("layered").layer(ServerName);
+
+        let res = forward(&filter, request(Method::GET, "/", ""))
+            .await
+            .unwrap();
+        assert_eq!(res.headers()[SERVER], "tower");
+        assert_eq!(body_string(res).await, "layered");
+    }
+
+    #[tokio::test]
+    async fn layered_filter_rejection_passed_on() {
+        let filter = GetOnly("layered").layer(ServerName);
+
+        let err = forward(&filter, request(Method::POST, "/", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(err.allowed_methods(), [Method::GET]);
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Marker;
+
+    /// Whether an extension set before running `filter` is still on the
+    /// route afterwards.
+    async fn keeps_extensions<F>(filter: &F, method: Method) -> bool
+    where
+        F: FilterBase,
+        F::Future: Unpin,
+    {
+        let mut req = request(method, "/", "");
+        req.extensions_mut().insert(Marker);
+        let route = Route::new(req, None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        let _ = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await;
+        let kept = route.borrow().extensions().get::<Marker>() == Some(&Marker);
+        kept
+    }
+
+    #[tokio::test]
+    async fn extensions_kept_by_outer_chain() {
+        let svc = service_fn(|_: Request| async { Ok::<_, hyper::Error>(Response::default()) });
+        assert!(keeps_extensions(&super::service(svc), Method::GET).await);
+
+        let layered = GetOnly("layered").layer(ServerName);
+        assert!(keeps_extensions(&layered, Method::GET).await);
+        assert!(keeps_extensions(&layered, Method::POST).await);
+    }
+
+    #[tokio::test]
+    async fn layered_filter_rejection_restores_body() {
+        let filter = GetOnly("layered").layer(ServerName);
+
+        let route = Route::new(request(Method::POST, "/", "unread"), None);
+        let mut future = route::set(&route, || filter.filter(Internal));
+        let res = poll_fn(|cx| route::set(&route, || Pin::new(&mut future).poll(cx))).await;
+        assert!(res.is_err());
+
+        let body = route.borrow_mut().take_body().expect("body restored");
+        assert_eq!(hyper::body::to_bytes(body).await.unwrap(), "unread");
+    }
+}
@@ -10,6 +10,7 @@
 //! [`Spans`]: https://docs.rs/tracing/latest/tracing/#spans
 use self::internal::WithTrace;
 use crate::filter::{Filter, WrapSealed};
+use crate::filters::request_id::RequestId;
 use crate::reject::IsReject;
 use crate::reply::Reply;
 use crate::route::Route;
@@ -18,7 +19,36 @@ use std::net::SocketAddr;
 use tracing::Span;
 
 pub fn request() -> Trace<impl Fn(Info<'_>) -> Span + Clone> {
-    trace(|info: Info<'_>| loop {})
+    use tracing::field::{display, Empty};
+
+    trace(|info: Info<'_>| {
+        let span = tracing::info_span!(
+            "request",
+            remote.addr = Empty,
+            method = %info.method(),
+            path = %info.path(),
+            version = ?info.route.version(),
+            referer = Empty,
+            request_id = Empty,
+        );
+
+        // Record optional fields.
+        if let Some(remote_addr) = info.remote_addr() {
+            span.record("remote.addr", &display(remote_addr));
+        }
+
+        if let Some(referer) = info.referer() {
+            span.record("referer", &display(referer));
+        }
+
+        if let Some(id) = info.request_id() {
+            span.record("request_id", display(id));
+        }
+
+        tracing::debug!(parent: &span, "received request");
+
+        span
+    })
 }
 
 pub fn trace<F>(func: F) -> Trace<F>
@@ -85,10 +115,16 @@ impl<'a> Info<'a> {
     pub fn request_headers(&self) -> &http::HeaderMap {
         loop {}
     }
+
+    /// View the ID of the request, if tagged with
+    /// [`request_id`](crate::request_id()).
+    pub fn request_id(&self) -> Option<&RequestId> {
+        self.route.extensions().get::<RequestId>()
+    }
 }
 mod internal {
     use super::{Info, Trace};
-    use crate::filter::{Filter, FilterBase, Internal};
+    use crate::filter::{Describe, Filter, FilterBase, Internal};
     use crate::reject::IsReject;
     use crate::reply::Reply;
     use crate::reply::Response;
@@ -134,5 +170,8 @@ mod internal {
         fn filter(&self, _: Internal) -> Self::Future {
             loop {}
         }
+        fn describe(&self, _: Internal) -> Describe {
+            self.filter.describe(Internal)
+        }
     }
 }
@@ -3,7 +3,7 @@ pub struct Product<H, T: HList>(pub(crate) H, pub(crate) T);
 pub type One<T> = (T,);
 #[inline]
 pub(crate) fn one<T>(val: T) -> One<T> {
-    loop {}
+    (val,)
 }
 #[derive(Debug)]
 pub enum Either<T, U> {
@@ -24,7 +24,7 @@ pub trait Tuple: Sized {
         T: Tuple,
         Self::HList: Combine<T::HList>,
     {
-        loop {}
+        self.hlist().combine(other.hlist()).flatten()
     }
 }
 pub type CombinedTuples<T, U> = <<<T as Tuple>::HList as Combine<
@@ -42,7 +42,7 @@ impl<T: HList> Combine<T> for () {
     type Output = T;
     #[inline]
     fn combine(self, other: T) -> Self::Output {
-        loop {}
+        other
     }
 }
 impl<H, T: HList, U: HList> Combine<U> for Product<H, T>
@@ -53,7 +53,7 @@ where
     type Output = Product<H, <T as Combine<U>>::Output>;
     #[inline]
     fn combine(self, other: U) -> Self::Output {
-        loop {}
+        Product(self.0, self.1.combine(other))
     }
 }
 impl HList for () {
@@ -73,7 +73,7 @@ where
     type Output = R;
     #[inline]
     fn call(&self, _args: ()) -> Self::Output {
-        loop {}
+        (*self)()
     }
 }
 impl<F, R> Func<crate::Rejection> for F
@@ -83,7 +83,7 @@ where
     type Output = R;
     #[inline]
     fn call(&self, arg: crate::Rejection) -> Self::Output {
-        loop {}
+        (*self)(arg)
     }
 }
 macro_rules! product {
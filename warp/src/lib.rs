@@ -91,6 +91,7 @@ mod error;
 mod filter;
 pub mod filters;
 mod generic;
+pub mod problem;
 pub mod redirect;
 pub mod reject;
 pub mod reply;
@@ -113,6 +114,9 @@ pub use self::filters::compression;
 #[cfg(feature = "multipart")]
 #[doc(hidden)]
 pub use self::filters::multipart;
+#[cfg(feature = "session")]
+#[doc(hidden)]
+pub use self::filters::session;
 #[cfg(feature = "websocket")]
 #[doc(hidden)]
 pub use self::filters::ws;
@@ -121,6 +125,7 @@ pub use self::filters::{
     addr,
     // any() function
     any::any,
+    auth,
     body,
     cookie,
     // cookie() function
@@ -128,23 +133,33 @@ pub use self::filters::{
     cors,
     // cors() function
     cors::cors,
+    csrf,
     ext,
     fs,
     header,
     // header() function
     header::header,
     host,
+    limit,
     log,
     // log() function
     log::log,
     method::{delete, get, head, method, options, patch, post, put},
+    openapi,
     path,
     // path() function and macro
     path::path,
     query,
     // query() function
     query::query,
+    request_id,
+    // request_id() function
+    request_id::request_id,
+    router,
+    // router() function
+    router::router,
     sse,
+    tower,
     trace,
     // trace() function
     trace::trace,
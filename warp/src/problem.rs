@@ -0,0 +1,263 @@
+//! Problem Details for HTTP APIs
+//!
+//! Renders [rejections](../reject) as [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807)
+//! `application/problem+json` responses, instead of the plain text the
+//! default rejection handling produces.
+//!
+//! Rejections built into warp are mapped automatically. Custom rejections
+//! can describe themselves by implementing [`IntoProblem`], and registering
+//! themselves with a [`handler`].
+//!
+//! # Example
+//!
+//! ```
+//! use warp::http::StatusCode;
+//! use warp::problem::{self, IntoProblem, Problem};
+//! use warp::{reject, Filter};
+//!
+//! #[derive(Debug)]
+//! struct OutOfCredit {
+//!     balance: u32,
+//! }
+//!
+//! impl reject::Reject for OutOfCredit {}
+//!
+//! impl IntoProblem for OutOfCredit {
+//!     fn problem(&self) -> Problem {
+//!         Problem::new(StatusCode::FORBIDDEN)
+//!             .with_type("https://example.com/probs/out-of-credit")
+//!             .with_title("You do not have enough credit.")
+//!             .with_field("balance", self.balance)
+//!     }
+//! }
+//!
+//! let route = warp::path("buy")
+//!     .and_then(|| async { Err::<String, _>(reject::custom(OutOfCredit { balance: 30 })) })
+//!     .recover(problem::handler().custom::<OutOfCredit>());
+//!
+//! // Or, with only the built-in rejections:
+//! let simple = warp::path("hello").map(|| "hi").recover(problem::recover);
+//! ```
+
+use std::convert::Infallible;
+use std::fmt;
+
+use futures_util::future;
+use http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use http::StatusCode;
+use hyper::Body;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::generic::Func;
+use crate::reject::{IsReject, Reject, Rejection};
+use crate::reply::{Reply, Response};
+
+/// A problem details object.
+#[derive(Clone, Debug)]
+pub struct Problem {
+    status: StatusCode,
+    fields: Map<String, Value>,
+    headers: HeaderMap,
+}
+
+impl Problem {
+    /// Create a problem with the given status, and the default `type` of
+    /// `about:blank` titled after the status.
+    pub fn new(status: StatusCode) -> Self {
+        let mut fields = Map::new();
+        fields.insert("type".to_owned(), Value::from("about:blank"));
+        fields.insert(
+            "title".to_owned(),
+            Value::from(status.canonical_reason().unwrap_or("Unknown")),
+        );
+        fields.insert("status".to_owned(), Value::from(status.as_u16()));
+        Problem {
+            status,
+            fields,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Set the URI reference identifying the problem type.
+    pub fn with_type(self, uri: impl Into<String>) -> Self {
+        self.with_field("type", uri.into())
+    }
+
+    /// Set the short, human-readable summary of the problem type.
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        self.with_field("title", title.into())
+    }
+
+    /// Set the explanation specific to this occurrence of the problem.
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        self.with_field("detail", detail.into())
+    }
+
+    /// Set the URI reference identifying this occurrence of the problem.
+    pub fn with_instance(self, uri: impl Into<String>) -> Self {
+        self.with_field("instance", uri.into())
+    }
+
+    /// Add an extension member.
+    ///
+    /// Values that fail to serialize are skipped.
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        let name = name.into();
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.fields.insert(name, value);
+            }
+            Err(err) => {
+                tracing::error!("problem field {:?} serialize error: {}", name, err);
+            }
+        }
+        self
+    }
+
+    /// The status code of this problem.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl Reply for Problem {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_vec(&self.fields).expect("JSON map always serializes");
+        let mut res = Response::new(Body::from(body));
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers;
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        res
+    }
+}
+
+/// Custom rejections that can describe themselves as a [`Problem`].
+pub trait IntoProblem: Reject {
+    /// Describe this rejection.
+    fn problem(&self) -> Problem;
+}
+
+/// Render a rejection with only the built-in rejections mapped.
+///
+/// Use with [`Filter::recover`](crate::Filter::recover).
+pub async fn recover(err: Rejection) -> Result<Problem, Infallible> {
+    Ok(render(&[], &err))
+}
+
+/// Create a recovery handler that can also render custom rejections.
+pub fn handler() -> Handler {
+    Handler { custom: Vec::new() }
+}
+
+/// A recovery handler rendering rejections as problems.
+///
+/// Pass it to [`Filter::recover`](crate::Filter::recover).
+#[derive(Clone)]
+pub struct Handler {
+    custom: Vec<fn(&Rejection) -> Option<Problem>>,
+}
+
+impl fmt::Debug for Handler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handler")
+            .field("custom", &self.custom.len())
+            .finish()
+    }
+}
+
+impl Handler {
+    /// Render rejections of type `T` with its [`IntoProblem`] implementation.
+    ///
+    /// Custom rejections are checked in the order they were added, before
+    /// the built-in ones.
+    pub fn custom<T: IntoProblem>(mut self) -> Self {
+        self.custom.push(find_custom::<T>);
+        self
+    }
+}
+
+impl Func<Rejection> for Handler {
+    type Output = future::Ready<Result<Problem, Infallible>>;
+
+    fn call(&self, err: Rejection) -> Self::Output {
+        future::ok(render(&self.custom, &err))
+    }
+}
+
+fn find_custom<T: IntoProblem>(err: &Rejection) -> Option<Problem> {
+    err.find::<T>().map(IntoProblem::problem)
+}
+
+fn render(custom: &[fn(&Rejection) -> Option<Problem>], err: &Rejection) -> Problem {
+    if let Some(problem) = custom.iter().find_map(|find| find(err)) {
+        return problem;
+    }
+    if err.is_not_found() {
+        return Problem::new(StatusCode::NOT_FOUND);
+    }
+
+    let mut problem = Problem::new(err.status());
+    err.insert_headers(&mut problem.headers);
+    match err.known_detail() {
+        Some(detail) => problem.with_detail(detail),
+        None => {
+            tracing::debug!("unhandled rejection rendered as problem: {:?}", err);
+            problem
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::header::{ALLOW, RETRY_AFTER, WWW_AUTHENTICATE};
+    use http::Method;
+
+    use super::*;
+    use crate::reject::{self, CombineRejection};
+
+    fn respond(err: Rejection) -> Response {
+        render(&[], &err).into_response()
+    }
+
+    #[test]
+    fn keeps_allow() {
+        let err = reject::method_not_allowed(Method::GET)
+            .combine(reject::method_not_allowed(Method::POST));
+        let res = respond(err);
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers()[ALLOW], "GET, POST");
+        assert_eq!(res.headers()[CONTENT_TYPE], "application/problem+json");
+    }
+
+    #[test]
+    fn detail_from_preferred_cause() {
+        let err = reject::payload_too_large().combine(reject::method_not_allowed(Method::GET));
+        let problem = render(&[], &err);
+        assert_eq!(problem.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(problem.fields["detail"], "The request payload is too large");
+    }
+
+    #[test]
+    fn keeps_www_authenticate() {
+        let res = respond(reject::unauthorized("Basic", "api", None));
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(res.headers()[WWW_AUTHENTICATE], "Basic realm=\"api\", charset=\"UTF-8\"");
+    }
+
+    #[test]
+    fn keeps_rate_limit_headers() {
+        let err = reject::too_many_requests(10, Duration::from_secs(3), Duration::from_secs(5));
+        let res = respond(err);
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()[RETRY_AFTER], "3");
+        assert_eq!(res.headers()["ratelimit-limit"], "10");
+        assert_eq!(res.headers()["ratelimit-remaining"], "0");
+        assert_eq!(res.headers()["ratelimit-reset"], "5");
+    }
+}
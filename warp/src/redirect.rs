@@ -102,7 +102,9 @@ pub fn permanent(uri: impl AsLocation) -> impl Reply {
 }
 mod sealed {
     
+    use std::convert::TryFrom;
     use http::{header::HeaderValue, Uri};
+    use crate::path::TypedPath;
     
     
     
@@ -117,4 +119,10 @@ mod sealed {
             loop {}
         }
     }
+    impl<'a, T: TypedPath> AsLocation for &'a T {}
+    impl<'a, T: TypedPath> Sealed for &'a T {
+        fn header_value(self) -> HeaderValue {
+            HeaderValue::try_from(self.url()).expect("percent-encoded path is a valid header value")
+        }
+    }
 }
@@ -59,7 +59,10 @@ use std::any::Any;
 use std::convert::Infallible;
 use std::error::Error as StdError;
 use std::fmt;
-use http::{self, StatusCode};
+use std::time::Duration;
+use http::header::{HeaderMap, HeaderValue, ALLOW, CONTENT_TYPE, RETRY_AFTER, WWW_AUTHENTICATE};
+use http::{self, Method, StatusCode};
+use hyper::Body;
 pub(crate) use self::sealed::{CombineRejection, IsReject};
 
 #[inline]
@@ -69,7 +72,9 @@ pub fn reject() -> Rejection {
 
 #[inline]
 pub fn not_found() -> Rejection {
-    loop {}
+    Rejection {
+        reason: Reason::NotFound,
+    }
 }
 #[inline]
 pub(crate) fn invalid_query() -> Rejection {
@@ -88,8 +93,35 @@ pub(crate) fn missing_cookie(name: &'static str) -> Rejection {
     loop {}
 }
 #[inline]
-pub(crate) fn method_not_allowed() -> Rejection {
-    loop {}
+pub(crate) fn method_not_allowed(allowed: Method) -> Rejection {
+    known(MethodNotAllowed { allowed })
+}
+#[inline]
+pub(crate) fn timed_out(duration: Duration, from_deadline: bool) -> Rejection {
+    known(TimedOut {
+        duration,
+        from_deadline,
+    })
+}
+#[inline]
+pub(crate) fn overloaded(retry_after: Duration) -> Rejection {
+    known(Overloaded { retry_after })
+}
+#[inline]
+pub(crate) fn too_many_requests(limit: u32, retry_after: Duration, reset: Duration) -> Rejection {
+    known(TooManyRequests {
+        limit,
+        retry_after,
+        reset,
+    })
+}
+#[inline]
+pub(crate) fn unauthorized(
+    scheme: &'static str,
+    realm: &'static str,
+    error: Option<(&'static str, &'static str)>,
+) -> Rejection {
+    known(Unauthorized::new(scheme, realm, error))
 }
 #[inline]
 pub(crate) fn length_required() -> Rejection {
@@ -97,11 +129,11 @@ pub(crate) fn length_required() -> Rejection {
 }
 #[inline]
 pub(crate) fn payload_too_large() -> Rejection {
-    loop {}
+    known(PayloadTooLarge { _p: () })
 }
 #[inline]
-pub(crate) fn unsupported_media_type() -> Rejection {
-    loop {}
+pub(crate) fn unsupported_media_type(accepted: Vec<String>) -> Rejection {
+    known(UnsupportedMediaType { accepted })
 }
 
 
@@ -147,12 +179,18 @@ where
     T: fmt::Debug + Send + Sync + 'static,
 {
     fn as_any(&self) -> &dyn Any {
-        loop {}
+        self
+    }
+}
+impl dyn Cause {
+    fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
     }
 }
-impl dyn Cause {}
 pub(crate) fn known<T: Into<Known>>(err: T) -> Rejection {
-    loop {}
+    Rejection {
+        reason: Reason::Other(Box::new(Rejections::Known(err.into()))),
+    }
 }
 
 
@@ -192,6 +230,20 @@ enum_known! {
     ::cors::CorsForbidden), #[cfg(feature = "websocket")] MissingConnectionUpgrade(crate
     ::ws::MissingConnectionUpgrade), MissingExtension(crate ::ext::MissingExtension),
     BodyConsumedMultipleTimes(crate ::body::BodyConsumedMultipleTimes),
+    Timeout(TimedOut),
+    Overloaded(Overloaded),
+    TooManyRequests(TooManyRequests),
+    ServiceError(crate ::tower::ServiceError),
+    CsrfForbidden(crate ::csrf::CsrfForbidden),
+    Unauthorized(Unauthorized),
+    #[cfg(feature = "jwt")]
+    InvalidJwt(crate ::auth::jwt::InvalidJwt),
+    #[cfg(any(
+        feature = "compression-brotli",
+        feature = "compression-gzip",
+        feature = "compression-zstd"
+    ))]
+    UnsupportedEncoding(crate ::filters::compression::UnsupportedEncoding),
 }
 impl Rejection {
     
@@ -214,7 +266,10 @@ impl Rejection {
     
     
     pub fn find<T: 'static>(&self) -> Option<&T> {
-        loop {}
+        if let Reason::Other(ref rejections) = self.reason {
+            return rejections.find();
+        }
+        None
     }
     
     
@@ -226,7 +281,22 @@ impl Rejection {
     
     
     pub fn is_not_found(&self) -> bool {
-        loop {}
+        matches!(self.reason, Reason::NotFound)
+    }
+
+    /// Returns the methods that would have been allowed, collected from
+    /// every `405 Method Not Allowed` this rejection was combined from.
+    ///
+    /// These are the methods listed in the `Allow` header of the default
+    /// `405` response. Since method filters can only tell which method
+    /// they expect, placing path filters *before* method filters keeps
+    /// routes that don't match the path out of this list.
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        let mut methods = Vec::new();
+        if let Reason::Other(ref rejections) = self.reason {
+            rejections.allowed_methods(&mut methods);
+        }
+        methods
     }
 }
 impl<T: Reject> From<T> for Rejection {
@@ -251,10 +321,93 @@ impl IsReject for Infallible {
 }
 impl IsReject for Rejection {
     fn status(&self) -> StatusCode {
-        loop {}
+        match self.reason {
+            Reason::NotFound => StatusCode::NOT_FOUND,
+            Reason::Other(ref other) => other.status(),
+        }
     }
     fn into_response(&self) -> crate::reply::Response {
-        loop {}
+        match self.reason {
+            Reason::NotFound => {
+                let mut res = http::Response::default();
+                *res.status_mut() = StatusCode::NOT_FOUND;
+                res
+            }
+            Reason::Other(ref other) => {
+                let mut res = other.into_response();
+                self.insert_headers(res.headers_mut());
+                res
+            }
+        }
+    }
+}
+
+impl Rejection {
+    /// The message of the built-in rejection responsible for this
+    /// rejection's status, if it is one.
+    pub(crate) fn known_detail(&self) -> Option<String> {
+        match self.reason {
+            Reason::Other(ref other) => match *other.preferred() {
+                Rejections::Known(ref known) => Some(known.to_string()),
+                Rejections::Custom(_) | Rejections::Combined(..) => None,
+            },
+            Reason::NotFound => None,
+        }
+    }
+
+    /// Add the headers the default response for this rejection carries,
+    /// such as `Allow` on a `405` or `WWW-Authenticate` on a `401`.
+    pub(crate) fn insert_headers(&self, headers: &mut HeaderMap) {
+        let other = match self.reason {
+            Reason::NotFound => return,
+            Reason::Other(ref other) => other,
+        };
+        if other.status() == StatusCode::METHOD_NOT_ALLOWED {
+            insert_allow(headers, &self.allowed_methods());
+        }
+        match *other.preferred() {
+            Rejections::Known(Known::Overloaded(ref e)) => {
+                e.insert_headers(headers);
+            }
+            Rejections::Known(Known::TooManyRequests(ref e)) => {
+                e.insert_headers(headers);
+            }
+            Rejections::Known(Known::Unauthorized(ref e)) => {
+                if let Ok(challenge) = HeaderValue::from_str(&e.challenge()) {
+                    headers.insert(WWW_AUTHENTICATE, challenge);
+                }
+            }
+            #[cfg(feature = "jwt")]
+            Rejections::Known(Known::InvalidJwt(ref e)) => {
+                if let Ok(challenge) = HeaderValue::from_str(&e.challenge()) {
+                    headers.insert(WWW_AUTHENTICATE, challenge);
+                }
+            }
+            #[cfg(any(
+                feature = "compression-brotli",
+                feature = "compression-gzip",
+                feature = "compression-zstd"
+            ))]
+            Rejections::Known(Known::UnsupportedEncoding(ref e)) => {
+                if let Ok(accepted) = HeaderValue::from_str(&e.accepted().join(", ")) {
+                    headers.insert(http::header::ACCEPT_ENCODING, accepted);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Set the `Allow` header to the given methods, in order and without duplicates.
+pub(crate) fn insert_allow(headers: &mut HeaderMap, methods: &[Method]) {
+    let mut allow: Vec<&str> = Vec::new();
+    for method in methods {
+        if !allow.contains(&method.as_str()) {
+            allow.push(method.as_str());
+        }
+    }
+    if let Ok(value) = HeaderValue::from_str(&allow.join(", ")) {
+        headers.insert(ALLOW, value);
     }
 }
 impl fmt::Debug for Rejection {
@@ -267,12 +420,311 @@ impl fmt::Debug for Reason {
         loop {}
     }
 }
-impl Rejections {}
+impl Rejections {
+    fn status(&self) -> StatusCode {
+        match *self {
+            Rejections::Known(ref k) => match *k {
+                Known::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+                Known::InvalidHeader(_)
+                | Known::MissingHeader(_)
+                | Known::MissingCookie(_)
+                | Known::InvalidQuery(_)
+                | Known::BodyReadError(_)
+                | Known::BodyDeserializeError(_) => StatusCode::BAD_REQUEST,
+                #[cfg(feature = "websocket")]
+                Known::MissingConnectionUpgrade(_) => StatusCode::BAD_REQUEST,
+                Known::LengthRequired(_) => StatusCode::LENGTH_REQUIRED,
+                Known::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                Known::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                #[cfg(any(
+                    feature = "compression-brotli",
+                    feature = "compression-gzip",
+                    feature = "compression-zstd"
+                ))]
+                Known::UnsupportedEncoding(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Known::FilePermissionError(_)
+                | Known::CorsForbidden(_)
+                | Known::CsrfForbidden(_) => StatusCode::FORBIDDEN,
+                Known::FileOpenError(_)
+                | Known::MissingExtension(_)
+                | Known::BodyConsumedMultipleTimes(_)
+                | Known::ServiceError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                Known::Timeout(ref t) => t.status(),
+                Known::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+                Known::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+                Known::Unauthorized(ref e) => e.status(),
+                #[cfg(feature = "jwt")]
+                Known::InvalidJwt(_) => StatusCode::UNAUTHORIZED,
+            },
+            Rejections::Custom(..) => StatusCode::INTERNAL_SERVER_ERROR,
+            Rejections::Combined(..) => self.preferred().status(),
+        }
+    }
+
+    fn into_response(&self) -> crate::reply::Response {
+        match *self {
+            Rejections::Known(ref e) => {
+                let mut res = http::Response::new(Body::from(e.to_string()));
+                *res.status_mut() = self.status();
+                res.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                res
+            }
+            Rejections::Custom(ref e) => {
+                tracing::error!(
+                    "unhandled custom rejection, returning 500 response: {:?}",
+                    e
+                );
+                let body = format!("Unhandled rejection: {:?}", e);
+                let mut res = http::Response::new(Body::from(body));
+                *res.status_mut() = self.status();
+                res.headers_mut().insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                res
+            }
+            Rejections::Combined(..) => self.preferred().into_response(),
+        }
+    }
+
+    fn find<T: 'static>(&self) -> Option<&T> {
+        match *self {
+            Rejections::Known(ref e) => e.inner_as_any().downcast_ref(),
+            Rejections::Custom(ref e) => e.downcast_ref(),
+            Rejections::Combined(ref a, ref b) => a.find().or_else(|| b.find()),
+        }
+    }
+
+    fn preferred(&self) -> &Rejections {
+        match self {
+            Rejections::Known(_) | Rejections::Custom(_) => self,
+            Rejections::Combined(a, b) => {
+                let a = a.preferred();
+                let b = b.preferred();
+                // Compare status codes, with this priority:
+                // - NOT_FOUND is lowest
+                // - METHOD_NOT_ALLOWED is second
+                // - if one status code is greater than the other
+                // - otherwise, prefer A...
+                match (a.status(), b.status()) {
+                    (_, StatusCode::NOT_FOUND) => a,
+                    (StatusCode::NOT_FOUND, _) => b,
+                    (_, StatusCode::METHOD_NOT_ALLOWED) => a,
+                    (StatusCode::METHOD_NOT_ALLOWED, _) => b,
+                    (sa, sb) if sa < sb => b,
+                    _ => a,
+                }
+            }
+        }
+    }
+
+    fn allowed_methods(&self, methods: &mut Vec<Method>) {
+        match *self {
+            Rejections::Known(Known::MethodNotAllowed(ref e)) => methods.push(e.allowed.clone()),
+            Rejections::Known(_) | Rejections::Custom(_) => (),
+            Rejections::Combined(ref a, ref b) => {
+                a.allowed_methods(methods);
+                b.allowed_methods(methods);
+            }
+        }
+    }
+}
 unit_error! {
     #[doc = " Invalid query"] pub InvalidQuery : "Invalid query string"
 }
-unit_error! {
-    #[doc = " HTTP method not allowed"] pub MethodNotAllowed : "HTTP method not allowed"
+/// HTTP method not allowed
+#[derive(Debug)]
+pub struct MethodNotAllowed {
+    allowed: Method,
+}
+impl MethodNotAllowed {
+    /// The method the rejecting filter would have allowed.
+    pub fn allowed(&self) -> &Method {
+        &self.allowed
+    }
+}
+impl fmt::Display for MethodNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HTTP method not allowed")
+    }
+}
+impl StdError for MethodNotAllowed {}
+
+/// A filter did not finish within its [`timeout`](crate::Filter::timeout)
+#[derive(Debug)]
+pub struct TimedOut {
+    duration: Duration,
+    from_deadline: bool,
+}
+impl TimedOut {
+    /// The time limit that elapsed.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Whether the limit was a deadline sent by the client, rather than the
+    /// filter's own timeout.
+    pub fn is_deadline(&self) -> bool {
+        self.from_deadline
+    }
+
+    fn status(&self) -> StatusCode {
+        if self.from_deadline {
+            StatusCode::GATEWAY_TIMEOUT
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request timed out after {:?}", self.duration)
+    }
+}
+impl StdError for TimedOut {}
+
+/// A route's [concurrency limit](crate::limit::concurrency) had no free slot
+#[derive(Debug)]
+pub struct Overloaded {
+    retry_after: Duration,
+}
+impl Overloaded {
+    /// How long the client is asked to wait before retrying.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    fn insert_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from(crate::limit::ceil_secs(self.retry_after)),
+        );
+    }
+}
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The service is overloaded, try again later")
+    }
+}
+impl StdError for Overloaded {}
+
+/// A client ran out of requests allowed by a [rate limit](crate::limit::rate)
+#[derive(Debug)]
+pub struct TooManyRequests {
+    limit: u32,
+    retry_after: Duration,
+    reset: Duration,
+}
+impl TooManyRequests {
+    /// The number of requests the limit allows in a burst.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// How long until the client may make another request.
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+
+    fn insert_headers(&self, headers: &mut HeaderMap) {
+        crate::limit::insert_rate_limit(headers, self.limit, 0, self.reset);
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from(crate::limit::ceil_secs(self.retry_after)),
+        );
+    }
+}
+impl fmt::Display for TooManyRequests {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Too many requests")
+    }
+}
+impl StdError for TooManyRequests {}
+
+/// A request lacked valid [credentials](crate::auth)
+#[derive(Debug)]
+pub struct Unauthorized {
+    scheme: &'static str,
+    realm: &'static str,
+    error: Option<(&'static str, &'static str)>,
+}
+impl Unauthorized {
+    pub(crate) fn new(
+        scheme: &'static str,
+        realm: &'static str,
+        error: Option<(&'static str, &'static str)>,
+    ) -> Unauthorized {
+        Unauthorized {
+            scheme,
+            realm,
+            error,
+        }
+    }
+
+    /// The authentication scheme expected, such as `Basic` or `Bearer`.
+    pub fn scheme(&self) -> &str {
+        self.scheme
+    }
+
+    /// The protection space the credentials were checked against.
+    pub fn realm(&self) -> &str {
+        self.realm
+    }
+
+    /// The error code, such as `invalid_token`, if credentials were sent.
+    pub fn error(&self) -> Option<&str> {
+        self.error.map(|(code, _)| code)
+    }
+
+    /// The `WWW-Authenticate` challenge sent with the default response.
+    pub fn challenge(&self) -> String {
+        let mut challenge = format!("{} realm={}", self.scheme, quote(self.realm));
+        match self.error {
+            Some((code, description)) => {
+                challenge.push_str(&format!(
+                    ", error={}, error_description={}",
+                    quote(code),
+                    quote(description),
+                ));
+            }
+            None if self.scheme == "Basic" => challenge.push_str(", charset=\"UTF-8\""),
+            None => (),
+        }
+        challenge
+    }
+
+    fn status(&self) -> StatusCode {
+        match self.error() {
+            Some("invalid_request") => StatusCode::BAD_REQUEST,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+impl fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            Some((_, description)) => f.write_str(description),
+            None => f.write_str("Authentication required"),
+        }
+    }
+}
+impl StdError for Unauthorized {}
+
+/// Format a `quoted-string`.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
 }
 unit_error! {
     #[doc = " A content-length header is required"] pub LengthRequired :
@@ -282,10 +734,27 @@ unit_error! {
     #[doc = " The request payload is too large"] pub PayloadTooLarge :
     "The request payload is too large"
 }
-unit_error! {
-    #[doc = " The request's content-type is not supported"] pub UnsupportedMediaType :
-    "The request's content-type is not supported"
+/// The request's content-type is not supported
+#[derive(Debug)]
+pub struct UnsupportedMediaType {
+    accepted: Vec<String>,
+}
+impl UnsupportedMediaType {
+    /// The content-types that would have been accepted, if known.
+    pub fn accepted(&self) -> &[String] {
+        &self.accepted
+    }
 }
+impl fmt::Display for UnsupportedMediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The request's content-type is not supported")?;
+        if !self.accepted.is_empty() {
+            write!(f, ", expected one of: {}", self.accepted.join(", "))?;
+        }
+        Ok(())
+    }
+}
+impl StdError for UnsupportedMediaType {}
 
 #[derive(Debug)]
 pub struct MissingHeader {
@@ -338,7 +807,7 @@ impl fmt::Display for MissingCookie {
 }
 impl StdError for MissingCookie {}
 mod sealed {
-    use super::Rejection;
+    use super::{Reason, Rejection, Rejections};
     use http::StatusCode;
     use std::convert::Infallible;
     use std::fmt;
@@ -373,7 +842,19 @@ mod sealed {
         type One = Rejection;
         type Combined = Rejection;
         fn combine(self, other: Rejection) -> Self::Combined {
-            loop {}
+            let reason = match (self.reason, other.reason) {
+                (Reason::Other(left), Reason::Other(right)) => {
+                    Reason::Other(Box::new(Rejections::Combined(left, right)))
+                }
+                (Reason::Other(other), Reason::NotFound)
+                | (Reason::NotFound, Reason::Other(other)) => {
+                    // ignore the NotFound
+                    Reason::Other(other)
+                }
+                (Reason::NotFound, Reason::NotFound) => Reason::NotFound,
+            };
+
+            Rejection { reason }
         }
     }
     impl CombineRejection<Infallible> for Rejection {
@@ -449,4 +930,26 @@ mod tests {
     fn test_debug() {
         loop {}
     }
+    #[test]
+    fn method_not_allowed_lists_allowed_methods() {
+        let rej = method_not_allowed(Method::GET)
+            .combine(method_not_allowed(Method::POST))
+            .combine(method_not_allowed(Method::GET));
+        assert_eq!(rej.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            rej.allowed_methods(),
+            vec![Method::GET, Method::POST, Method::GET]
+        );
+
+        let resp = rej.into_response();
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers()["allow"], "GET, POST");
+    }
+    #[test]
+    fn allow_prefers_other_rejections() {
+        let rej = method_not_allowed(Method::GET).combine(known(LengthRequired { _p: () }));
+        let resp = rej.into_response();
+        assert_eq!(resp.status(), StatusCode::LENGTH_REQUIRED);
+        assert!(!resp.headers().contains_key("allow"));
+    }
 }
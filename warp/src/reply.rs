@@ -36,8 +36,10 @@ use std::borrow::Cow;
 use std::convert::TryFrom;
 use std::error::Error as StdError;
 use std::fmt;
+use crate::filters::cookie::Cookie;
 use crate::generic::{Either, One};
-use http::header::{HeaderName, HeaderValue};
+use futures_util::{Stream, StreamExt};
+use http::header::{HeaderName, HeaderValue, CONTENT_TYPE, SET_COOKIE};
 use http::StatusCode;
 use hyper::Body;
 use serde::Serialize;
@@ -95,7 +97,11 @@ pub fn json<T>(val: &T) -> Json
 where
     T: Serialize,
 {
-    loop {}
+    Json {
+        inner: serde_json::to_vec(val).map_err(|err| {
+            tracing::error!("reply::json error: {}", err);
+        }),
+    }
 }
 
 #[allow(missing_debug_implementations)]
@@ -105,7 +111,15 @@ pub struct Json {
 impl Reply for Json {
     #[inline]
     fn into_response(self) -> Response {
-        loop {}
+        match self.inner {
+            Ok(body) => {
+                let mut res = Response::new(body.into());
+                res.headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                res
+            }
+            Err(()) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
     }
 }
 #[derive(Debug)]
@@ -117,6 +131,66 @@ impl fmt::Display for ReplyJsonError {
 }
 impl StdError for ReplyJsonError {}
 
+/// Reply with a stream of values serialized as JSON Lines (NDJSON).
+///
+/// Each item is serialized onto its own line and sent as its own chunk, so
+/// clients see every value as soon as the stream yields it. The response
+/// has the `content-type: application/x-ndjson` header.
+///
+/// If an item fails to serialize, the response body is aborted.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+///
+/// let route = warp::path("events").map(|| {
+///     let events = futures_util::stream::iter(vec![
+///         serde_json::json!({ "kind": "started" }),
+///         serde_json::json!({ "kind": "finished" }),
+///     ]);
+///     warp::reply::json_lines(events)
+/// });
+/// ```
+pub fn json_lines<S>(stream: S) -> JsonLines<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    JsonLines { stream }
+}
+
+/// A JSON Lines formatted reply.
+///
+/// Created with [`json_lines`].
+#[allow(missing_debug_implementations)]
+pub struct JsonLines<S> {
+    stream: S,
+}
+
+impl<S> Reply for JsonLines<S>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    fn into_response(self) -> Response {
+        let lines = self.stream.map(|item| {
+            let mut line = serde_json::to_vec(&item).map_err(|err| {
+                tracing::error!("reply::json_lines error: {}", err);
+                ReplyJsonError
+            })?;
+            line.push(b'\n');
+            Ok::<_, ReplyJsonError>(line)
+        });
+        let mut res = Response::new(Body::wrap_stream(lines));
+        res.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-ndjson"),
+        );
+        res
+    }
+}
+
 
 
 
@@ -269,19 +343,68 @@ impl<T: Reply> Reply for WithHeader<T> {
         loop {}
     }
 }
+/// Wrap an `impl Reply` to add a `Set-Cookie` header.
+///
+/// Each cookie gets its own `Set-Cookie` header, so this can be nested to
+/// set several cookies. Cookies that can't be encoded, such as with a `;`
+/// in their value, are skipped and logged.
+///
+/// # Example
+///
+/// ```
+/// use warp::Filter;
+/// use warp::cookie::Cookie;
+///
+/// let route = warp::path("logout").map(|| {
+///     let reply = warp::reply::with_cookie(warp::reply(), Cookie::removal("session"));
+///     warp::reply::with_cookie(reply, Cookie::new("logged-out", "1").path("/"))
+/// });
+/// ```
+pub fn with_cookie<T: Reply>(reply: T, cookie: Cookie) -> WithCookie<T> {
+    let cookie = match cookie.to_header_value() {
+        Some(value) => Some(value),
+        None => {
+            tracing::error!("with_cookie: invalid cookie {:?}", cookie.name());
+            None
+        }
+    };
+    WithCookie { cookie, reply }
+}
+
+/// Wrap an `impl Reply` to add a `Set-Cookie` header.
+///
+/// Returned by `reply::with_cookie`.
+#[derive(Debug)]
+pub struct WithCookie<T> {
+    cookie: Option<HeaderValue>,
+    reply: T,
+}
+
+impl<T: Reply> Reply for WithCookie<T> {
+    fn into_response(self) -> Response {
+        let mut res = self.reply.into_response();
+        if let Some(cookie) = self.cookie {
+            res.headers_mut().append(SET_COOKIE, cookie);
+        }
+        res
+    }
+}
+
 impl<T: Send> Reply for ::http::Response<T>
 where
     Body: From<T>,
 {
     #[inline]
     fn into_response(self) -> Response {
-        loop {}
+        self.map(Body::from)
     }
 }
 impl Reply for ::http::StatusCode {
     #[inline]
     fn into_response(self) -> Response {
-        loop {}
+        let mut res = Response::default();
+        *res.status_mut() = self;
+        res
     }
 }
 impl<T> Reply for Result<T, ::http::Error>
@@ -330,7 +453,10 @@ where
 {
     #[inline]
     fn into_response(self) -> Response {
-        loop {}
+        match self {
+            Either::A(a) => a.into_response(),
+            Either::B(b) => b.into_response(),
+        }
     }
 }
 impl<T> Reply for One<T>
@@ -339,7 +465,7 @@ where
 {
     #[inline]
     fn into_response(self) -> Response {
-        loop {}
+        self.0.into_response()
     }
 }
 impl Reply for std::convert::Infallible {
@@ -385,4 +511,15 @@ mod tests {
     fn boxed_reply() {
         loop {}
     }
+    #[tokio::test]
+    async fn json_lines_reply() {
+        let items = futures_util::stream::iter(vec![
+            serde_json::json!({ "a": 1 }),
+            serde_json::json!([2]),
+        ]);
+        let res = json_lines(items).into_response();
+        assert_eq!(res.headers()["content-type"], "application/x-ndjson");
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "{\"a\":1}\n[2]\n");
+    }
 }
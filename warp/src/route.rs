@@ -9,16 +9,16 @@ pub(crate) fn set<F, U>(r: &RefCell<Route>, func: F) -> U
 where
     F: FnOnce() -> U,
 {
-    loop {}
+    ROUTE.set(r, func)
 }
 pub(crate) fn is_set() -> bool {
-    loop {}
+    ROUTE.is_set()
 }
 pub(crate) fn with<F, R>(func: F) -> R
 where
     F: FnOnce(&mut Route) -> R,
 {
-    loop {}
+    ROUTE.with(move |route| func(&mut route.borrow_mut()))
 }
 #[derive(Debug)]
 pub(crate) struct Route {
@@ -34,49 +34,78 @@ enum BodyState {
 }
 impl Route {
     pub(crate) fn new(req: Request, remote_addr: Option<SocketAddr>) -> RefCell<Route> {
-        loop {}
+        let idx = req.uri().path().len() - req.uri().path().trim_start_matches('/').len();
+        RefCell::new(Route {
+            body: BodyState::Ready,
+            remote_addr,
+            req,
+            segments_index: idx,
+        })
     }
     pub(crate) fn method(&self) -> &http::Method {
-        loop {}
+        self.req.method()
     }
     pub(crate) fn headers(&self) -> &http::HeaderMap {
-        loop {}
+        self.req.headers()
+    }
+    pub(crate) fn headers_mut(&mut self) -> &mut http::HeaderMap {
+        self.req.headers_mut()
     }
     pub(crate) fn version(&self) -> http::Version {
-        loop {}
+        self.req.version()
     }
     pub(crate) fn extensions(&self) -> &http::Extensions {
-        loop {}
+        self.req.extensions()
     }
-    #[cfg(feature = "websocket")]
     pub(crate) fn extensions_mut(&mut self) -> &mut http::Extensions {
-        loop {}
+        self.req.extensions_mut()
     }
     pub(crate) fn uri(&self) -> &http::Uri {
-        loop {}
+        self.req.uri()
     }
     pub(crate) fn path(&self) -> &str {
-        loop {}
+        &self.req.uri().path()[self.segments_index..]
     }
     pub(crate) fn full_path(&self) -> &str {
-        loop {}
+        self.req.uri().path()
     }
     pub(crate) fn set_unmatched_path(&mut self, index: usize) {
-        loop {}
+        let index = self.segments_index + index;
+        let path = self.req.uri().path();
+        if path.is_empty() {
+            // malformed path
+            return;
+        } else if path.len() == index {
+            self.segments_index = index;
+        } else {
+            debug_assert_eq!(path.as_bytes()[index], b'/');
+            self.segments_index = index + 1;
+        }
     }
     pub(crate) fn query(&self) -> Option<&str> {
-        loop {}
+        self.req.uri().query()
     }
     pub(crate) fn matched_path_index(&self) -> usize {
-        loop {}
+        self.segments_index
     }
     pub(crate) fn reset_matched_path_index(&mut self, index: usize) {
-        loop {}
+        self.segments_index = index;
     }
     pub(crate) fn remote_addr(&self) -> Option<SocketAddr> {
-        loop {}
+        self.remote_addr
     }
     pub(crate) fn take_body(&mut self) -> Option<Body> {
-        loop {}
+        match self.body {
+            BodyState::Ready => {
+                let body = std::mem::replace(self.req.body_mut(), Body::empty());
+                self.body = BodyState::Taken;
+                Some(body)
+            }
+            BodyState::Taken => None,
+        }
+    }
+    pub(crate) fn set_body(&mut self, body: Body) {
+        *self.req.body_mut() = body;
+        self.body = BodyState::Ready;
     }
 }